@@ -0,0 +1,905 @@
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Error, Result};
+
+// Local module imports
+use crate::{
+    assets::{self, store_dir, Pointer},
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError},
+    },
+    config::{ConflictPolicy, ProfileConfig, Strategy, TomlConfig},
+    externals::{self, ExternalState},
+    history::{patch, PatchLine},
+    journal::{Change, Journal, Planned},
+    manifest::TrackedFile,
+    mappings,
+    repository::sync::checkout_branch,
+};
+
+/// What applying a single entry did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Nothing existed at the target path.
+    Created,
+    /// The target path held something else, which was replaced.
+    Updated,
+    /// The target path already matched the profile.
+    Unchanged,
+    /// The target path held something else, which was kept.
+    Skipped,
+}
+
+/// What an `apply` did, listing the target paths of every entry.
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    pub created: Vec<PathBuf>,
+    pub updated: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    /// Entries that could not be applied, with the reason.
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl ApplyReport {
+    fn record(&mut self, target: PathBuf, result: Result<Outcome>) {
+        match result {
+            Ok(Outcome::Created) => self.created.push(target),
+            Ok(Outcome::Updated) => self.updated.push(target),
+            Ok(Outcome::Unchanged) => self.unchanged.push(target),
+            Ok(Outcome::Skipped) => self.skipped.push(target),
+            Err(error) => self.failed.push((target, error)),
+        }
+    }
+}
+
+/// Places the active profile's files and externals at their locations on disk.
+///
+/// Every tracked file is deployed to its source path with its strategy:
+/// - `symlink` and `hardlink` link to the file in the repository, so the
+///   profile's branch is checked out first.
+/// - `copy` writes the contents committed on the profile's branch. Files kept
+///   in the asset store are always copied.
+///
+/// Existing files that are not already linked to the repository are handled
+/// by `policy`, or the profile's conflict policy: they are backed up before
+/// being replaced, kept, replaced, or `ask` is called with the differences to
+/// choose one of these. Symbolic links are replaced rather than written
+/// through. Every file then gets the mode recorded in the manifest, which for a
+/// link is set on the file in the repository. Externals are cloned or moved to
+/// their pinned commits.
+///
+/// Files are deployed all or nothing. Every file is checked, and every question
+/// asked, before anything is changed; when a file cannot be applied, only the
+/// failures are reported. The changes are then made through a `Journal`, so
+/// that a failure puts back the files already replaced. Externals are deployed
+/// afterwards, and a failing external does not stop the others.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the base path and active profile.
+/// * `home` - The user's home directory, where externals are checked out.
+/// * `fs` - An implementation of `FileSystem` for writing the files.
+/// * `git` - An implementation of `Git` for reading the profile's branch.
+/// * `policy` - The conflict policy for this run, instead of the profile's.
+/// * `ask` - Chooses what to do with an existing file, given its path and the changes.
+///
+/// # Returns
+/// Returns an `ApplyReport` describing every entry.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile.
+/// - Nothing has been committed on the profile's branch yet.
+/// - `externals.toml` on the branch cannot be parsed.
+/// - An earlier apply did not finish.
+/// - The mode of a file cannot be read.
+/// - A file or its mode cannot be replaced, after the files already replaced
+///   were put back.
+///
+pub async fn apply(
+    config: &TomlConfig,
+    home: &Path,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+    policy: Option<ConflictPolicy>,
+    ask: &mut dyn FnMut(&Path, &[PatchLine]) -> Result<ConflictPolicy>,
+) -> Result<ApplyReport> {
+    // Checked before anything changes, including the branch that is checked out.
+    if Journal::pending(fs, config.state_dir()).await?.is_some() {
+        bail!("An earlier `dotty apply` did not finish. Run `dotty apply --resume` or `dotty apply --roll-back` first.");
+    }
+
+    let repo = &config.base_path;
+    let profile = config.active_profile()?;
+
+    let externals = match externals::committed(repo, &profile.branch, git) {
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            bail!(
+                "Nothing has been committed on branch '{}' yet, so there is nothing to apply.",
+                profile.branch
+            );
+        }
+        result => result?,
+    };
+
+    let branch = git.current_branch(repo)?;
+    if profile
+        .files
+        .iter()
+        .any(|file| profile.strategy_for(file) != Strategy::Copy)
+    {
+        checkout_branch(git, repo, &profile.branch)?;
+    }
+
+    let mut report = ApplyReport::default();
+    let mut planned = Vec::new();
+    for file in &profile.files {
+        let policy = policy.unwrap_or(profile.conflict);
+        let result = plan_file(config, profile, file, fs, git, policy, ask).await;
+        let result = result.map(|(outcome, plans)| {
+            planned.extend(plans);
+            outcome
+        });
+        report.record(file.source.clone(), result);
+    }
+
+    if !report.failed.is_empty() {
+        return Ok(ApplyReport {
+            failed: report.failed,
+            ..ApplyReport::default()
+        });
+    }
+
+    Journal::begin(
+        fs,
+        config.state_dir(),
+        &config.active_profile,
+        branch,
+        planned,
+    )
+    .await?
+    .complete(fs)
+    .await?;
+
+    for external in &externals.externals {
+        let result = externals::deploy(external, home, fs, git)
+            .await
+            .map(|state| match state {
+                ExternalState::Cloned => Outcome::Created,
+                ExternalState::Updated => Outcome::Updated,
+                ExternalState::Unchanged => Outcome::Unchanged,
+            });
+        report.record(external.destination(home), result);
+    }
+
+    Ok(report)
+}
+
+/// Moves the active profile's files to where its mappings place them on this machine.
+///
+/// The mappings committed on the profile's branch are used, so a new rule or a
+/// different XDG directory moves the files before they are applied.
+///
+/// # Returns
+/// Returns the old and new location of every file that moved.
+///
+/// # Errors
+/// This function will return an error if there is no active profile or the
+/// mappings cannot be read.
+///
+pub fn relocate(
+    config: &mut TomlConfig,
+    home: &Path,
+    git: &dyn Git,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let repo = config.base_path.clone();
+    let profile = config.active_profile_mut()?;
+    let mappings = mappings::committed(&repo, &profile.branch, git)?;
+
+    Ok(profile.relocate(&mappings, home))
+}
+
+/// Decides what applying `file` does, asking about an existing file if needed.
+///
+/// Nothing is changed; a file that needs replacing, or a mode that needs
+/// setting, is returned as a `Planned` step.
+async fn plan_file(
+    config: &TomlConfig,
+    profile: &ProfileConfig,
+    file: &TrackedFile,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+    policy: ConflictPolicy,
+    ask: &mut dyn FnMut(&Path, &[PatchLine]) -> Result<ConflictPolicy>,
+) -> Result<(Outcome, Vec<Planned>)> {
+    let target = &file.source;
+    let linked = config.base_path.join(&file.repo_path);
+    let Some(committed) = git.show_file(&config.base_path, &profile.branch, &file.repo_path)?
+    else {
+        bail!("It has not been committed yet. Run `dotty sync` first.");
+    };
+
+    let mut strategy = profile.strategy_for(file);
+    if strategy != Strategy::Copy && Pointer::parse(&committed).is_some() {
+        log::info!(
+            "{} is in the asset store, so it is copied",
+            target.display()
+        );
+        strategy = Strategy::Copy;
+    }
+
+    let contents = match strategy {
+        Strategy::Copy => assets::resolve(fs, &store_dir(config), committed).await?,
+        _ if !fs.exists(&linked) => bail!("{} is missing from the repository.", linked.display()),
+        _ => Vec::new(),
+    };
+
+    let is_symlink = fs.is_symlink(target);
+    let unchanged = match strategy {
+        Strategy::Symlink => is_symlink && fs.read_link(target).await? == linked,
+        Strategy::Hardlink => !is_symlink && fs.is_same_file(target, &linked),
+        Strategy::Copy => {
+            !is_symlink
+                && fs.exists(target)
+                && !fs.is_dir(target)
+                && !fs.is_same_file(target, &linked)
+                && fs.read(target).await? == contents
+        }
+    };
+    // A link shares the mode of the file in the repository, so that is the one set.
+    let mode_target = match strategy {
+        Strategy::Copy => target,
+        _ => &linked,
+    };
+    if unchanged {
+        return Ok(match plan_mode(fs, file, mode_target).await? {
+            Some(plan) => (Outcome::Updated, vec![plan]),
+            None => (Outcome::Unchanged, Vec::new()),
+        });
+    }
+
+    let mut backup = false;
+    let outcome = if !is_symlink && fs.is_dir(target) {
+        bail!("A directory is in the way.");
+    } else if is_symlink || fs.exists(target) {
+        // A file linked to the repository holds nothing that is not kept there already.
+        if !fs.is_same_file(target, &linked) {
+            let policy = match policy {
+                ConflictPolicy::Ask => {
+                    let after = match strategy {
+                        Strategy::Copy => contents.clone(),
+                        _ => fs.read(&linked).await?,
+                    };
+                    ask(target, &changes(fs, target, &file.repo_path, &after).await)?
+                }
+                policy => policy,
+            };
+
+            match policy {
+                ConflictPolicy::Backup => backup = true,
+                ConflictPolicy::Overwrite => {}
+                ConflictPolicy::Skip | ConflictPolicy::Ask => {
+                    return Ok((Outcome::Skipped, Vec::new()))
+                }
+            }
+        }
+        Outcome::Updated
+    } else {
+        Outcome::Created
+    };
+
+    // A copy is written afresh, so it needs its mode whatever the old file had.
+    let mode = match (strategy, file.mode) {
+        (Strategy::Copy, Some(mode)) => Some(Planned {
+            target: target.clone(),
+            change: Change::Mode(mode),
+            backup: false,
+        }),
+        _ => plan_mode(fs, file, mode_target).await?,
+    };
+    let change = match strategy {
+        Strategy::Symlink => Change::Symlink(linked),
+        Strategy::Hardlink => Change::Hardlink(linked),
+        Strategy::Copy => Change::Copy(contents),
+    };
+    let mut plans = vec![Planned {
+        target: target.clone(),
+        change,
+        backup,
+    }];
+    plans.extend(mode);
+
+    Ok((outcome, plans))
+}
+
+/// Returns a step giving `path` the mode recorded for `file`, unless it has it already.
+async fn plan_mode(
+    fs: &impl FileSystem,
+    file: &TrackedFile,
+    path: &Path,
+) -> Result<Option<Planned>> {
+    let Some(mode) = file.mode else {
+        return Ok(None);
+    };
+    if fs.metadata(path).await?.mode == mode {
+        return Ok(None);
+    }
+
+    Ok(Some(Planned {
+        target: path.to_path_buf(),
+        change: Change::Mode(mode),
+        backup: false,
+    }))
+}
+
+/// Returns the changes deploying `after` would make to the file at `target`.
+async fn changes(
+    fs: &impl FileSystem,
+    target: &Path,
+    repo_path: &Path,
+    after: &[u8],
+) -> Vec<PatchLine> {
+    // A dangling link reads as empty.
+    let before = fs.read(target).await.unwrap_or_default();
+    let old = target.display().to_string();
+    let new = repo_path.display().to_string();
+
+    match (std::str::from_utf8(&before), std::str::from_utf8(after)) {
+        (Ok(before), Ok(after)) => patch(&old, &new, before, after),
+        _ => vec![PatchLine::Header(format!(
+            "Binary files {} and {} differ",
+            old, new
+        ))],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mappings::Mappings;
+    use crate::{
+        backup::{self, BACKUP_DIR},
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::{ConfigLoaderClient, ProfileConfig},
+        journal::undo,
+        mappings::MAPPINGS_FILE,
+        repository::{init, sync::sync},
+    };
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn never_ask(_: &Path, _: &[PatchLine]) -> Result<ConflictPolicy> {
+        panic!("Only the `ask` policy asks.");
+    }
+
+    /// Creates a repository with `.zshrc` and `.vimrc` committed from `home`.
+    async fn repository(root: &Path, home: &Path, strategy: Strategy) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path: root.join("dotty"),
+            active_profile: "default".to_string(),
+            ..TomlConfig::default()
+        };
+        config.profiles.insert(
+            "default".to_string(),
+            ProfileConfig {
+                strategy,
+                ..ProfileConfig::default()
+            },
+        );
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        fs::create_dir_all(home).unwrap();
+        for (name, contents) in [(".zshrc", "export EDITOR=nvim"), (".vimrc", "set number")] {
+            fs::write(home.join(name), contents).unwrap();
+            config
+                .active_profile_mut()
+                .unwrap()
+                .track(
+                    &FileSystemClient,
+                    &home.join(name),
+                    home,
+                    &Mappings::default(),
+                )
+                .await
+                .unwrap();
+        }
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        config
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+
+        fs::remove_file(home.join(".zshrc")).unwrap();
+        fs::write(home.join(".vimrc"), "set relativenumber").unwrap();
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.created, vec![home.join(".zshrc")]);
+        assert_eq!(report.updated, vec![home.join(".vimrc")]);
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            fs::read_to_string(home.join(".zshrc")).unwrap(),
+            "export EDITOR=nvim"
+        );
+        assert_eq!(
+            fs::read_to_string(home.join(".vimrc")).unwrap(),
+            "set number"
+        );
+        assert!(config.base_path.join(BACKUP_DIR).exists());
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.unchanged.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_reports_failures() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home, Strategy::Symlink).await;
+
+        fs::remove_file(home.join(".vimrc")).unwrap();
+        fs::create_dir(home.join(".vimrc")).unwrap();
+        fs::remove_file(home.join(".zshrc")).unwrap();
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, home.join(".vimrc"));
+        // Nothing is changed unless every file can be applied.
+        assert!(report.created.is_empty());
+        assert!(!home.join(".zshrc").exists());
+    }
+
+    #[tokio::test]
+    async fn test_apply_rolls_back() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+
+        // The second file can only fail once Dotty tries to write it.
+        fs::write(home.join(".zshrc"), "export EDITOR=nano").unwrap();
+        fs::write(home.join("blocker"), "").unwrap();
+        config.active_profile_mut().unwrap().files[1].source = home.join("blocker/.vimrc");
+
+        let error = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("put back"));
+        assert_eq!(
+            fs::read_to_string(home.join(".zshrc")).unwrap(),
+            "export EDITOR=nano"
+        );
+        assert!(Journal::pending(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_replaces_symlinks() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+
+        let elsewhere = temp_dir.path().join("zshrc");
+        fs::write(&elsewhere, "export EDITOR=helix").unwrap();
+        fs::remove_file(home.join(".zshrc")).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, home.join(".zshrc")).unwrap();
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.updated, vec![home.join(".zshrc")]);
+        assert!(!home.join(".zshrc").is_symlink());
+        assert_eq!(
+            fs::read_to_string(&elsewhere).unwrap(),
+            "export EDITOR=helix"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_links() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Symlink).await;
+        let repo = config.base_path.clone();
+        config.active_profile_mut().unwrap().files[1].strategy = Some(Strategy::Hardlink);
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.updated.len(), 2);
+        assert_eq!(
+            fs::read_link(home.join(".zshrc")).unwrap(),
+            repo.join("home/.zshrc")
+        );
+        assert!(FileSystemClient.is_same_file(&home.join(".vimrc"), &repo.join("home/.vimrc")));
+
+        // Editing through the link is committed by a sync without losing the contents.
+        fs::write(home.join(".zshrc"), "export EDITOR=helix").unwrap();
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+        assert_eq!(
+            GitClient
+                .show_file(&repo, "main", Path::new("home/.zshrc"))
+                .unwrap()
+                .unwrap(),
+            b"export EDITOR=helix"
+        );
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.unchanged.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_mappings() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+        let repo = config.base_path.clone();
+
+        fs::write(repo.join(MAPPINGS_FILE), r#""zsh/" = "~/.zsh/""#).unwrap();
+        fs::create_dir_all(home.join(".zsh")).unwrap();
+        fs::write(home.join(".zsh/aliases.zsh"), "alias g=git").unwrap();
+        let rules = mappings::load(&FileSystemClient, &repo).await.unwrap();
+        config
+            .active_profile_mut()
+            .unwrap()
+            .track(&FileSystemClient, &home.join(".zsh"), &home, &rules)
+            .await
+            .unwrap();
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        assert!(GitClient
+            .show_file(&repo, "main", Path::new("zsh/aliases.zsh"))
+            .unwrap()
+            .is_some());
+        assert!(relocate(&mut config, &home, &GitClient).unwrap().is_empty());
+
+        // A new rule moves the file when it is applied.
+        fs::write(repo.join(MAPPINGS_FILE), r#""zsh/" = "~/.config/zsh/""#).unwrap();
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+        let moved = relocate(&mut config, &home, &GitClient).unwrap();
+        assert_eq!(
+            moved,
+            vec![(
+                home.join(".zsh/aliases.zsh"),
+                home.join(".config/zsh/aliases.zsh")
+            )]
+        );
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.created, vec![home.join(".config/zsh/aliases.zsh")]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_conflicts() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+        let vimrc = home.join(".vimrc");
+        fs::write(&vimrc, "set relativenumber").unwrap();
+
+        for policy in [ConflictPolicy::Skip, ConflictPolicy::Ask] {
+            let mut asked = Vec::new();
+            let report = apply(
+                &config,
+                &home,
+                &FileSystemClient,
+                &GitClient,
+                Some(policy),
+                &mut |target, changes| {
+                    asked.push((target.to_path_buf(), changes.to_vec()));
+                    Ok(ConflictPolicy::Skip)
+                },
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(report.skipped, vec![vimrc.clone()]);
+            assert_eq!(fs::read_to_string(&vimrc).unwrap(), "set relativenumber");
+            if policy == ConflictPolicy::Ask {
+                assert_eq!(asked.len(), 1);
+                assert_eq!(asked[0].0, vimrc);
+                assert!(asked[0]
+                    .1
+                    .contains(&PatchLine::Added("+set number".to_string())));
+            } else {
+                assert!(asked.is_empty());
+            }
+        }
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            Some(ConflictPolicy::Overwrite),
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.updated, vec![vimrc.clone()]);
+        assert_eq!(fs::read_to_string(&vimrc).unwrap(), "set number");
+        assert!(backup::list(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The profile's policy backs the file up, and the backup can be restored.
+        fs::write(&vimrc, "set relativenumber").unwrap();
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.updated, vec![vimrc.clone()]);
+        let backups = backup::list(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].source, vimrc);
+
+        backup::restore(&FileSystemClient, config.state_dir(), 1)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&vimrc).unwrap(), "set relativenumber");
+    }
+
+    #[tokio::test]
+    async fn test_apply_undo() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+        fs::write(home.join(".vimrc"), "set relativenumber").unwrap();
+
+        apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            Some(ConflictPolicy::Overwrite),
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+        let snapshots = Journal::snapshots(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].profile, "default");
+        assert_eq!(snapshots[0].targets().count(), 1);
+
+        // An apply that changes nothing leaves no snapshot.
+        apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+
+        let undone = undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(undone.len(), 1);
+        assert_eq!(
+            fs::read_to_string(home.join(".vimrc")).unwrap(),
+            "set relativenumber"
+        );
+        assert!(undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            1
+        )
+        .await
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_modes() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Copy).await;
+        let vimrc = home.join(".vimrc");
+        FileSystemClient
+            .set_permissions(&vimrc, 0o644)
+            .await
+            .unwrap();
+        for file in &mut config.active_profile_mut().unwrap().files {
+            file.mode = Some(if file.source == vimrc { 0o600 } else { 0o644 });
+        }
+
+        let report = apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+
+        // The contents match, so only the mode changed.
+        assert_eq!(report.updated, vec![home.join(".vimrc")]);
+        assert_eq!(report.unchanged, vec![home.join(".zshrc")]);
+        assert_eq!(FileSystemClient.metadata(&vimrc).await.unwrap().mode, 0o600);
+
+        // The mode was changed through the journal, so it can be undone.
+        undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(FileSystemClient.metadata(&vimrc).await.unwrap().mode, 0o644);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_modes_of_links() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = repository(temp_dir.path(), &home, Strategy::Symlink).await;
+        for file in &mut config.active_profile_mut().unwrap().files {
+            file.mode = Some(0o600);
+        }
+        let linked = config
+            .base_path
+            .join(&config.active_profile().unwrap().files[1].repo_path);
+
+        apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .unwrap();
+
+        // The mode is set on the file in the repository, which the link points to.
+        assert!(FileSystemClient.is_symlink(&home.join(".vimrc")));
+        assert_eq!(
+            FileSystemClient.metadata(&linked).await.unwrap().mode,
+            0o600
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_pending() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home, Strategy::Symlink).await;
+        GitClient
+            .create_branch(&config.base_path, "other", "HEAD")
+            .unwrap();
+        GitClient.checkout(&config.base_path, "other").unwrap();
+
+        let planned = vec![Planned {
+            target: home.join(".zshrc"),
+            change: Change::Copy(b"export EDITOR=nano".to_vec()),
+            backup: false,
+        }];
+        Journal::begin(
+            &FileSystemClient,
+            config.state_dir(),
+            "default",
+            None,
+            planned,
+        )
+        .await
+        .unwrap();
+
+        assert!(apply(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            None,
+            &mut never_ask,
+        )
+        .await
+        .is_err());
+        // The unfinished apply is found before the profile's branch is checked out.
+        assert_eq!(
+            GitClient.current_branch(&config.base_path).unwrap(),
+            Some("other".to_string())
+        );
+    }
+}
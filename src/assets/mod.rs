@@ -0,0 +1,227 @@
+//! Content-addressed storage for files too large for the Git history.
+//!
+//! A tracked file over `assets.size_limit` is copied into the asset store under
+//! its SHA-256 hash, and the repository only holds a small pointer naming that
+//! hash. Pointers are resolved back to the real contents whenever a file is
+//! written to its location on disk.
+
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+
+// Local module imports
+use crate::{clients::file_system::FileSystem, config::TomlConfig};
+
+/// First line of every pointer file.
+pub const POINTER_HEADER: &str = "dotty-asset v1";
+
+/// Directory inside the base path used as the store when none is configured.
+pub const ASSET_DIR: &str = "assets";
+
+/// The stand-in committed for a large file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer {
+    /// The SHA-256 hash of the contents, in lowercase hex.
+    pub hash: String,
+    pub size: u64,
+}
+
+impl Pointer {
+    /// Returns the pointer for `data`.
+    pub fn for_data(data: &[u8]) -> Self {
+        Self {
+            hash: format!("{:x}", Sha256::digest(data)),
+            size: data.len() as u64,
+        }
+    }
+
+    /// Reads a pointer file, returning `None` when `data` is not one.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(data).ok()?;
+        let mut lines = text.lines();
+
+        if lines.next()? != POINTER_HEADER {
+            return None;
+        }
+
+        let hash = lines.next()?.strip_prefix("sha256 ")?;
+        let size = lines.next()?.strip_prefix("size ")?.parse().ok()?;
+
+        Some(Self {
+            hash: hash.to_string(),
+            size,
+        })
+    }
+
+    /// Returns the contents of the pointer file.
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}\nsha256 {}\nsize {}\n",
+            POINTER_HEADER, self.hash, self.size
+        )
+    }
+}
+
+/// Returns the asset store of `config`.
+pub fn store_dir(config: &TomlConfig) -> PathBuf {
+    config
+        .assets
+        .store
+        .clone()
+        .unwrap_or_else(|| config.base_path.join(ASSET_DIR))
+}
+
+fn object_path(store: &Path, hash: &str) -> PathBuf {
+    store.join(&hash[..2]).join(&hash[2..])
+}
+
+/// Copies `source` to `target` in the repository, moving it to the store if it is too large.
+///
+/// Nothing is copied when `source` is a link to `target`, as the repository
+/// already holds its contents.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the size limit and store.
+/// * `fs` - An implementation of `FileSystem` for copying the file.
+/// * `source` - The tracked file on disk.
+/// * `target` - Where the file, or its pointer, is written in the repository.
+///
+/// # Errors
+/// This function will return an error if the file cannot be read or written.
+///
+pub async fn stage_file(
+    config: &TomlConfig,
+    fs: &impl FileSystem,
+    source: &Path,
+    target: &Path,
+) -> Result<()> {
+    if fs.is_same_file(source, target) {
+        return Ok(());
+    }
+
+    let data = fs.read(source).await?;
+
+    if data.len() as u64 <= config.assets.size_limit {
+        return fs.copy(source, target).await;
+    }
+
+    let pointer = Pointer::for_data(&data);
+    let object = object_path(&store_dir(config), &pointer.hash);
+    if !fs.exists(&object) {
+        fs.write_bytes(&object, &data).await?;
+        log::info!("Stored {} as asset {}", source.display(), pointer.hash);
+    }
+
+    fs.write(target, &pointer.to_text()).await
+}
+
+/// Returns the real contents of a committed file, fetching pointers from the store.
+///
+/// # Arguments
+/// * `fs` - An implementation of `FileSystem` for reading the store.
+/// * `store` - The asset store.
+/// * `data` - The committed contents, which are returned as they are unless they are a pointer.
+///
+/// # Errors
+/// This function will return an error if the asset is missing from the store,
+/// for example because the drive holding it is not mounted, or is damaged.
+///
+pub async fn resolve(fs: &impl FileSystem, store: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(pointer) = Pointer::parse(&data) else {
+        return Ok(data);
+    };
+
+    let object = object_path(store, &pointer.hash);
+    if !fs.exists(&object) {
+        bail!(
+            "Asset {} is not in the store at {}. If the store is on another drive, make sure it is mounted.",
+            pointer.hash,
+            store.display()
+        );
+    }
+
+    let contents = fs.read(&object).await?;
+    if Pointer::for_data(&contents) != pointer {
+        bail!("Asset {} in {} is damaged.", pointer.hash, store.display());
+    }
+
+    Ok(contents)
+}
+
+/// Returns whether `data` on disk matches the committed contents, which may be a pointer.
+pub fn matches(committed: &[u8], data: &[u8]) -> bool {
+    match Pointer::parse(committed) {
+        Some(pointer) => Pointer::for_data(data) == pointer,
+        None => committed == data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::file_system::FileSystemClient;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_stage_and_resolve() {
+        let temp_dir = tempdir().unwrap();
+        let config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            assets: crate::config::AssetConfig {
+                size_limit: 9,
+                store: Some(temp_dir.path().join("drive")),
+            },
+            ..TomlConfig::default()
+        };
+        let wallpaper = temp_dir.path().join("wallpaper.png");
+        let zshrc = temp_dir.path().join(".zshrc");
+        fs::write(&wallpaper, [0x89, b'P', b'N', b'G', 0, 1, 2, 3, 4, 5]).unwrap();
+        fs::write(&zshrc, "set -o vi").unwrap();
+
+        let target = config.base_path.join("home/wallpaper.png");
+        stage_file(&config, &FileSystemClient, &wallpaper, &target)
+            .await
+            .unwrap();
+        let pointer = fs::read(&target).unwrap();
+        assert_eq!(Pointer::parse(&pointer).unwrap().size, 10);
+        assert!(matches(&pointer, &fs::read(&wallpaper).unwrap()));
+
+        // Small files are copied as they are.
+        let small = config.base_path.join("home/.zshrc");
+        stage_file(&config, &FileSystemClient, &zshrc, &small)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&small).unwrap(), "set -o vi");
+
+        let store = store_dir(&config);
+        assert_eq!(
+            resolve(&FileSystemClient, &store, pointer.clone())
+                .await
+                .unwrap(),
+            fs::read(&wallpaper).unwrap()
+        );
+        assert_eq!(
+            resolve(&FileSystemClient, &store, b"set -o vi".to_vec())
+                .await
+                .unwrap(),
+            b"set -o vi"
+        );
+
+        // An unmounted store is reported rather than deploying the pointer.
+        fs::remove_dir_all(&store).unwrap();
+        assert!(resolve(&FileSystemClient, &store, pointer).await.is_err());
+    }
+
+    #[test]
+    fn test_pointer() {
+        let pointer = Pointer::for_data(b"wallpaper");
+
+        assert_eq!(Pointer::parse(pointer.to_text().as_bytes()), Some(pointer));
+        assert_eq!(Pointer::parse(b"dotty-asset v1\nsize 9\n"), None);
+        assert_eq!(Pointer::parse(b"export EDITOR=nvim"), None);
+    }
+}
@@ -0,0 +1,247 @@
+// Standard library imports
+use std::path::{Component, Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+// Local module imports
+use crate::clients::file_system::FileSystem;
+
+/// Directory inside the state directory that holds the backups, one directory per run.
+pub const BACKUP_DIR: &str = "backups";
+
+/// File inside `backups` recording every backup, oldest first.
+pub const INDEX_FILE: &str = "index.toml";
+
+/// A copy of a file that Dotty replaced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    /// Where the file was.
+    pub source: PathBuf,
+    /// Where the copy is kept.
+    pub location: PathBuf,
+    pub created: DateTime<Local>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default, rename = "backup")]
+    backups: Vec<Backup>,
+}
+
+/// Copies `source` into a new backup directory before Dotty overwrites it.
+///
+/// The backup keeps the full path of the file, so `/home/me/.zshrc` is saved as
+/// `backups/<timestamp>/home/me/.zshrc`, and is recorded in the index so that
+/// it can be listed and restored.
+///
+/// # Arguments
+/// * `fs` - An implementation of `FileSystem` for copying the file.
+/// * `state_path` - The directory containing `backups`, see `TomlConfig::state_dir`.
+/// * `source` - The file to back up.
+///
+/// # Returns
+/// Returns the location of the copy, or `None` when `source` does not exist.
+///
+/// # Errors
+/// This function will return an error if the file cannot be copied or the
+/// index cannot be updated.
+///
+pub async fn backup(
+    fs: &impl FileSystem,
+    state_path: &Path,
+    source: &Path,
+) -> Result<Option<PathBuf>> {
+    if !fs.exists(source) {
+        return Ok(None);
+    }
+
+    let relative: PathBuf = source
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    let created = Local::now();
+    let timestamp = created.format("%Y%m%dT%H%M%S").to_string();
+    let mut target = state_path.join(BACKUP_DIR).join(&timestamp).join(&relative);
+    // The same file may be backed up twice within a second.
+    for count in 1.. {
+        if !fs.exists(&target) {
+            break;
+        }
+        target = state_path
+            .join(BACKUP_DIR)
+            .join(format!("{}-{}", timestamp, count))
+            .join(&relative);
+    }
+
+    fs.copy(source, &target).await?;
+    log::info!("Backed up {} to {}", source.display(), target.display());
+
+    let mut index = read_index(fs, state_path).await?;
+    index.backups.push(Backup {
+        source: source.to_path_buf(),
+        location: target.clone(),
+        created,
+    });
+    fs.write(&index_path(state_path), &toml::to_string(&index)?)
+        .await?;
+
+    Ok(Some(target))
+}
+
+/// Lists the recorded backups, oldest first. Backups are numbered from 1 in this order.
+///
+/// # Errors
+/// This function will return an error if the index cannot be read or parsed.
+///
+pub async fn list(fs: &impl FileSystem, state_path: &Path) -> Result<Vec<Backup>> {
+    Ok(read_index(fs, state_path).await?.backups)
+}
+
+/// Copies the backup numbered `number` back to where the file was.
+///
+/// The file that is there now is backed up first. A symbolic link is removed
+/// instead, so that the backup is not written through it.
+///
+/// # Arguments
+/// * `fs` - An implementation of `FileSystem` for copying the file.
+/// * `state_path` - The directory containing `backups`.
+/// * `number` - The number of the backup, as listed by `list`.
+///
+/// # Returns
+/// Returns the restored backup, and the backup of the replaced file if there was one.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no backup with that number, or its copy is missing.
+/// - A directory is where the file was.
+/// - The file cannot be backed up or copied.
+///
+pub async fn restore(
+    fs: &impl FileSystem,
+    state_path: &Path,
+    number: usize,
+) -> Result<(Backup, Option<PathBuf>)> {
+    let backups = list(fs, state_path).await?;
+    let Some(entry) = number.checked_sub(1).and_then(|index| backups.get(index)) else {
+        bail!(
+            "There is no backup #{}. Run `dotty backups` to list them.",
+            number
+        );
+    };
+
+    if !fs.exists(&entry.location) {
+        bail!(
+            "The backup of {} is missing from {}.",
+            entry.source.display(),
+            entry.location.display()
+        );
+    }
+
+    let replaced = if fs.is_symlink(&entry.source) {
+        fs.remove_file(&entry.source).await?;
+        None
+    } else if fs.is_dir(&entry.source) {
+        bail!("A directory is in the way of {}.", entry.source.display());
+    } else {
+        backup(fs, state_path, &entry.source).await?
+    };
+
+    fs.copy(&entry.location, &entry.source).await?;
+    log::info!(
+        "Restored {} from {}",
+        entry.source.display(),
+        entry.location.display()
+    );
+
+    Ok((entry.clone(), replaced))
+}
+
+fn index_path(state_path: &Path) -> PathBuf {
+    state_path.join(BACKUP_DIR).join(INDEX_FILE)
+}
+
+async fn read_index(fs: &impl FileSystem, state_path: &Path) -> Result<Index> {
+    let path = index_path(state_path);
+    if !fs.exists(&path) {
+        return Ok(Index::default());
+    }
+
+    toml::from_str(&fs.read_to_string(&path).await?)
+        .with_context(|| format!("Unable to parse {}.", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::file_system::FileSystemClient;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_backup() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("home/.zshrc");
+        let base_path = temp_dir.path().join("dotty");
+
+        assert_eq!(
+            backup(&FileSystemClient, &base_path, &source)
+                .await
+                .unwrap(),
+            None
+        );
+
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "export EDITOR=nvim").unwrap();
+        let target = backup(&FileSystemClient, &base_path, &source)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(target.starts_with(base_path.join(BACKUP_DIR)));
+        assert!(target.ends_with(source.strip_prefix("/").unwrap()));
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "export EDITOR=nvim"
+        );
+
+        let backups = list(&FileSystemClient, &base_path).await.unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].source, source);
+        assert_eq!(backups[0].location, target);
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("home/.bashrc");
+        let base_path = temp_dir.path().join("dotty");
+
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "# Distribution default").unwrap();
+        backup(&FileSystemClient, &base_path, &source)
+            .await
+            .unwrap();
+        std::fs::write(&source, "# From the dotfiles").unwrap();
+
+        let (restored, replaced) = restore(&FileSystemClient, &base_path, 1).await.unwrap();
+        assert_eq!(restored.source, source);
+        assert_eq!(
+            std::fs::read_to_string(&source).unwrap(),
+            "# Distribution default"
+        );
+        assert_eq!(
+            std::fs::read_to_string(replaced.unwrap()).unwrap(),
+            "# From the dotfiles"
+        );
+
+        // The replaced file was recorded as well.
+        assert_eq!(list(&FileSystemClient, &base_path).await.unwrap().len(), 2);
+        for number in [0, 3] {
+            assert!(restore(&FileSystemClient, &base_path, number)
+                .await
+                .is_err());
+        }
+    }
+}
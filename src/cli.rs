@@ -1,5 +1,5 @@
 // Standard library imports
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 // External crate imports
 use anyhow::Result;
@@ -7,9 +7,29 @@ use clap::{Parser, Subcommand};
 
 // Local module imports
 use crate::{
-    clients::{file_system::FileSystem, git::Git},
+    clients::{
+        dry_run::{Operation, Plan},
+        file_system::FileSystem,
+        git::Git,
+    },
+    command::{
+        apply::{offer_recovery, ApplyCommand},
+        backups::BackupsCommand,
+        clone::CloneCommand,
+        externals::ExternalsCommand,
+        history::HistoryCommand,
+        init::InitCommand,
+        log::LogCommand,
+        push::PushCommand,
+        restore::RestoreCommand,
+        status::StatusCommand,
+        sync::SyncCommand,
+        track::TrackCommand,
+        undo::UndoCommand,
+        untrack::UntrackCommand,
+    },
     config::{command::Commands as ConfigCommands, ConfigLoader, TomlConfig},
-    ui::cli::style,
+    ui::cli::{paint, style, use_colour, ERROR, HEADER, LITERAL, PLACEHOLDER},
 };
 
 #[derive(Parser, Debug)]
@@ -19,12 +39,30 @@ use crate::{
 pub struct Cli {
     #[command(subcommand)]
     pub command: DottyCommands,
+
+    /// Print the file and Git changes the command would make, without making them.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum DottyCommands {
     #[command(subcommand)]
     Config(ConfigCommands),
+    Init(InitCommand),
+    Clone(CloneCommand),
+    Status(StatusCommand),
+    Sync(SyncCommand),
+    Apply(ApplyCommand),
+    Undo(UndoCommand),
+    History(HistoryCommand),
+    Log(LogCommand),
+    Restore(RestoreCommand),
+    Backups(BackupsCommand),
+    Push(PushCommand),
+    Externals(ExternalsCommand),
+    Track(TrackCommand),
+    Untrack(UntrackCommand),
 }
 
 impl DottyCommands {
@@ -32,7 +70,8 @@ impl DottyCommands {
     ///
     /// This function serves as a dispatcher for the various subcommands of Dotty.
     /// It matches on the enum variant of `DottyCommands` and calls the appropriate
-    /// `execute` method for the selected subcommand.
+    /// `execute` method for the selected subcommand. An apply that did not complete
+    /// is offered to be resumed or rolled back first.
     ///
     /// # Arguments
     /// * `self` - The `DottyCommands` enum instance representing the selected subcommand.
@@ -56,8 +95,88 @@ impl DottyCommands {
         loader: &impl ConfigLoader,
         git: Arc<dyn Git>,
     ) -> Result<()> {
+        // `dotty apply` can also be asked to resume or roll back, so it offers this itself.
+        if !matches!(self, Self::Apply(_)) {
+            offer_recovery(&config, fs).await?;
+        }
+
         match self {
             Self::Config(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Init(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Clone(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Status(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Sync(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Apply(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Undo(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::History(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Log(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Restore(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Backups(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Push(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Externals(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Track(cmd) => cmd.execute(config, fs, loader, git).await,
+            Self::Untrack(cmd) => cmd.execute(config, fs, loader, git).await,
         }
     }
 }
+
+/// Prints the changes recorded during a `--dry-run`, followed by how many there are of each kind.
+///
+/// Paths inside the home directory are shown relative to it.
+///
+pub fn print_plan(plan: &Plan) {
+    let colour = use_colour();
+    let home = home::home_dir().unwrap_or_default();
+    let show = |path: &Path| match path.strip_prefix(&home) {
+        Ok(relative) if !home.as_os_str().is_empty() => format!("~/{}", relative.display()),
+        _ => path.display().to_string(),
+    };
+
+    let operations = plan.operations();
+    if !operations.is_empty() {
+        println!("{}", paint(HEADER, "Plan:", colour));
+    }
+    for operation in &operations {
+        let (label, style, detail) = match operation {
+            Operation::Create(path) => ("create", LITERAL, show(path)),
+            Operation::Replace(path) => ("replace", HEADER, show(path)),
+            Operation::Symlink { link, target } => (
+                "symlink",
+                LITERAL,
+                format!("{} -> {}", show(link), show(target)),
+            ),
+            Operation::Hardlink { link, target } => (
+                "hardlink",
+                LITERAL,
+                format!("{} => {}", show(link), show(target)),
+            ),
+            Operation::Delete(path) => ("delete", ERROR, show(path)),
+            Operation::Move { from, to } => (
+                "move",
+                PLACEHOLDER,
+                format!("{} -> {}", show(from), show(to)),
+            ),
+            Operation::SetPermissions { path, mode } => {
+                ("chmod", HEADER, format!("{} to {:o}", show(path), mode))
+            }
+            Operation::Git(description) => ("git", PLACEHOLDER, description.clone()),
+        };
+        println!(
+            "    {} {}",
+            paint(style, &format!("{:<8}", label), colour),
+            detail
+        );
+    }
+
+    let summary = plan.summary();
+    println!(
+        "Dry run: {} to create, {} to replace, {} to link, {} to delete, {} to move, {} modes to set and {} Git operations. Nothing was changed.",
+        summary.created,
+        summary.replaced,
+        summary.linked,
+        summary.deleted,
+        summary.moved,
+        summary.permissions,
+        summary.git
+    );
+}
@@ -0,0 +1,1049 @@
+//! Recording implementations of `FileSystem`, `Git` and `ConfigLoader` for `--dry-run`.
+//!
+//! Every command runs its usual code path against these. Reads go to the real
+//! file system and repository, while every change is recorded in a `Plan`
+//! instead of being made. Files written during the run are kept in memory, so
+//! that later steps read what a real run would have written. This includes the
+//! files a recorded checkout would have put in the working tree, and `HEAD`
+//! names the branch checked out during the run.
+
+// Standard library imports
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+// External crate imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::{FileSystem, Metadata},
+        git::{ChangeKind, CommitInfo, Git, GitError},
+    },
+    config::{ConfigLoader, ConfigLoaderClient, TomlConfig},
+};
+
+/// The commit ID returned for commits that were only recorded.
+pub const DRY_RUN_COMMIT: &str = "dry-run";
+
+/// A change that a real run would have made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Create(PathBuf),
+    Replace(PathBuf),
+    Symlink {
+        link: PathBuf,
+        target: PathBuf,
+    },
+    Hardlink {
+        link: PathBuf,
+        target: PathBuf,
+    },
+    Delete(PathBuf),
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    SetPermissions {
+        path: PathBuf,
+        mode: u32,
+    },
+    /// A Git operation, described for the user.
+    Git(String),
+}
+
+impl Operation {
+    /// Returns the paths the operation changes.
+    fn paths(&self) -> Vec<&Path> {
+        match self {
+            Operation::Create(path)
+            | Operation::Replace(path)
+            | Operation::Delete(path)
+            | Operation::SetPermissions { path, .. } => vec![path],
+            Operation::Symlink { link, .. } | Operation::Hardlink { link, .. } => vec![link],
+            Operation::Move { from, to } => vec![from, to],
+            Operation::Git(_) => Vec::new(),
+        }
+    }
+}
+
+/// How many operations of each kind a `Plan` holds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub created: usize,
+    pub replaced: usize,
+    pub linked: usize,
+    pub deleted: usize,
+    pub moved: usize,
+    pub permissions: usize,
+    pub git: usize,
+}
+
+/// What a path holds after the changes recorded so far.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    File(Vec<u8>),
+    Symlink(PathBuf),
+    Dir,
+    Removed,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    operations: Vec<Operation>,
+    nodes: BTreeMap<PathBuf, Node>,
+    /// The modes set on each path.
+    modes: BTreeMap<PathBuf, u32>,
+    /// Repositories created by `init` or `clone_repository`.
+    repositories: BTreeSet<PathBuf>,
+    /// The branch checked out in each repository.
+    branches: HashMap<PathBuf, String>,
+    /// The branches created in each repository, with the revision each starts at.
+    created: HashMap<(PathBuf, String), String>,
+    /// The paths added in each repository since its last commit.
+    added: HashMap<PathBuf, Vec<PathBuf>>,
+    /// The directories whose changes are left out of `operations`.
+    excluded: Vec<PathBuf>,
+}
+
+/// The changes recorded during a dry run.
+#[derive(Debug, Default)]
+pub struct Plan {
+    state: Mutex<State>,
+}
+
+impl Plan {
+    /// Leaves the changes made inside `dir`, such as Dotty's own state, out of
+    /// `operations` and `summary`. They are still recorded, so later reads see them.
+    pub fn exclude(&self, dir: &Path) {
+        self.lock().excluded.push(dir.to_path_buf());
+    }
+
+    /// Returns the recorded operations in the order they were made, except those
+    /// that only change paths inside excluded directories.
+    pub fn operations(&self) -> Vec<Operation> {
+        let state = self.lock();
+        let is_excluded = |operation: &Operation| {
+            let paths = operation.paths();
+            !paths.is_empty()
+                && paths
+                    .iter()
+                    .all(|path| state.excluded.iter().any(|dir| path.starts_with(dir)))
+        };
+
+        state
+            .operations
+            .iter()
+            .filter(|operation| !is_excluded(operation))
+            .cloned()
+            .collect()
+    }
+
+    /// Counts the recorded operations by kind.
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for operation in self.operations() {
+            match operation {
+                Operation::Create(_) => summary.created += 1,
+                Operation::Replace(_) => summary.replaced += 1,
+                Operation::Symlink { .. } | Operation::Hardlink { .. } => summary.linked += 1,
+                Operation::Delete(_) => summary.deleted += 1,
+                Operation::Move { .. } => summary.moved += 1,
+                Operation::SetPermissions { .. } => summary.permissions += 1,
+                Operation::Git(_) => summary.git += 1,
+            }
+        }
+
+        summary
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn record(&self, operation: Operation) {
+        log::debug!("Dry run: {:?}", operation);
+        self.lock().operations.push(operation);
+    }
+
+    fn set(&self, path: &Path, node: Node) {
+        let mut state = self.lock();
+        if matches!(node, Node::Removed) {
+            state.nodes.retain(|other, _| !other.starts_with(path));
+            state.modes.retain(|other, _| !other.starts_with(path));
+        }
+        state.nodes.insert(path.to_path_buf(), node);
+    }
+
+    fn node(&self, path: &Path) -> Option<Node> {
+        self.lock().nodes.get(path).cloned()
+    }
+
+    /// Returns whether something written during the run is inside `path`.
+    fn has_children(&self, path: &Path) -> bool {
+        self.lock()
+            .nodes
+            .iter()
+            .any(|(other, node)| other != path && other.starts_with(path) && *node != Node::Removed)
+    }
+
+    /// Returns whether `path` or one of its parents was removed during the run.
+    fn is_removed(&self, path: &Path) -> bool {
+        path.ancestors()
+            .any(|ancestor| self.node(ancestor) == Some(Node::Removed))
+    }
+}
+
+/// A `FileSystem` that reads from `inner` and records every change in a `Plan`.
+pub struct RecordingFileSystem<F> {
+    inner: F,
+    plan: Arc<Plan>,
+}
+
+impl<F: FileSystem + Send + Sync> RecordingFileSystem<F> {
+    pub fn new(inner: F, plan: Arc<Plan>) -> Self {
+        Self { inner, plan }
+    }
+
+    fn write_node(&self, path: &Path, contents: Vec<u8>) {
+        let operation = match self.exists(path) || self.is_symlink(path) {
+            true => Operation::Replace(path.to_path_buf()),
+            false => Operation::Create(path.to_path_buf()),
+        };
+        self.plan.record(operation);
+        self.plan.set(path, Node::File(contents));
+    }
+}
+
+#[async_trait]
+impl<F: FileSystem + Send + Sync> FileSystem for RecordingFileSystem<F> {
+    fn exists(&self, path: &Path) -> bool {
+        match self.plan.node(path) {
+            Some(Node::File(_) | Node::Dir) => true,
+            Some(Node::Symlink(target)) => self.exists(&path.parent().unwrap_or(path).join(target)),
+            Some(Node::Removed) => false,
+            None if self.plan.has_children(path) => true,
+            None => !self.plan.is_removed(path) && self.inner.exists(path),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.plan.node(path) {
+            Some(Node::Dir) => true,
+            Some(Node::Symlink(target)) => self.is_dir(&path.parent().unwrap_or(path).join(target)),
+            Some(_) => false,
+            None if self.plan.has_children(path) => true,
+            None => !self.plan.is_removed(path) && self.inner.is_dir(path),
+        }
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        match self.plan.node(path) {
+            Some(node) => matches!(node, Node::Symlink(_)),
+            None => !self.plan.is_removed(path) && self.inner.is_symlink(path),
+        }
+    }
+
+    fn is_same_file(&self, a: &Path, b: &Path) -> bool {
+        match self.plan.node(a).is_some() || self.plan.node(b).is_some() {
+            true => a == b,
+            false => self.inner.is_same_file(a, b),
+        }
+    }
+
+    async fn read_to_string(&self, path: &Path) -> Result<String> {
+        Ok(String::from_utf8(self.read(path).await?)?)
+    }
+
+    async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        match self.plan.node(path) {
+            Some(Node::File(contents)) => Ok(contents),
+            Some(Node::Symlink(target)) => {
+                self.read(&path.parent().unwrap_or(path).join(target)).await
+            }
+            Some(_) => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            None if self.plan.is_removed(path) => {
+                Err(io::Error::from(io::ErrorKind::NotFound).into())
+            }
+            None => self.inner.read(path).await,
+        }
+    }
+
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut entries = BTreeSet::new();
+        if !self.plan.is_removed(path) && self.inner.is_dir(path) {
+            entries.extend(self.inner.read_dir(path).await?);
+        }
+
+        for (other, node) in &self.plan.lock().nodes {
+            let Ok(relative) = other.strip_prefix(path) else {
+                continue;
+            };
+            match (relative.components().next(), node) {
+                (None, _) => {}
+                (Some(child), Node::Removed) if relative.components().count() == 1 => {
+                    entries.remove(&path.join(child));
+                }
+                (Some(_), Node::Removed) => {}
+                (Some(child), _) => {
+                    entries.insert(path.join(child));
+                }
+            }
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    async fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        self.write_node(path, contents.as_bytes().to_vec());
+        Ok(())
+    }
+
+    async fn write_bytes(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.write_node(path, contents.to_vec());
+        Ok(())
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let contents = self.read(from).await?;
+        self.write_node(to, contents);
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &Path) -> Result<()> {
+        if !self.exists(path) && !self.is_symlink(path) {
+            return Err(io::Error::from(io::ErrorKind::NotFound).into());
+        }
+
+        self.plan.record(Operation::Delete(path.to_path_buf()));
+        self.plan.set(path, Node::Removed);
+        Ok(())
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.plan.record(Operation::Delete(path.to_path_buf()));
+        self.plan.set(path, Node::Removed);
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.plan.record(Operation::Move {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+
+        let moved: Vec<(PathBuf, Node)> = {
+            let state = self.plan.lock();
+            state
+                .nodes
+                .iter()
+                .filter_map(|(path, node)| {
+                    let relative = path.strip_prefix(from).ok()?;
+                    Some((to.join(relative), node.clone()))
+                })
+                .collect()
+        };
+        let node = match self.is_dir(from) {
+            true => Node::Dir,
+            false => Node::File(self.read(from).await?),
+        };
+
+        self.plan.set(from, Node::Removed);
+        self.plan.set(to, node);
+        for (path, node) in moved {
+            self.plan.set(&path, node);
+        }
+        Ok(())
+    }
+
+    async fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        match self.plan.node(path) {
+            Some(Node::Symlink(target)) => Ok(target),
+            Some(_) => Err(io::Error::from(io::ErrorKind::InvalidInput).into()),
+            None => self.inner.read_link(path).await,
+        }
+    }
+
+    async fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        self.plan.record(Operation::Symlink {
+            link: link.to_path_buf(),
+            target: target.to_path_buf(),
+        });
+        self.plan.set(link, Node::Symlink(target.to_path_buf()));
+        Ok(())
+    }
+
+    async fn hard_link(&self, target: &Path, link: &Path) -> Result<()> {
+        let contents = self.read(target).await?;
+        self.plan.record(Operation::Hardlink {
+            link: link.to_path_buf(),
+            target: target.to_path_buf(),
+        });
+        self.plan.set(link, Node::File(contents));
+        Ok(())
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<Metadata> {
+        if let Some(mode) = self.plan.lock().modes.get(path) {
+            return Ok(Metadata { mode: *mode });
+        }
+
+        match self.plan.node(path) {
+            // New files get the usual mode of 644.
+            Some(Node::File(_)) => Ok(Metadata { mode: 0o644 }),
+            Some(Node::Dir) => Ok(Metadata { mode: 0o755 }),
+            Some(Node::Symlink(target)) => {
+                self.metadata(&path.parent().unwrap_or(path).join(target))
+                    .await
+            }
+            Some(Node::Removed) => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            None if self.plan.is_removed(path) => {
+                Err(io::Error::from(io::ErrorKind::NotFound).into())
+            }
+            None => self.inner.metadata(path).await,
+        }
+    }
+
+    async fn set_permissions(&self, path: &Path, mode: u32) -> Result<()> {
+        self.metadata(path).await?;
+        self.plan.record(Operation::SetPermissions {
+            path: path.to_path_buf(),
+            mode,
+        });
+        self.plan.lock().modes.insert(path.to_path_buf(), mode);
+        Ok(())
+    }
+}
+
+/// A `Git` that reads from `inner` and records every change in a `Plan`.
+pub struct RecordingGit {
+    inner: Arc<dyn Git>,
+    plan: Arc<Plan>,
+}
+
+impl RecordingGit {
+    pub fn new(inner: Arc<dyn Git>, plan: Arc<Plan>) -> Self {
+        Self { inner, plan }
+    }
+
+    fn record(&self, repo: &Path, description: String) {
+        self.plan.record(Operation::Git(format!(
+            "{} in {}",
+            description,
+            repo.display()
+        )));
+    }
+
+    /// Returns whether `repo` only exists because of the recorded changes.
+    fn is_recorded(&self, repo: &Path) -> bool {
+        self.plan.lock().repositories.contains(repo)
+    }
+
+    /// Returns the revision in `inner` that `rev` names after the recorded changes.
+    ///
+    /// `HEAD` is the branch checked out during the run, and a branch created
+    /// during the run is the revision it was created at.
+    fn revision(&self, repo: &Path, rev: &str) -> String {
+        let state = self.plan.lock();
+        let rev = match (rev, state.branches.get(repo)) {
+            ("HEAD", Some(branch)) => branch.as_str(),
+            _ => rev,
+        };
+
+        state
+            .created
+            .get(&(repo.to_path_buf(), rev.to_string()))
+            .cloned()
+            .unwrap_or_else(|| rev.to_string())
+    }
+
+    /// Records the working tree of `repo` as checking out `to` instead of `from` leaves it.
+    ///
+    /// Files that are the same in both revisions are left alone, as a checkout
+    /// keeps any changes made to them.
+    fn switch_files(&self, repo: &Path, from: &str, to: &str) -> Result<()> {
+        let before = self.inner.list_files(repo, from).unwrap_or_default();
+        let after = self.inner.list_files(repo, to)?;
+
+        for path in before.iter().filter(|path| !after.contains(path)) {
+            self.plan.set(&repo.join(path), Node::Removed);
+        }
+        for path in &after {
+            let Some(contents) = self.inner.show_file(repo, to, path)? else {
+                continue;
+            };
+            if self.inner.show_file(repo, from, path).unwrap_or(None) != Some(contents.clone()) {
+                self.plan.set(&repo.join(path), Node::File(contents));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Git for RecordingGit {
+    fn is_repository(&self, path: &Path) -> bool {
+        self.is_recorded(path) || self.inner.is_repository(path)
+    }
+
+    fn init(&self, path: &Path, branch: &str) -> Result<()> {
+        self.record(path, format!("create a repository on branch '{}'", branch));
+        let mut state = self.plan.lock();
+        state.repositories.insert(path.to_path_buf());
+        state
+            .branches
+            .insert(path.to_path_buf(), branch.to_string());
+        Ok(())
+    }
+
+    fn clone_repository(&self, url: &str, path: &Path) -> Result<()> {
+        self.record(path, format!("clone {}", url));
+        self.plan.lock().repositories.insert(path.to_path_buf());
+        Ok(())
+    }
+
+    fn add(&self, repo: &Path, paths: &[PathBuf]) -> Result<()> {
+        let list: Vec<String> = paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        self.record(repo, format!("stage {}", list.join(", ")));
+        self.plan
+            .lock()
+            .added
+            .entry(repo.to_path_buf())
+            .or_default()
+            .extend(paths.iter().cloned());
+        Ok(())
+    }
+
+    fn staged_changes(&self, repo: &Path) -> Result<Vec<(ChangeKind, PathBuf)>> {
+        let mut changes = match self.is_recorded(repo) {
+            true => Vec::new(),
+            false => self.inner.staged_changes(repo)?,
+        };
+
+        let (added, nodes) = {
+            let state = self.plan.lock();
+            let added = state.added.get(repo).cloned().unwrap_or_default();
+            (added, state.nodes.clone())
+        };
+        for (path, node) in nodes {
+            let Ok(relative) = path.strip_prefix(repo) else {
+                continue;
+            };
+            if !added.iter().any(|added| relative.starts_with(added)) {
+                continue;
+            }
+
+            let committed = match self.is_recorded(repo) {
+                true => None,
+                false => {
+                    let head = self.revision(repo, "HEAD");
+                    self.inner.show_file(repo, &head, relative).unwrap_or(None)
+                }
+            };
+            let kind = match (node, committed) {
+                (Node::File(contents), Some(committed)) if contents != committed => {
+                    ChangeKind::Modified
+                }
+                (Node::File(_), None) => ChangeKind::Added,
+                (Node::Removed, Some(_)) => ChangeKind::Removed,
+                _ => continue,
+            };
+            if !changes.iter().any(|(_, other)| other == relative) {
+                changes.push((kind, relative.to_path_buf()));
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn commit(&self, repo: &Path, message: &str) -> Result<String> {
+        let summary = message.lines().next().unwrap_or_default();
+        self.record(repo, format!("commit \"{}\"", summary));
+        self.plan.lock().added.remove(repo);
+        Ok(DRY_RUN_COMMIT.to_string())
+    }
+
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>> {
+        if let Some(branch) = self.plan.lock().branches.get(repo) {
+            return Ok(Some(branch.clone()));
+        }
+
+        match self.is_recorded(repo) {
+            true => Ok(None),
+            false => self.inner.current_branch(repo),
+        }
+    }
+
+    fn create_branch(&self, repo: &Path, name: &str, start: &str) -> Result<()> {
+        self.record(repo, format!("create branch '{}' from '{}'", name, start));
+        let start = self.revision(repo, start);
+        self.plan
+            .lock()
+            .created
+            .insert((repo.to_path_buf(), name.to_string()), start);
+        Ok(())
+    }
+
+    fn rename_branch(&self, repo: &Path, from: &str, to: &str) -> Result<()> {
+        self.record(repo, format!("rename branch '{}' to '{}'", from, to));
+        Ok(())
+    }
+
+    fn resolve_revision(&self, repo: &Path, rev: &str) -> Result<String> {
+        match self.is_recorded(repo) {
+            true => Ok(rev.to_string()),
+            false => self.inner.resolve_revision(repo, &self.revision(repo, rev)),
+        }
+    }
+
+    fn is_ancestor(&self, repo: &Path, ancestor: &str, rev: &str) -> Result<bool> {
+        match self.is_recorded(repo) {
+            true => Ok(false),
+            false => self.inner.is_ancestor(
+                repo,
+                &self.revision(repo, ancestor),
+                &self.revision(repo, rev),
+            ),
+        }
+    }
+
+    fn checkout(&self, repo: &Path, name: &str) -> Result<()> {
+        if !self.is_recorded(repo) {
+            let (from, to) = (self.revision(repo, "HEAD"), self.revision(repo, name));
+            let created = (repo.to_path_buf(), name.to_string());
+            match self.inner.resolve_revision(repo, &to) {
+                Ok(_) => self.switch_files(repo, &from, &to)?,
+                // A branch created during the run may start on an unborn `HEAD`.
+                Err(_) if self.plan.lock().created.contains_key(&created) => {}
+                Err(_) => return Err(GitError::BranchNotFound(name.to_string()).into()),
+            }
+        }
+
+        self.record(repo, format!("check out '{}'", name));
+        self.plan
+            .lock()
+            .branches
+            .insert(repo.to_path_buf(), name.to_string());
+        Ok(())
+    }
+
+    fn delete_branch(&self, repo: &Path, name: &str) -> Result<()> {
+        self.record(repo, format!("delete branch '{}'", name));
+        Ok(())
+    }
+
+    fn list_branches(&self, repo: &Path) -> Result<Vec<String>> {
+        match self.is_recorded(repo) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_branches(repo),
+        }
+    }
+
+    fn create_tag(&self, repo: &Path, name: &str, target: &str) -> Result<()> {
+        self.record(repo, format!("tag '{}' as '{}'", target, name));
+        Ok(())
+    }
+
+    fn delete_tag(&self, repo: &Path, name: &str) -> Result<()> {
+        self.record(repo, format!("delete tag '{}'", name));
+        Ok(())
+    }
+
+    fn list_remote_branches(&self, repo: &Path, remote: &str) -> Result<Vec<String>> {
+        match self.is_recorded(repo) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_remote_branches(repo, remote),
+        }
+    }
+
+    fn list_files(&self, repo: &Path, rev: &str) -> Result<Vec<PathBuf>> {
+        match self.is_recorded(repo) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_files(repo, &self.revision(repo, rev)),
+        }
+    }
+
+    fn show_file(&self, repo: &Path, rev: &str, path: &Path) -> Result<Option<Vec<u8>>> {
+        match self.is_recorded(repo) {
+            true => Ok(None),
+            false => self.inner.show_file(repo, &self.revision(repo, rev), path),
+        }
+    }
+
+    fn file_history(&self, repo: &Path, rev: &str, path: &Path) -> Result<Vec<CommitInfo>> {
+        match self.is_recorded(repo) {
+            true => Ok(Vec::new()),
+            false => self
+                .inner
+                .file_history(repo, &self.revision(repo, rev), path),
+        }
+    }
+
+    fn check_remote(&self, url: &str) -> Result<()> {
+        self.inner.check_remote(url)
+    }
+
+    fn set_remote(&self, repo: &Path, name: &str, url: &str) -> Result<()> {
+        self.record(repo, format!("point remote '{}' at {}", name, url));
+        Ok(())
+    }
+
+    fn fetch(&self, repo: &Path, remote: &str) -> Result<()> {
+        self.record(repo, format!("fetch '{}'", remote));
+        Ok(())
+    }
+
+    fn push(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        self.record(repo, format!("push '{}' to '{}'", branch, remote));
+        Ok(())
+    }
+
+    fn pull(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        self.record(repo, format!("pull '{}' from '{}'", branch, remote));
+        Ok(())
+    }
+}
+
+/// A `ConfigLoader` that records the directories it would create in a `Plan`.
+pub struct RecordingConfigLoader {
+    plan: Arc<Plan>,
+    /// The home directory the XDG defaults are resolved under, instead of the
+    /// directories `ConfigLoaderClient` uses.
+    root: Option<PathBuf>,
+}
+
+impl RecordingConfigLoader {
+    pub fn new(plan: Arc<Plan>) -> Self {
+        Self { plan, root: None }
+    }
+
+    /// Creates a loader that keeps its directories inside `root`, at the
+    /// locations the XDG defaults give them under a home directory.
+    pub fn with_root(plan: Arc<Plan>, root: &Path) -> Self {
+        Self {
+            plan,
+            root: Some(root.to_path_buf()),
+        }
+    }
+
+    /// Returns `default` inside the root, or `client` when there is none.
+    fn path(&self, client: fn() -> Result<PathBuf>, default: &str) -> Result<PathBuf> {
+        match &self.root {
+            Some(root) => Ok(root.join(default).join("dotty")),
+            None => client(),
+        }
+    }
+
+    fn create_dir(&self, path: PathBuf) -> Result<PathBuf> {
+        if !path.exists() && self.plan.node(&path).is_none() {
+            self.plan.record(Operation::Create(path.clone()));
+            self.plan.set(&path, Node::Dir);
+        }
+
+        Ok(path)
+    }
+}
+
+impl ConfigLoader for RecordingConfigLoader {
+    fn get_base_path(&self) -> Result<PathBuf> {
+        self.create_dir(self.path(ConfigLoaderClient::base_path, ".config")?)
+    }
+
+    fn get_state_path(&self) -> Result<PathBuf> {
+        self.create_dir(self.path(ConfigLoaderClient::state_path, ".local/state")?)
+    }
+
+    fn get_cache_path(&self) -> Result<PathBuf> {
+        self.create_dir(self.path(ConfigLoaderClient::cache_path, ".cache")?)
+    }
+
+    fn config_from_str(&self, content: &str) -> Result<TomlConfig> {
+        ConfigLoaderClient.config_from_str(content)
+    }
+
+    fn config_to_string(&self, config: &TomlConfig) -> Result<String> {
+        ConfigLoaderClient.config_to_string(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apply::apply,
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::{ConflictPolicy, ProfileConfig, TomlConfig},
+        mappings::Mappings,
+        repository::{init, sync::sync},
+    };
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Creates a repository with `.vimrc` committed from `home`.
+    async fn repository(root: &Path, home: &Path) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path: root.join("dotty"),
+            active_profile: "default".to_string(),
+            ..TomlConfig::default()
+        };
+        config
+            .profiles
+            .insert("default".to_string(), ProfileConfig::default());
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        fs::create_dir_all(home).unwrap();
+        fs::write(home.join(".vimrc"), "set number").unwrap();
+        config
+            .active_profile_mut()
+            .unwrap()
+            .track(
+                &FileSystemClient,
+                &home.join(".vimrc"),
+                home,
+                &Mappings::default(),
+            )
+            .await
+            .unwrap();
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        config
+    }
+
+    #[tokio::test]
+    async fn test_recording_file_system() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("kept"), "on disk").unwrap();
+
+        let plan = Arc::new(Plan::default());
+        let fs = RecordingFileSystem::new(FileSystemClient, plan.clone());
+        fs.write(&dir.join("new/file"), "written").await.unwrap();
+        fs.write(&dir.join("kept"), "replaced").await.unwrap();
+        fs.symlink(&dir.join("kept"), &dir.join("link"))
+            .await
+            .unwrap();
+
+        // Later reads see the recorded changes, but nothing reached the disk.
+        assert_eq!(
+            fs.read_to_string(&dir.join("new/file")).await.unwrap(),
+            "written"
+        );
+        assert!(fs.is_dir(&dir.join("new")));
+        assert_eq!(
+            fs.read_to_string(&dir.join("link")).await.unwrap(),
+            "replaced"
+        );
+        assert!(!dir.join("new").exists());
+        assert_eq!(fs::read_to_string(dir.join("kept")).unwrap(), "on disk");
+
+        fs.remove_dir_all(dir).await.unwrap();
+        assert!(!fs.exists(&dir.join("kept")));
+        assert!(fs.read_dir(dir).await.unwrap().is_empty());
+        assert!(dir.join("kept").exists());
+
+        assert_eq!(
+            plan.operations(),
+            vec![
+                Operation::Create(dir.join("new/file")),
+                Operation::Replace(dir.join("kept")),
+                Operation::Symlink {
+                    link: dir.join("link"),
+                    target: dir.join("kept"),
+                },
+                Operation::Delete(dir.to_path_buf()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_apply() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home).await;
+        fs::write(home.join(".vimrc"), "set relativenumber").unwrap();
+
+        let plan = Arc::new(Plan::default());
+        let fs = RecordingFileSystem::new(FileSystemClient, plan.clone());
+        let git = RecordingGit::new(Arc::new(GitClient), plan.clone());
+        let report = apply(
+            &config,
+            &home,
+            &fs,
+            &git,
+            Some(ConflictPolicy::Overwrite),
+            &mut |_, _| panic!("Only the `ask` policy asks."),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.updated, vec![home.join(".vimrc")]);
+        plan.exclude(config.state_dir());
+        plan.exclude(config.cache_dir());
+        assert_eq!(
+            plan.operations(),
+            vec![
+                Operation::Delete(home.join(".vimrc")),
+                Operation::Symlink {
+                    link: home.join(".vimrc"),
+                    target: config.base_path.join("home/.vimrc"),
+                },
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(home.join(".vimrc")).unwrap(),
+            "set relativenumber"
+        );
+        assert!(!config.state_dir().join("snapshots").exists());
+    }
+
+    #[tokio::test]
+    async fn test_recorded_checkout() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home).await;
+        let repo = &config.base_path;
+        let branch = GitClient.current_branch(repo).unwrap().unwrap();
+        GitClient.create_branch(repo, "other", "HEAD").unwrap();
+        GitClient.checkout(repo, "other").unwrap();
+        fs::write(repo.join("home/.vimrc"), "set relativenumber").unwrap();
+        fs::write(repo.join("home/.zshrc"), "export EDITOR=nvim").unwrap();
+        let paths = [PathBuf::from("home/.vimrc"), PathBuf::from("home/.zshrc")];
+        GitClient.add(repo, &paths).unwrap();
+        GitClient.commit(repo, "Change the editor").unwrap();
+        GitClient.checkout(repo, &branch).unwrap();
+
+        let plan = Arc::new(Plan::default());
+        let fs = RecordingFileSystem::new(FileSystemClient, plan.clone());
+        let git = RecordingGit::new(Arc::new(GitClient), plan.clone());
+        git.checkout(repo, "other").unwrap();
+
+        // Reads answer from the branch a real checkout would have switched to.
+        assert_eq!(
+            fs.read_to_string(&repo.join("home/.vimrc")).await.unwrap(),
+            "set relativenumber"
+        );
+        assert!(fs.exists(&repo.join("home/.zshrc")));
+        assert!(git
+            .show_file(repo, "HEAD", Path::new("home/.zshrc"))
+            .unwrap()
+            .is_some());
+        assert!(git
+            .list_files(repo, "HEAD")
+            .unwrap()
+            .contains(&PathBuf::from("home/.zshrc")));
+
+        git.checkout(repo, &branch).unwrap();
+        assert!(!fs.exists(&repo.join("home/.zshrc")));
+        assert_eq!(
+            git.checkout(repo, "missing")
+                .unwrap_err()
+                .downcast_ref::<GitError>(),
+            Some(&GitError::BranchNotFound("missing".to_string()))
+        );
+
+        // Nothing changed on disk.
+        assert_eq!(GitClient.current_branch(repo).unwrap(), Some(branch));
+        assert!(!repo.join("home/.zshrc").exists());
+    }
+
+    #[test]
+    fn test_recording_config_loader() {
+        let temp_dir = tempdir().unwrap();
+        let state = temp_dir.path().join(".local/state/dotty");
+        fs::create_dir_all(&state).unwrap();
+
+        let plan = Arc::new(Plan::default());
+        let loader = RecordingConfigLoader::with_root(plan.clone(), temp_dir.path());
+        let cache = loader.get_cache_path().unwrap();
+        assert_eq!(cache, temp_dir.path().join(".cache/dotty"));
+        assert_eq!(loader.get_state_path().unwrap(), state);
+        // Asking again does not record the directory twice.
+        loader.get_cache_path().unwrap();
+
+        assert!(!cache.exists());
+        assert_eq!(plan.operations(), vec![Operation::Create(cache)]);
+    }
+
+    #[test]
+    fn test_operations() {
+        let plan = Plan::default();
+        let state = PathBuf::from("/state/dotty");
+        let path = PathBuf::from("/home/.vimrc");
+        plan.record(Operation::Create(path.clone()));
+        plan.record(Operation::Delete(path.clone()));
+        plan.record(Operation::Create(path.clone()));
+        plan.record(Operation::Create(state.join("journal.toml")));
+        plan.record(Operation::Move {
+            from: state.join("journal"),
+            to: state.join("snapshots/1"),
+        });
+        plan.record(Operation::Move {
+            from: path.clone(),
+            to: state.join("backups/.vimrc"),
+        });
+        plan.exclude(&state);
+
+        // Repeats are kept, and only changes entirely inside excluded directories are left out.
+        assert_eq!(
+            plan.operations(),
+            vec![
+                Operation::Create(path.clone()),
+                Operation::Delete(path.clone()),
+                Operation::Create(path.clone()),
+                Operation::Move {
+                    from: path.clone(),
+                    to: state.join("backups/.vimrc"),
+                },
+            ]
+        );
+        assert_eq!(
+            plan.summary(),
+            Summary {
+                created: 2,
+                deleted: 1,
+                moved: 1,
+                ..Summary::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sync() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let config = repository(temp_dir.path(), &home).await;
+        fs::write(home.join(".vimrc"), "set relativenumber").unwrap();
+        let head = GitClient
+            .resolve_revision(&config.base_path, "HEAD")
+            .unwrap();
+
+        let plan = Arc::new(Plan::default());
+        let fs = RecordingFileSystem::new(FileSystemClient, plan.clone());
+        let git = RecordingGit::new(Arc::new(GitClient), plan.clone());
+        sync(&config, &fs, &git).await.unwrap();
+
+        let operations = plan.operations();
+        assert!(operations.contains(&Operation::Replace(config.base_path.join("home/.vimrc"))));
+        assert!(operations.iter().any(
+            |operation| matches!(operation, Operation::Git(description) if description.starts_with("commit"))
+        ));
+        assert_eq!(
+            GitClient
+                .resolve_revision(&config.base_path, "HEAD")
+                .unwrap(),
+            head
+        );
+        assert!(GitClient
+            .staged_changes(&config.base_path)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,16 +1,49 @@
 // Standard library imports
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 // External crate imports
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::fs;
 
+/// What Dotty reads about a file besides its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// The Unix permission bits, such as `0o600`.
+    pub mode: u32,
+}
+
+#[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait FileSystem {
     fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Returns whether `path` is a symbolic link, without following it.
+    fn is_symlink(&self, path: &Path) -> bool;
+    /// Returns whether `a` and `b` are the same file, following symbolic links.
+    fn is_same_file(&self, a: &Path, b: &Path) -> bool;
     async fn read_to_string(&self, path: &Path) -> Result<String>;
+    async fn read(&self, path: &Path) -> Result<Vec<u8>>;
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>>;
     async fn write(&self, path: &Path, contents: &str) -> Result<()>;
+    /// Writes raw bytes to `path`, creating its parent directories.
+    async fn write_bytes(&self, path: &Path, contents: &[u8]) -> Result<()>;
+    /// Copies `from` to `to`, creating the parent directories of `to`.
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()>;
+    async fn remove_file(&self, path: &Path) -> Result<()>;
+    /// Removes the directory `path` and everything inside it.
+    async fn remove_dir_all(&self, path: &Path) -> Result<()>;
+    /// Moves `from` to `to`, creating the parent directories of `to`.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+    async fn read_link(&self, path: &Path) -> Result<PathBuf>;
+    /// Creates a symbolic link at `link` pointing to `target`, creating the parent directories of `link`.
+    async fn symlink(&self, target: &Path, link: &Path) -> Result<()>;
+    /// Creates a hard link at `link` to `target`, creating the parent directories of `link`.
+    async fn hard_link(&self, target: &Path, link: &Path) -> Result<()>;
+    /// Returns the metadata of `path`, following symbolic links.
+    async fn metadata(&self, path: &Path) -> Result<Metadata>;
+    /// Sets the Unix permission bits of `path`, following symbolic links.
+    async fn set_permissions(&self, path: &Path, mode: u32) -> Result<()>;
 }
 
 pub struct FileSystemClient;
@@ -21,15 +54,135 @@ impl FileSystem for FileSystemClient {
         path.exists()
     }
 
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+
+    #[cfg(unix)]
+    fn is_same_file(&self, a: &Path, b: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn is_same_file(&self, _a: &Path, _b: &Path) -> bool {
+        false
+    }
+
     async fn read_to_string(&self, path: &Path) -> Result<String> {
         Ok(fs::read_to_string(path).await?)
     }
 
+    async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(fs::read(path).await?)
+    }
+
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(path).await?;
+        let mut paths = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            paths.push(entry.path());
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+
     async fn write(&self, path: &Path, contents: &str) -> Result<()> {
         fs::create_dir_all(path.parent().unwrap_or(path)).await?;
         fs::write(path, contents).await?;
         Ok(())
     }
+
+    async fn write_bytes(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        fs::create_dir_all(path.parent().unwrap_or(path)).await?;
+        fs::write(path, contents).await?;
+        Ok(())
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::create_dir_all(to.parent().unwrap_or(to)).await?;
+        fs::copy(from, to).await?;
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path).await?;
+        Ok(())
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        fs::remove_dir_all(path).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::create_dir_all(to.parent().unwrap_or(to)).await?;
+        fs::rename(from, to).await?;
+        Ok(())
+    }
+
+    async fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        Ok(fs::read_link(path).await?)
+    }
+
+    async fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        fs::create_dir_all(link.parent().unwrap_or(link)).await?;
+        #[cfg(unix)]
+        fs::symlink(target, link).await?;
+        #[cfg(windows)]
+        fs::symlink_file(target, link).await?;
+        Ok(())
+    }
+
+    async fn hard_link(&self, target: &Path, link: &Path) -> Result<()> {
+        fs::create_dir_all(link.parent().unwrap_or(link)).await?;
+        fs::hard_link(target, link).await?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    async fn metadata(&self, path: &Path) -> Result<Metadata> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).await?.permissions().mode() & 0o7777;
+        Ok(Metadata { mode })
+    }
+
+    // Only the read-only flag exists elsewhere, so it stands in for the write bits.
+    #[cfg(not(unix))]
+    async fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let mode = match fs::metadata(path).await?.permissions().readonly() {
+            true => 0o444,
+            false => 0o644,
+        };
+        Ok(Metadata { mode })
+    }
+
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> Result<()> {
+        let mut permissions = fs::metadata(path).await?.permissions();
+        permissions.set_readonly(mode & 0o200 == 0);
+        fs::set_permissions(path, permissions).await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -66,6 +219,51 @@ mod tests {
         assert_eq!(result, content);
     }
 
+    #[test]
+    async fn test_read() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_file.bin");
+        let content = [0xde, 0xad, 0xbe, 0xef];
+
+        tokio::fs::write(&file_path, content).await.unwrap();
+
+        let fs_client = FileSystemClient;
+        let result = fs_client.read(&file_path).await.unwrap();
+
+        assert_eq!(result, content);
+    }
+
+    #[test]
+    async fn test_is_dir() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_file.txt");
+        tokio::fs::write(&file_path, "test content").await.unwrap();
+
+        let fs_client = FileSystemClient;
+
+        assert!(fs_client.is_dir(temp_dir.path()));
+        assert!(!fs_client.is_dir(&file_path));
+    }
+
+    #[test]
+    async fn test_read_dir() {
+        let temp_dir = tempdir().unwrap();
+        tokio::fs::write(temp_dir.path().join("b.txt"), "b")
+            .await
+            .unwrap();
+        tokio::fs::write(temp_dir.path().join("a.txt"), "a")
+            .await
+            .unwrap();
+
+        let fs_client = FileSystemClient;
+        let entries = fs_client.read_dir(temp_dir.path()).await.unwrap();
+
+        assert_eq!(
+            entries,
+            vec![temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt")]
+        );
+    }
+
     #[test]
     async fn test_write() {
         let temp_dir = tempdir().unwrap();
@@ -79,6 +277,98 @@ mod tests {
         assert_eq!(read_content, content);
     }
 
+    #[test]
+    async fn test_write_bytes() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("nested").join("image.bin");
+
+        let fs_client = FileSystemClient;
+        fs_client
+            .write_bytes(&file_path, &[0, 159, 146, 150])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tokio::fs::read(&file_path).await.unwrap(),
+            [0, 159, 146, 150]
+        );
+    }
+
+    #[test]
+    async fn test_copy_and_remove_file() {
+        let temp_dir = tempdir().unwrap();
+        let from = temp_dir.path().join("from.txt");
+        let to = temp_dir.path().join("nested").join("to.txt");
+        tokio::fs::write(&from, "Copied content").await.unwrap();
+
+        let fs_client = FileSystemClient;
+        fs_client.copy(&from, &to).await.unwrap();
+        assert_eq!(
+            tokio::fs::read_to_string(&to).await.unwrap(),
+            "Copied content"
+        );
+
+        fs_client.remove_file(&to).await.unwrap();
+        assert!(!to.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    async fn test_is_symlink() {
+        let temp_dir = tempdir().unwrap();
+        let target = temp_dir.path().join("target.txt");
+        let link = temp_dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let fs_client = FileSystemClient;
+        assert!(fs_client.is_symlink(&link));
+        assert!(!fs_client.exists(&link));
+
+        tokio::fs::write(&target, "Linked content").await.unwrap();
+        assert!(fs_client.is_symlink(&link));
+        assert!(!fs_client.is_symlink(&target));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    async fn test_links() {
+        let temp_dir = tempdir().unwrap();
+        let target = temp_dir.path().join("target.txt");
+        let symlink = temp_dir.path().join("nested").join("symlink.txt");
+        let hard_link = temp_dir.path().join("nested").join("hard_link.txt");
+        let copy = temp_dir.path().join("copy.txt");
+        tokio::fs::write(&target, "Linked content").await.unwrap();
+
+        let fs_client = FileSystemClient;
+        fs_client.symlink(&target, &symlink).await.unwrap();
+        fs_client.hard_link(&target, &hard_link).await.unwrap();
+        fs_client.copy(&target, &copy).await.unwrap();
+
+        assert_eq!(fs_client.read_link(&symlink).await.unwrap(), target);
+        assert!(fs_client.is_same_file(&symlink, &target));
+        assert!(fs_client.is_same_file(&hard_link, &target));
+        assert!(!fs_client.is_symlink(&hard_link));
+        assert!(!fs_client.is_same_file(&copy, &target));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    async fn test_permissions() {
+        let temp_dir = tempdir().unwrap();
+        let target = temp_dir.path().join("netrc");
+        let symlink = temp_dir.path().join("symlink");
+        tokio::fs::write(&target, "machine example.com")
+            .await
+            .unwrap();
+
+        let fs_client = FileSystemClient;
+        fs_client.symlink(&target, &symlink).await.unwrap();
+        fs_client.set_permissions(&symlink, 0o600).await.unwrap();
+
+        assert_eq!(fs_client.metadata(&target).await.unwrap().mode, 0o600);
+        assert_eq!(fs_client.metadata(&symlink).await.unwrap().mode, 0o600);
+    }
+
     #[test]
     async fn test_write_creates_directories() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,504 @@
+// Standard library imports
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+// External crate imports
+use anyhow::{Context, Result};
+use chrono::DateTime;
+
+// Local module imports
+use crate::clients::git::{ChangeKind, CommitInfo, Git, GitError, DEFAULT_REMOTE};
+
+/// Identity used for commits when the user has not configured one.
+const FALLBACK_IDENTITY: [&str; 4] = ["-c", "user.name=Dotty", "-c", "user.email=dotty@localhost"];
+
+/// A `Git` implementation that shells out to the system `git` binary.
+pub struct GitClient;
+
+impl Git for GitClient {
+    fn is_repository(&self, path: &Path) -> bool {
+        let Ok(toplevel) = self.run(path, &["rev-parse", "--show-toplevel"]) else {
+            return false;
+        };
+
+        match (Path::new(&toplevel).canonicalize(), path.canonicalize()) {
+            (Ok(toplevel), Ok(path)) => toplevel == path,
+            _ => false,
+        }
+    }
+
+    fn init(&self, path: &Path, branch: &str) -> Result<()> {
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("Unable to create '{}'.", path.display()))?;
+
+        self.run(path, &["init", "--quiet", "--initial-branch", branch])?;
+        Ok(())
+    }
+
+    fn clone_repository(&self, url: &str, path: &Path) -> Result<()> {
+        self.init(path, "main")?;
+        self.run(path, &["remote", "add", DEFAULT_REMOTE, url])?;
+        self.fetch(path, DEFAULT_REMOTE)
+    }
+
+    fn add(&self, repo: &Path, paths: &[PathBuf]) -> Result<()> {
+        // `git add --all --` with no paths would stage the whole tree.
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut args = vec!["add", "--all", "--"];
+        for path in paths {
+            args.push(path.to_str().context("Path is not valid UTF-8")?);
+        }
+
+        self.run(repo, &args)?;
+        Ok(())
+    }
+
+    fn staged_changes(&self, repo: &Path) -> Result<Vec<(ChangeKind, PathBuf)>> {
+        let args = ["diff", "--cached", "--name-status", "--no-renames", "-z"];
+        let output = self.output(repo, &args)?;
+
+        if !output.status.success() {
+            return Err(self.error(repo, &args, &output).into());
+        }
+
+        let fields: Vec<_> = output.stdout.split(|byte| *byte == 0).collect();
+        let mut changes: Vec<_> = fields
+            .chunks_exact(2)
+            .map(|field| {
+                let kind = match field[0] {
+                    b"A" => ChangeKind::Added,
+                    b"D" => ChangeKind::Removed,
+                    _ => ChangeKind::Modified,
+                };
+                (kind, PathBuf::from(OsStr::from_bytes(field[1])))
+            })
+            .collect();
+
+        changes.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(changes)
+    }
+
+    fn commit(&self, repo: &Path, message: &str) -> Result<String> {
+        let mut args = self.identity_args(repo);
+        args.extend(["commit", "--quiet", "--message", message]);
+
+        let output = self.output(repo, &args)?;
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if stdout.contains("nothing to commit") || stdout.contains("no changes added") {
+                return Err(GitError::NothingToCommit.into());
+            }
+            return Err(self.error(repo, &args, &output).into());
+        }
+
+        self.run(repo, &["rev-parse", "HEAD"])
+    }
+
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>> {
+        let args = ["symbolic-ref", "--short", "--quiet", "HEAD"];
+        let output = self.output(repo, &args)?;
+
+        match output.status.code() {
+            Some(0) => Ok(Some(stdout(&output))),
+            Some(1) => Ok(None),
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn create_branch(&self, repo: &Path, name: &str, start: &str) -> Result<()> {
+        let args = ["branch", "--", name, start];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("already exists") => {
+                Err(GitError::BranchExists(name.to_string()).into())
+            }
+            message if message.contains("not a valid object name") => {
+                Err(GitError::BranchNotFound(start.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn rename_branch(&self, repo: &Path, from: &str, to: &str) -> Result<()> {
+        let args = ["branch", "--move", "--", from, to];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("already exists") => {
+                Err(GitError::BranchExists(to.to_string()).into())
+            }
+            message if message.contains("No branch named") => {
+                Err(GitError::BranchNotFound(from.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn resolve_revision(&self, repo: &Path, rev: &str) -> Result<String> {
+        let commit = format!("{}^{{commit}}", rev);
+        let args = [
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            "--end-of-options",
+            &commit,
+        ];
+        let output = self.output(repo, &args)?;
+
+        match output.status.success() {
+            true => Ok(stdout(&output)),
+            false if stderr(&output).is_empty() => {
+                Err(GitError::BranchNotFound(rev.to_string()).into())
+            }
+            false => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn is_ancestor(&self, repo: &Path, ancestor: &str, rev: &str) -> Result<bool> {
+        // `merge-base` has no `--end-of-options`, so both revisions are resolved safely first.
+        let ancestor = self.resolve_revision(repo, ancestor)?;
+        let rev = self.resolve_revision(repo, rev)?;
+        let args = ["merge-base", "--is-ancestor", &ancestor, &rev];
+        let output = self.output(repo, &args)?;
+
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn checkout(&self, repo: &Path, name: &str) -> Result<()> {
+        let args = ["checkout", "--quiet", name, "--"];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message
+                if message.contains("did not match any")
+                    || message.contains("invalid reference") =>
+            {
+                Err(GitError::BranchNotFound(name.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn delete_branch(&self, repo: &Path, name: &str) -> Result<()> {
+        let args = ["branch", "--delete", "--force", "--", name];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("not found") => {
+                Err(GitError::BranchNotFound(name.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn list_branches(&self, repo: &Path) -> Result<Vec<String>> {
+        let branches = self.run(
+            repo,
+            &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+        )?;
+
+        Ok(branches.lines().map(String::from).collect())
+    }
+
+    fn create_tag(&self, repo: &Path, name: &str, target: &str) -> Result<()> {
+        let args = ["tag", "--", name, target];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("already exists") => {
+                Err(GitError::TagExists(name.to_string()).into())
+            }
+            message if message.contains("Failed to resolve") => {
+                Err(GitError::BranchNotFound(target.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn delete_tag(&self, repo: &Path, name: &str) -> Result<()> {
+        self.run(repo, &["tag", "--delete", "--", name])?;
+        Ok(())
+    }
+
+    fn list_remote_branches(&self, repo: &Path, remote: &str) -> Result<Vec<String>> {
+        let prefix = format!("refs/remotes/{}/", remote);
+        let refs = self.run(repo, &["for-each-ref", "--format=%(refname)", &prefix])?;
+
+        Ok(refs
+            .lines()
+            .filter_map(|name| name.strip_prefix(&prefix))
+            .filter(|name| *name != "HEAD")
+            .map(String::from)
+            .collect())
+    }
+
+    fn list_files(&self, repo: &Path, rev: &str) -> Result<Vec<PathBuf>> {
+        // `ls-tree` has no `--end-of-options`, so the revision is resolved safely first.
+        let commit = self.resolve_revision(repo, rev)?;
+        let args = ["ls-tree", "-r", "-z", "--name-only", &commit, "--"];
+        let output = self.output(repo, &args)?;
+
+        match output.status.success() {
+            true => Ok(output
+                .stdout
+                .split(|byte| *byte == 0)
+                .filter(|path| !path.is_empty())
+                .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+                .collect()),
+            false => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn show_file(&self, repo: &Path, rev: &str, path: &Path) -> Result<Option<Vec<u8>>> {
+        let object = format!("{}:{}", rev, path.display());
+        let args = ["cat-file", "blob", object.as_str()];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(Some(output.stdout)),
+            message if message.contains("does not exist in") => Ok(None),
+            message if message.contains("invalid object name") => {
+                Err(GitError::BranchNotFound(rev.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn file_history(&self, repo: &Path, rev: &str, path: &Path) -> Result<Vec<CommitInfo>> {
+        let path = path.to_str().context("Path is not valid UTF-8")?;
+        let args = [
+            "log",
+            "-z",
+            "--format=%H%x00%P%x00%an%x00%aI%x00%B",
+            "--end-of-options",
+            rev,
+            "--",
+            path,
+        ];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => {}
+            message
+                if message.contains("unknown revision")
+                    || message.contains("bad revision")
+                    || message.contains("does not have any commits") =>
+            {
+                return Err(GitError::BranchNotFound(rev.to_string()).into());
+            }
+            _ => return Err(self.error(repo, &args, &output).into()),
+        }
+
+        // Every commit is five NUL-terminated fields; the last one is not terminated.
+        let stdout = stdout(&output);
+        let fields: Vec<&str> = stdout.split('\0').collect();
+
+        fields
+            .chunks_exact(5)
+            .map(|commit| {
+                Ok(CommitInfo {
+                    id: commit[0].trim_start_matches('\n').to_string(),
+                    parent: commit[1]
+                        .split(' ')
+                        .next()
+                        .filter(|id| !id.is_empty())
+                        .map(String::from),
+                    author: commit[2].to_string(),
+                    date: DateTime::parse_from_rfc3339(commit[3])?,
+                    message: commit[4].trim_end().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn check_remote(&self, url: &str) -> Result<()> {
+        // `ls-remote` needs no repository, so it runs anywhere.
+        self.run(&std::env::temp_dir(), &["ls-remote", "--heads", "--", url])?;
+        Ok(())
+    }
+
+    fn set_remote(&self, repo: &Path, name: &str, url: &str) -> Result<()> {
+        let args = ["remote", "add", "--", name, url];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("already exists") => {
+                self.run(repo, &["remote", "set-url", "--", name, url])?;
+                Ok(())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn fetch(&self, repo: &Path, remote: &str) -> Result<()> {
+        let args = ["fetch", "--quiet", "--prune", remote];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if is_missing_remote(&message) => {
+                Err(GitError::RemoteNotFound(remote.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn push(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        let args = [
+            "push",
+            "--quiet",
+            "--set-upstream",
+            remote,
+            refspec.as_str(),
+        ];
+        let output = self.output(repo, &args)?;
+
+        match stderr(&output) {
+            _ if output.status.success() => Ok(()),
+            message if message.contains("[rejected]") || message.contains("non-fast-forward") => {
+                Err(GitError::PushRejected {
+                    remote: remote.to_string(),
+                    branch: branch.to_string(),
+                }
+                .into())
+            }
+            message if message.contains("src refspec") => {
+                Err(GitError::BranchNotFound(branch.to_string()).into())
+            }
+            message if is_missing_remote(&message) => {
+                Err(GitError::RemoteNotFound(remote.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+
+    fn pull(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        let mut args = self.identity_args(repo);
+        args.extend([
+            "pull",
+            "--quiet",
+            "--no-rebase",
+            "--no-edit",
+            remote,
+            branch,
+        ]);
+        let output = self.output(repo, &args)?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let conflicts = self.run(repo, &["diff", "--name-only", "--diff-filter=U"])?;
+        if !conflicts.is_empty() {
+            self.run(repo, &["merge", "--abort"])?;
+            return Err(
+                GitError::MergeConflict(conflicts.lines().map(PathBuf::from).collect()).into(),
+            );
+        }
+
+        match stderr(&output) {
+            message if message.contains("couldn't find remote ref") => {
+                Err(GitError::BranchNotFound(branch.to_string()).into())
+            }
+            message if is_missing_remote(&message) => {
+                Err(GitError::RemoteNotFound(remote.to_string()).into())
+            }
+            _ => Err(self.error(repo, &args, &output).into()),
+        }
+    }
+}
+
+impl GitClient {
+    /// Runs `git` inside `repo` and returns its raw output.
+    ///
+    /// The locale is forced to `C` so that error messages can be matched
+    /// reliably, and credential prompts are disabled so that Dotty never hangs
+    /// waiting for input that the user cannot see.
+    fn output(&self, repo: &Path, args: &[&str]) -> Result<Output> {
+        Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(args)
+            .env("LC_ALL", "C")
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .context("Unable to run git. Please make sure it is installed and on your PATH.")
+    }
+
+    /// Runs `git` inside `repo` and returns its trimmed standard output.
+    fn run(&self, repo: &Path, args: &[&str]) -> Result<String> {
+        let output = self.output(repo, args)?;
+
+        if output.status.success() {
+            Ok(stdout(&output))
+        } else {
+            Err(self.error(repo, args, &output).into())
+        }
+    }
+
+    /// Maps a failed invocation to the most specific `GitError` possible.
+    fn error(&self, repo: &Path, args: &[&str], output: &Output) -> GitError {
+        let message = match stderr(output) {
+            message if message.is_empty() => stdout(output),
+            message => message,
+        };
+
+        if message.contains("not a git repository") {
+            GitError::NotARepository(repo.to_path_buf())
+        } else {
+            GitError::Failed {
+                command: format!("git {}", args.join(" ")),
+                message,
+            }
+        }
+    }
+
+    /// Returns `-c` overrides for the committer identity when none is configured.
+    fn identity_args(&self, repo: &Path) -> Vec<&'static str> {
+        let configured = ["user.name", "user.email"]
+            .iter()
+            .all(|key| self.run(repo, &["config", "--get", key]).is_ok());
+
+        if configured {
+            Vec::new()
+        } else {
+            log::warn!("No Git identity configured, committing as Dotty");
+            FALLBACK_IDENTITY.to_vec()
+        }
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr)
+        .trim_end()
+        .to_string()
+}
+
+fn is_missing_remote(message: &str) -> bool {
+    message.contains("does not appear to be a git repository") || message.contains("No such remote")
+}
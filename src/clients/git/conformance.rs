@@ -0,0 +1,627 @@
+//! Behaviour that every `Git` backend must share.
+//!
+//! Each check is written against `&dyn Git` and instantiated once per backend
+//! by `conformance_tests!`, so the backends cannot drift apart. Remotes are
+//! bare repositories in a temporary directory, served over HTTP on the loopback
+//! interface where a check needs a network remote.
+
+// Standard library imports
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+// External crate imports
+use anyhow::Result;
+use tempfile::{tempdir, TempDir};
+
+// Local module imports
+use super::{ChangeKind, Git, GitError, DEFAULT_REMOTE};
+
+/// Creates a bare repository that acts as the remote in tests.
+pub fn bare_remote() -> (TempDir, PathBuf) {
+    let temp_dir = tempdir().unwrap();
+    let remote = temp_dir.path().join("remote.git");
+
+    let status = Command::new("git")
+        .args(["init", "--quiet", "--bare", "--initial-branch", "main"])
+        .arg(&remote)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    (temp_dir, remote)
+}
+
+/// Writes `contents` to `name` inside `repo` and commits it.
+pub fn commit_file(git: &dyn Git, repo: &Path, name: &str, contents: &str) -> String {
+    let path = repo.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+
+    git.add(repo, &[PathBuf::from(name)]).unwrap();
+    git.commit(repo, &format!("Update {}", name)).unwrap()
+}
+
+/// Serves the repositories inside `root` over Git's smart HTTP protocol through
+/// `git http-backend`, and returns the URL `root` is served at.
+pub fn serve_http(root: &Path) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let root = root.to_path_buf();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A failed request fails the check that made it.
+            let _ = respond(&root, stream);
+        }
+    });
+
+    url
+}
+
+/// Answers one HTTP request by running `git http-backend` as a CGI script.
+fn respond(root: &Path, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut request = line.split_whitespace();
+    let method = request.next().unwrap_or_default().to_string();
+    let target = request.next().unwrap_or_default().to_string();
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+    }
+    let header = |name: &str| headers.get(name).cloned().unwrap_or_default();
+
+    let mut body = Vec::new();
+    if header("transfer-encoding") == "chunked" {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = usize::from_str_radix(line.trim(), 16).unwrap_or_default();
+            // Each chunk, including the last and empty one, ends with a line break.
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk)?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Ok(length) = header("content-length").parse() {
+        reader.by_ref().take(length).read_to_end(&mut body)?;
+    }
+
+    let mut backend = Command::new("git")
+        .arg("http-backend")
+        .env("GIT_PROJECT_ROOT", root)
+        .env("GIT_HTTP_EXPORT_ALL", "1")
+        .env("REQUEST_METHOD", method)
+        .env("PATH_INFO", path)
+        .env("QUERY_STRING", query)
+        .env("CONTENT_TYPE", header("content-type"))
+        .env("CONTENT_LENGTH", body.len().to_string())
+        .env("HTTP_CONTENT_ENCODING", header("content-encoding"))
+        .env("HTTP_GIT_PROTOCOL", header("git-protocol"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    backend.stdin.take().unwrap().write_all(&body)?;
+    let output = backend.wait_with_output()?;
+
+    // The script prints its headers, with an optional status, before the body.
+    let end = output
+        .stdout
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .unwrap_or(output.stdout.len());
+    let (head, body) = output.stdout.split_at(end);
+    let body = body.get(4..).unwrap_or_default();
+    let mut status = "200 OK".to_string();
+    let mut response = String::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        match line.strip_prefix("Status: ") {
+            Some(value) => status = value.to_string(),
+            None => response.push_str(&format!("{}\r\n", line)),
+        }
+    }
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        response,
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+pub fn git_error(result: Result<impl std::fmt::Debug>) -> GitError {
+    result.unwrap_err().downcast::<GitError>().unwrap()
+}
+
+/// Clones `remote` into `path` and checks out `main`, which is unborn when the remote is empty.
+fn clone_main(git: &dyn Git, remote: &Path, path: &Path) {
+    git.clone_repository(remote.to_str().unwrap(), path)
+        .unwrap();
+
+    match git.checkout(path, "main") {
+        Err(error) if error.downcast_ref() == Some(&GitError::BranchNotFound("main".into())) => {}
+        result => result.unwrap(),
+    }
+}
+
+pub fn init_and_commit(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path().join("dotfiles");
+
+    assert!(!git.is_repository(&repo));
+
+    git.init(&repo, "nord-theme").unwrap();
+
+    assert!(git.is_repository(&repo));
+    assert!(!git.is_repository(&repo.join("nested")));
+    assert_eq!(
+        git.current_branch(&repo).unwrap(),
+        Some("nord-theme".to_string())
+    );
+    assert_eq!(
+        git_error(git.commit(&repo, "Empty")),
+        GitError::NothingToCommit
+    );
+    assert!(git.staged_changes(&repo).unwrap().is_empty());
+
+    let id = commit_file(git, &repo, "home/.zshrc", "export EDITOR=nvim");
+    assert_eq!(id.len(), 40);
+    assert_eq!(git.list_branches(&repo).unwrap(), vec!["nord-theme"]);
+    assert_eq!(
+        git_error(git.commit(&repo, "Empty")),
+        GitError::NothingToCommit
+    );
+
+    // Staging a deleted file records the deletion.
+    fs::remove_file(repo.join("home/.zshrc")).unwrap();
+    fs::write(repo.join("home/.bashrc"), "set -o vi").unwrap();
+    git.add(&repo, &[PathBuf::from("home")]).unwrap();
+    assert_eq!(
+        git.staged_changes(&repo).unwrap(),
+        vec![
+            (ChangeKind::Added, PathBuf::from("home/.bashrc")),
+            (ChangeKind::Removed, PathBuf::from("home/.zshrc")),
+        ]
+    );
+    assert_ne!(git.commit(&repo, "Remove zshrc").unwrap(), id);
+}
+
+pub fn not_a_repository(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+
+    assert_eq!(
+        git_error(git.list_branches(temp_dir.path())),
+        GitError::NotARepository(temp_dir.path().to_path_buf())
+    );
+}
+
+pub fn branches(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path();
+
+    git.init(repo, "main").unwrap();
+    commit_file(git, repo, "zshrc", "export EDITOR=nvim");
+
+    git.create_branch(repo, "gruvbox", "HEAD").unwrap();
+    assert_eq!(
+        git_error(git.create_branch(repo, "gruvbox", "HEAD")),
+        GitError::BranchExists("gruvbox".to_string())
+    );
+    assert_eq!(git.list_branches(repo).unwrap(), vec!["gruvbox", "main"]);
+
+    git.checkout(repo, "gruvbox").unwrap();
+    assert_eq!(
+        git.current_branch(repo).unwrap(),
+        Some("gruvbox".to_string())
+    );
+    commit_file(git, repo, "zshrc", "export EDITOR=helix");
+    assert_eq!(
+        git_error(git.checkout(repo, "missing")),
+        GitError::BranchNotFound("missing".to_string())
+    );
+
+    git.checkout(repo, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(repo.join("zshrc")).unwrap(),
+        "export EDITOR=nvim"
+    );
+
+    assert_eq!(
+        git.list_files(repo, "gruvbox").unwrap(),
+        vec![PathBuf::from("zshrc")]
+    );
+    assert_eq!(
+        git_error(git.list_files(repo, "missing")),
+        GitError::BranchNotFound("missing".to_string())
+    );
+    assert_eq!(
+        git.show_file(repo, "gruvbox", Path::new("zshrc")).unwrap(),
+        Some(b"export EDITOR=helix".to_vec())
+    );
+    assert_eq!(
+        git.show_file(repo, "main", Path::new("bashrc")).unwrap(),
+        None
+    );
+    assert_eq!(
+        git_error(git.show_file(repo, "missing", Path::new("zshrc"))),
+        GitError::BranchNotFound("missing".to_string())
+    );
+
+    git.delete_branch(repo, "gruvbox").unwrap();
+    assert_eq!(git.list_branches(repo).unwrap(), vec!["main"]);
+    assert_eq!(
+        git_error(git.delete_branch(repo, "gruvbox")),
+        GitError::BranchNotFound("gruvbox".to_string())
+    );
+}
+
+pub fn rename_and_tag(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path();
+
+    git.init(repo, "main").unwrap();
+    commit_file(git, repo, "zshrc", "export EDITOR=nvim");
+    git.create_branch(repo, "gruvbox", "main").unwrap();
+    assert_eq!(
+        git_error(git.create_branch(repo, "nord", "missing")),
+        GitError::BranchNotFound("missing".to_string())
+    );
+
+    // Renaming the checked out branch moves `HEAD` along.
+    git.rename_branch(repo, "main", "default").unwrap();
+    assert_eq!(
+        git.current_branch(repo).unwrap(),
+        Some("default".to_string())
+    );
+    assert_eq!(
+        git_error(git.rename_branch(repo, "main", "nord")),
+        GitError::BranchNotFound("main".to_string())
+    );
+    assert_eq!(
+        git_error(git.rename_branch(repo, "default", "gruvbox")),
+        GitError::BranchExists("gruvbox".to_string())
+    );
+
+    // An archived branch can be restored from its tag.
+    let tag = "archive/gruvbox-2024-01-31";
+    git.create_tag(repo, tag, "gruvbox").unwrap();
+    assert_eq!(
+        git_error(git.create_tag(repo, tag, "gruvbox")),
+        GitError::TagExists(tag.to_string())
+    );
+    git.delete_branch(repo, "gruvbox").unwrap();
+    git.create_branch(repo, "gruvbox", tag).unwrap();
+    assert_eq!(git.list_branches(repo).unwrap(), vec!["default", "gruvbox"]);
+
+    git.delete_tag(repo, tag).unwrap();
+    assert!(git.delete_tag(repo, tag).is_err());
+}
+
+pub fn revisions(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path();
+
+    git.init(repo, "main").unwrap();
+    let first = commit_file(git, repo, "zshrc", "export EDITOR=nvim");
+    let second = commit_file(git, repo, "zshrc", "export EDITOR=helix");
+
+    assert_eq!(git.resolve_revision(repo, "main").unwrap(), second);
+    assert_eq!(git.resolve_revision(repo, &first).unwrap(), first);
+    assert_eq!(
+        git_error(git.resolve_revision(repo, "missing")),
+        GitError::BranchNotFound("missing".to_string())
+    );
+
+    // A commit is checked out with a detached `HEAD`.
+    git.checkout(repo, &first).unwrap();
+    assert_eq!(git.current_branch(repo).unwrap(), None);
+    assert_eq!(
+        fs::read_to_string(repo.join("zshrc")).unwrap(),
+        "export EDITOR=nvim"
+    );
+
+    git.checkout(repo, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(repo.join("zshrc")).unwrap(),
+        "export EDITOR=helix"
+    );
+
+    // Only earlier commits of a branch are reachable from it.
+    git.create_branch(repo, "other", &first).unwrap();
+    git.checkout(repo, "other").unwrap();
+    let third = commit_file(git, repo, "vimrc", "set number");
+    assert!(git.is_ancestor(repo, &first, "main").unwrap());
+    assert!(git.is_ancestor(repo, "main", "main").unwrap());
+    assert!(!git.is_ancestor(repo, &third, "main").unwrap());
+    assert!(!git.is_ancestor(repo, "main", &first).unwrap());
+}
+
+pub fn file_history(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path();
+
+    git.init(repo, "main").unwrap();
+    let first = commit_file(git, repo, "zshrc", "export EDITOR=nvim");
+    commit_file(git, repo, "bashrc", "set -o vi");
+    let second = commit_file(git, repo, "zshrc", "export EDITOR=helix");
+
+    let history = git.file_history(repo, "main", Path::new("zshrc")).unwrap();
+    let ids: Vec<_> = history.iter().map(|commit| commit.id.clone()).collect();
+    assert_eq!(ids, vec![second, first.clone()]);
+    assert!(history[0].parent.is_some());
+    assert_eq!(history[1].parent, None);
+    assert_eq!(history[1].message, "Update zshrc");
+    assert!(!history[1].author.is_empty());
+
+    assert!(git
+        .file_history(repo, &first, Path::new("vimrc"))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        git_error(git.file_history(repo, "missing", Path::new("zshrc"))),
+        GitError::BranchNotFound("missing".to_string())
+    );
+
+    // A revision is never read as an option.
+    let output = format!("--output={}", repo.join("written").display());
+    assert!(git.file_history(repo, &output, Path::new("zshrc")).is_err());
+    assert!(git.list_files(repo, &output).is_err());
+    assert!(!repo.join("written").exists());
+}
+
+pub fn push_clone_and_pull(git: &dyn Git) {
+    let (temp_dir, remote) = bare_remote();
+    let first = temp_dir.path().join("first");
+    let second = temp_dir.path().join("second");
+
+    clone_main(git, &remote, &first);
+    commit_file(git, &first, "zshrc", "export EDITOR=nvim");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+
+    // The destination may already contain untracked files.
+    fs::create_dir_all(&second).unwrap();
+    fs::write(second.join("config.toml"), "").unwrap();
+    git.clone_repository(&format!("file://{}", remote.display()), &second)
+        .unwrap();
+    git.checkout(&second, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(second.join("zshrc")).unwrap(),
+        "export EDITOR=nvim"
+    );
+    assert!(second.join("config.toml").exists());
+
+    commit_file(git, &first, "zshrc", "export EDITOR=helix");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+
+    // Unrelated changes on both sides are merged.
+    commit_file(git, &second, "bashrc", "set -o vi");
+    git.pull(&second, DEFAULT_REMOTE, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(second.join("zshrc")).unwrap(),
+        "export EDITOR=helix"
+    );
+    git.push(&second, DEFAULT_REMOTE, "main").unwrap();
+
+    git.pull(&first, DEFAULT_REMOTE, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(first.join("bashrc")).unwrap(),
+        "set -o vi"
+    );
+}
+
+pub fn push_rejected_and_merge_conflict(git: &dyn Git) {
+    let (temp_dir, remote) = bare_remote();
+    let first = temp_dir.path().join("first");
+    let second = temp_dir.path().join("second");
+
+    clone_main(git, &remote, &first);
+    commit_file(git, &first, "zshrc", "export EDITOR=nvim");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+
+    clone_main(git, &remote, &second);
+    assert_eq!(
+        git.list_remote_branches(&second, DEFAULT_REMOTE).unwrap(),
+        vec!["main"]
+    );
+
+    commit_file(git, &first, "zshrc", "export EDITOR=helix");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+
+    commit_file(git, &second, "zshrc", "export EDITOR=emacs");
+    assert_eq!(
+        git_error(git.push(&second, DEFAULT_REMOTE, "main")),
+        GitError::PushRejected {
+            remote: DEFAULT_REMOTE.to_string(),
+            branch: "main".to_string()
+        }
+    );
+
+    assert_eq!(
+        git_error(git.pull(&second, DEFAULT_REMOTE, "main")),
+        GitError::MergeConflict(vec![PathBuf::from("zshrc")])
+    );
+
+    // The aborted merge leaves the repository as it was.
+    assert_eq!(
+        git.current_branch(&second).unwrap(),
+        Some("main".to_string())
+    );
+    assert_eq!(
+        fs::read_to_string(second.join("zshrc")).unwrap(),
+        "export EDITOR=emacs"
+    );
+    assert_eq!(
+        git_error(git.commit(&second, "Empty")),
+        GitError::NothingToCommit
+    );
+}
+
+pub fn remotes(git: &dyn Git) {
+    let (temp_dir, backup) = bare_remote();
+    let showcase = temp_dir.path().join("showcase.git");
+    let repo = temp_dir.path().join("repo");
+
+    let first = temp_dir.path().join("first");
+    clone_main(git, &backup, &first);
+    commit_file(git, &first, "zshrc", "export EDITOR=nvim");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+    fs::create_dir_all(&showcase).unwrap();
+    git.init(&showcase, "main").unwrap();
+
+    git.init(&repo, "main").unwrap();
+    git.set_remote(&repo, "backup", backup.to_str().unwrap())
+        .unwrap();
+    git.fetch(&repo, "backup").unwrap();
+    assert_eq!(
+        git.list_remote_branches(&repo, "backup").unwrap(),
+        vec!["main"]
+    );
+
+    git.check_remote(backup.to_str().unwrap()).unwrap();
+    assert!(git
+        .check_remote(temp_dir.path().join("missing").to_str().unwrap())
+        .is_err());
+
+    // Setting an existing remote changes where it points.
+    git.set_remote(&repo, "backup", showcase.to_str().unwrap())
+        .unwrap();
+    git.fetch(&repo, "backup").unwrap();
+    assert!(git
+        .list_remote_branches(&repo, "backup")
+        .unwrap()
+        .is_empty());
+}
+
+pub fn http_remote(git: &dyn Git) {
+    let (temp_dir, remote) = bare_remote();
+    let server = serve_http(temp_dir.path());
+    let url = format!("{}/remote.git", server);
+    let first = temp_dir.path().join("first");
+    let second = temp_dir.path().join("second");
+
+    clone_main(git, &remote, &first);
+    commit_file(git, &first, "zshrc", "export EDITOR=nvim");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+
+    git.check_remote(&url).unwrap();
+    assert!(git
+        .check_remote(&format!("{}/missing.git", server))
+        .is_err());
+
+    git.clone_repository(&url, &second).unwrap();
+    git.checkout(&second, "main").unwrap();
+    assert_eq!(
+        fs::read_to_string(second.join("zshrc")).unwrap(),
+        "export EDITOR=nvim"
+    );
+
+    let head = commit_file(git, &first, "vimrc", "set number");
+    git.push(&first, DEFAULT_REMOTE, "main").unwrap();
+    git.pull(&second, DEFAULT_REMOTE, "main").unwrap();
+    assert_eq!(git.resolve_revision(&second, "HEAD").unwrap(), head);
+    assert_eq!(
+        git.list_remote_branches(&second, DEFAULT_REMOTE).unwrap(),
+        vec!["main"]
+    );
+}
+
+pub fn missing_remote(git: &dyn Git) {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path();
+
+    git.init(repo, "main").unwrap();
+
+    assert_eq!(
+        git_error(git.fetch(repo, DEFAULT_REMOTE)),
+        GitError::RemoteNotFound(DEFAULT_REMOTE.to_string())
+    );
+}
+
+/// Instantiates every conformance check as a `#[test]` for `$backend`.
+macro_rules! conformance_tests {
+    ($module:ident, $backend:expr) => {
+        mod $module {
+            use super::*;
+
+            #[test]
+            fn test_init_and_commit() {
+                init_and_commit(&$backend);
+            }
+
+            #[test]
+            fn test_not_a_repository() {
+                not_a_repository(&$backend);
+            }
+
+            #[test]
+            fn test_branches() {
+                branches(&$backend);
+            }
+
+            #[test]
+            fn test_rename_and_tag() {
+                rename_and_tag(&$backend);
+            }
+
+            #[test]
+            fn test_remotes() {
+                remotes(&$backend);
+            }
+
+            #[test]
+            fn test_revisions() {
+                revisions(&$backend);
+            }
+
+            #[test]
+            fn test_file_history() {
+                file_history(&$backend);
+            }
+
+            #[test]
+            fn test_push_clone_and_pull() {
+                push_clone_and_pull(&$backend);
+            }
+
+            #[test]
+            fn test_push_rejected_and_merge_conflict() {
+                push_rejected_and_merge_conflict(&$backend);
+            }
+
+            #[test]
+            fn test_http_remote() {
+                http_remote(&$backend);
+            }
+
+            #[test]
+            fn test_missing_remote() {
+                missing_remote(&$backend);
+            }
+        }
+    };
+}
+
+conformance_tests!(git_client, super::super::GitClient);
+
+#[cfg(feature = "gix-backend")]
+conformance_tests!(gix_client, super::super::GixClient);
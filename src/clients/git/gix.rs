@@ -0,0 +1,1036 @@
+// Standard library imports
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsStr,
+    fs,
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::{symlink, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use chrono::{FixedOffset, TimeZone};
+use gix::{
+    bstr::{BString, ByteSlice},
+    index::{
+        entry::{Flags, Mode, Stat},
+        fs::Metadata,
+        State,
+    },
+    merge::{blob::builtin_driver::text::Labels, tree::TreatAsUnresolved},
+    objs::{tree::EntryKind, Kind, Write as _},
+    progress,
+    protocol::transport::{client::blocking_io::connect::connect, Service},
+    refs::{
+        transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+        FullName, Target,
+    },
+    remote::Direction,
+    url::Scheme,
+    ObjectId, Repository, Url,
+};
+
+// Local module imports
+use crate::clients::git::{ChangeKind, CommitInfo, Git, GitError, DEFAULT_REMOTE};
+
+/// Identity used for commits when the user has not configured one.
+const FALLBACK_NAME: &str = "Dotty";
+const FALLBACK_EMAIL: &str = "dotty@localhost";
+
+/// A `Git` implementation backed by gitoxide, for systems without a `git` binary.
+///
+/// The object database, references, index and working tree are all handled in
+/// process. Objects are copied directly between local repositories, while other
+/// remotes are fetched over the network. gitoxide cannot push yet, so pushes are
+/// limited to local paths and `file://` URLs.
+pub struct GixClient;
+
+impl Git for GixClient {
+    fn is_repository(&self, path: &Path) -> bool {
+        let Ok(repo) = open_repository(path) else {
+            return false;
+        };
+
+        match (repo.workdir().map(Path::canonicalize), path.canonicalize()) {
+            (Some(Ok(workdir)), Ok(path)) => workdir == path,
+            _ => false,
+        }
+    }
+
+    fn init(&self, path: &Path, branch: &str) -> Result<()> {
+        fs::create_dir_all(path)
+            .with_context(|| format!("Unable to create '{}'.", path.display()))?;
+
+        gix::init(path)?;
+        let repo = self.open(path)?;
+        set_head(&repo, Target::Symbolic(branch_ref(branch)?), "init")
+    }
+
+    fn clone_repository(&self, url: &str, path: &Path) -> Result<()> {
+        self.init(path, "main")?;
+        self.set_remote(path, DEFAULT_REMOTE, url)?;
+        self.fetch(path, DEFAULT_REMOTE)
+    }
+
+    fn add(&self, repo: &Path, paths: &[PathBuf]) -> Result<()> {
+        let local = self.open(repo)?;
+        let workdir = workdir(&local)?;
+        let mut index = load_index(&local)?;
+
+        for path in paths {
+            let prefix = to_bstring(path);
+            let mut nested = prefix.clone();
+            nested.push(b'/');
+
+            index.remove_entries(|_, entry, _| entry == prefix || entry.starts_with(&nested));
+
+            for file in files_under(&workdir.join(path))? {
+                let metadata = Metadata::from_path_no_follow(&file)?;
+                let (mode, data) = read_worktree_file(&file)?;
+                let id = local.write_blob(&data)?.detach();
+                let relative = to_bstring(file.strip_prefix(workdir)?);
+
+                index.dangerously_push_entry(
+                    Stat::from_fs(&metadata)?,
+                    id,
+                    Flags::empty(),
+                    mode,
+                    relative.as_ref(),
+                );
+            }
+        }
+
+        index.sort_entries();
+        // The cached trees no longer describe the entries.
+        index.remove_tree();
+        index.write(Default::default())?;
+        Ok(())
+    }
+
+    fn staged_changes(&self, repo: &Path) -> Result<Vec<(ChangeKind, PathBuf)>> {
+        let local = self.open(repo)?;
+        let index = local.index_or_empty()?;
+        let mut head = tree_files(&local, head_tree(&local)?)?;
+        let mut changes = Vec::new();
+
+        for entry in index.entries() {
+            let path = entry.path(&index);
+            match head.remove(path) {
+                None => changes.push((ChangeKind::Added, to_path(path).to_path_buf())),
+                Some((_, id)) if id != entry.id => {
+                    changes.push((ChangeKind::Modified, to_path(path).to_path_buf()))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for path in head.into_keys() {
+            changes.push((ChangeKind::Removed, to_path(&path).to_path_buf()));
+        }
+
+        changes.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(changes)
+    }
+
+    fn commit(&self, repo: &Path, message: &str) -> Result<String> {
+        let local = self.open(repo)?;
+        let index = local.index_or_empty()?;
+
+        let mut editor = local.edit_tree(ObjectId::empty_tree(local.object_hash()))?;
+        for entry in index.entries() {
+            let kind = entry
+                .mode
+                .to_tree_entry_mode()
+                .context("The index contains an entry of unknown type.")?
+                .kind();
+            editor.upsert(entry.path(&index), kind, entry.id)?;
+        }
+        let tree = editor.write()?.detach();
+
+        let parent = head_id(&local)?;
+        let parent_tree = match parent {
+            Some(parent) => local.find_commit(parent)?.tree_id()?.detach(),
+            None => ObjectId::empty_tree(local.object_hash()),
+        };
+
+        if tree == parent_tree {
+            return Err(GitError::NothingToCommit.into());
+        }
+
+        let id = commit_as(&local, "HEAD", message, tree, parent)?;
+        Ok(id.to_string())
+    }
+
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>> {
+        let local = self.open(repo)?;
+
+        Ok(local.head_name()?.map(|name| name.shorten().to_string()))
+    }
+
+    fn create_branch(&self, repo: &Path, name: &str, start: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let reference = branch_ref(name)?;
+
+        if local.try_find_reference(&reference)?.is_some() {
+            return Err(GitError::BranchExists(name.to_string()).into());
+        }
+
+        let target = resolve_commit(&local, start)?;
+        local.reference(
+            reference,
+            target,
+            PreviousValue::MustNotExist,
+            format!("branch: Created from {}", start),
+        )?;
+        Ok(())
+    }
+
+    fn rename_branch(&self, repo: &Path, from: &str, to: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let (old, new) = (branch_ref(from)?, branch_ref(to)?);
+
+        let Some(mut branch) = local.try_find_reference(&old)? else {
+            return Err(GitError::BranchNotFound(from.to_string()).into());
+        };
+        if local.try_find_reference(&new)?.is_some() {
+            return Err(GitError::BranchExists(to.to_string()).into());
+        }
+
+        let target = branch.peel_to_id()?.detach();
+        let message = format!("Branch: renamed {} to {}", old.as_bstr(), new.as_bstr());
+        local.reference(
+            new.clone(),
+            target,
+            PreviousValue::MustNotExist,
+            message.as_str(),
+        )?;
+
+        if local.head_name()?.as_ref() == Some(&old) {
+            set_head(&local, Target::Symbolic(new), &message)?;
+        }
+
+        branch.delete()?;
+        Ok(())
+    }
+
+    fn resolve_revision(&self, repo: &Path, rev: &str) -> Result<String> {
+        let local = self.open(repo)?;
+        Ok(resolve_commit(&local, rev)?.to_string())
+    }
+
+    fn is_ancestor(&self, repo: &Path, ancestor: &str, rev: &str) -> Result<bool> {
+        let local = self.open(repo)?;
+        let ancestor = resolve_commit(&local, ancestor)?;
+        let start = resolve_commit(&local, rev)?;
+
+        for info in local.rev_walk([start]).all()? {
+            if info?.id == ancestor {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn checkout(&self, repo: &Path, name: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let local_ref = branch_ref(name)?;
+        let remote_ref = format!("refs/remotes/{}/{}", DEFAULT_REMOTE, name);
+
+        let (target, create) = if let Some(mut branch) = local.try_find_reference(&local_ref)? {
+            (branch.peel_to_id()?.detach(), false)
+        } else if let Some(mut branch) = local.try_find_reference(remote_ref.as_str())? {
+            (branch.peel_to_id()?.detach(), true)
+        } else if let Ok(id) = local.rev_parse_single(name) {
+            (id.object()?.peel_to_commit()?.id, false)
+        } else {
+            return Err(GitError::BranchNotFound(name.to_string()).into());
+        };
+
+        let from = head_tree(&local)?;
+        let to = local.find_commit(target)?.tree_id()?.detach();
+        update_worktree(&local, from, to)?;
+
+        let is_branch = create || local.try_find_reference(&local_ref)?.is_some();
+        if create {
+            local.reference(
+                local_ref.clone(),
+                target,
+                PreviousValue::MustNotExist,
+                format!("branch: Created from {}", remote_ref),
+            )?;
+        }
+
+        let head = if is_branch {
+            Target::Symbolic(local_ref)
+        } else {
+            Target::Object(target)
+        };
+        set_head(&local, head, &format!("checkout: moving to {}", name))
+    }
+
+    fn delete_branch(&self, repo: &Path, name: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let reference = branch_ref(name)?;
+
+        let Some(branch) = local.try_find_reference(&reference)? else {
+            return Err(GitError::BranchNotFound(name.to_string()).into());
+        };
+
+        if local.head_name()?.as_ref() == Some(&reference) {
+            bail!(GitError::Failed {
+                command: format!("branch --delete {}", name),
+                message: format!("Cannot delete branch '{}' as it is checked out.", name),
+            });
+        }
+
+        branch.delete()?;
+        Ok(())
+    }
+
+    fn list_branches(&self, repo: &Path) -> Result<Vec<String>> {
+        let local = self.open(repo)?;
+        let mut branches = Vec::new();
+
+        for branch in local.references()?.local_branches()? {
+            branches.push(branch?.name().shorten().to_string());
+        }
+
+        branches.sort();
+        Ok(branches)
+    }
+
+    fn create_tag(&self, repo: &Path, name: &str, target: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let reference = format!("refs/tags/{}", name);
+
+        if local.try_find_reference(reference.as_str())?.is_some() {
+            return Err(GitError::TagExists(name.to_string()).into());
+        }
+
+        let target = resolve_commit(&local, target)?;
+        local.reference(reference, target, PreviousValue::MustNotExist, "tag")?;
+        Ok(())
+    }
+
+    fn delete_tag(&self, repo: &Path, name: &str) -> Result<()> {
+        let local = self.open(repo)?;
+
+        match local.try_find_reference(format!("refs/tags/{}", name).as_str())? {
+            Some(tag) => Ok(tag.delete()?),
+            None => bail!(GitError::Failed {
+                command: format!("tag --delete {}", name),
+                message: format!("Tag '{}' not found.", name),
+            }),
+        }
+    }
+
+    fn list_remote_branches(&self, repo: &Path, remote: &str) -> Result<Vec<String>> {
+        let local = self.open(repo)?;
+        let prefix = format!("refs/remotes/{}/", remote);
+        let mut branches = Vec::new();
+
+        for branch in local.references()?.prefixed(prefix.as_str())? {
+            let name = branch?.name().as_bstr().to_string();
+            if &name[prefix.len()..] != "HEAD" {
+                branches.push(name[prefix.len()..].to_string());
+            }
+        }
+
+        branches.sort();
+        Ok(branches)
+    }
+
+    fn list_files(&self, repo: &Path, rev: &str) -> Result<Vec<PathBuf>> {
+        let local = self.open(repo)?;
+        let Ok(id) = local.rev_parse_single(rev) else {
+            return Err(GitError::BranchNotFound(rev.to_string()).into());
+        };
+
+        let tree = id.object()?.peel_to_tree()?.id;
+        Ok(tree_files(&local, Some(tree))?
+            .into_keys()
+            .map(|path| to_path(&path).to_path_buf())
+            .collect())
+    }
+
+    fn show_file(&self, repo: &Path, rev: &str, path: &Path) -> Result<Option<Vec<u8>>> {
+        let local = self.open(repo)?;
+        let Ok(id) = local.rev_parse_single(rev) else {
+            return Err(GitError::BranchNotFound(rev.to_string()).into());
+        };
+
+        let tree = id.object()?.peel_to_tree()?;
+        match tree.lookup_entry_by_path(path)? {
+            Some(entry) if entry.mode().is_blob_or_symlink() => {
+                Ok(Some(entry.object()?.detach().data))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn file_history(&self, repo: &Path, rev: &str, path: &Path) -> Result<Vec<CommitInfo>> {
+        let local = self.open(repo)?;
+        let start = resolve_commit(&local, rev)?;
+        let entry_at = |commit: ObjectId| -> Result<Option<ObjectId>> {
+            let tree = local.find_commit(commit)?.tree()?;
+            Ok(tree
+                .lookup_entry_by_path(path)?
+                .map(|entry| entry.object_id()))
+        };
+
+        let mut history = Vec::new();
+        for info in local.rev_walk([start]).all()? {
+            let commit = local.find_commit(info?.id)?;
+            let parents: Vec<ObjectId> = commit.parent_ids().map(|id| id.detach()).collect();
+            let entry = entry_at(commit.id)?;
+
+            let changed = match parents.is_empty() {
+                true => entry.is_some(),
+                false => parents
+                    .iter()
+                    .map(|&parent| entry_at(parent))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .all(|previous| previous != entry),
+            };
+            if !changed {
+                continue;
+            }
+
+            let author = commit.author()?;
+            let time = author.time()?;
+            let date = FixedOffset::east_opt(time.offset)
+                .and_then(|offset| offset.timestamp_opt(time.seconds, 0).single())
+                .context("Commit has an invalid date")?;
+
+            history.push(CommitInfo {
+                id: commit.id.to_string(),
+                parent: parents.first().map(ObjectId::to_string),
+                author: author.name.to_string(),
+                date,
+                message: commit.message_raw()?.to_str_lossy().trim_end().to_string(),
+            });
+        }
+
+        Ok(history)
+    }
+
+    fn check_remote(&self, url: &str) -> Result<()> {
+        let parsed = gix::url::parse(url.as_bytes().as_bstr())?;
+        if parsed.scheme == Scheme::File {
+            open_repository(to_path(&parsed.path))?;
+            return Ok(());
+        }
+
+        let transport = connect(parsed, Default::default())?;
+        gix::protocol::handshake(
+            transport,
+            Service::UploadPack,
+            gix::credentials::builtin,
+            Vec::new(),
+            &mut progress::Discard,
+        )?;
+        Ok(())
+    }
+
+    fn set_remote(&self, repo: &Path, name: &str, url: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let mut config = local.config_file_mut(local.git_dir().join("config"))?;
+
+        // Saving replaces any existing section for the remote.
+        local
+            .remote_at(url)?
+            .with_refspecs(
+                Some(format!("+refs/heads/*:refs/remotes/{}/*", name).as_str()),
+                Direction::Fetch,
+            )?
+            .save_as_to(name, &mut config)?;
+        config.commit()?;
+
+        Ok(())
+    }
+
+    fn fetch(&self, repo: &Path, remote: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let tips = match self.remote_url(&local, remote)?.scheme {
+            Scheme::File => {
+                let upstream = self.open_remote(&local, remote)?;
+                let tips = branch_tips(&upstream)?;
+                copy_objects(&upstream, &local, tips.values().copied())?;
+                tips
+            }
+            _ => receive(&local, remote)?,
+        };
+
+        let prefix = format!("refs/remotes/{}/", remote);
+        for tracking in local.references()?.prefixed(prefix.as_str())? {
+            let tracking = tracking?;
+            let name = tracking.name().as_bstr().to_string();
+            if !tips.contains_key(&name[prefix.len()..]) {
+                tracking.delete()?;
+            }
+        }
+
+        for (name, id) in tips {
+            local.reference(
+                format!("{}{}", prefix, name),
+                id,
+                PreviousValue::Any,
+                format!("fetch: {}", remote),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn push(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        let local = self.open(repo)?;
+        let upstream = self.open_remote(&local, remote)?;
+        let reference = branch_ref(branch)?;
+
+        let Some(mut ours) = local.try_find_reference(&reference)? else {
+            return Err(GitError::BranchNotFound(branch.to_string()).into());
+        };
+        let ours = ours.peel_to_id()?.detach();
+
+        let theirs = match upstream.try_find_reference(&reference)? {
+            Some(mut theirs) => Some(theirs.peel_to_id()?.detach()),
+            None => None,
+        };
+
+        if let Some(theirs) = theirs {
+            if !local.has_object(theirs) || !is_ancestor(&local, theirs, ours) {
+                return Err(GitError::PushRejected {
+                    remote: remote.to_string(),
+                    branch: branch.to_string(),
+                }
+                .into());
+            }
+        }
+
+        copy_objects(&local, &upstream, [ours])?;
+
+        let expected = match theirs {
+            Some(theirs) => PreviousValue::MustExistAndMatch(Target::Object(theirs)),
+            None => PreviousValue::MustNotExist,
+        };
+        upstream.reference(reference, ours, expected, "push")?;
+        local.reference(
+            format!("refs/remotes/{}/{}", remote, branch),
+            ours,
+            PreviousValue::Any,
+            "update by push",
+        )?;
+
+        Ok(())
+    }
+
+    fn pull(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        self.fetch(repo, remote)?;
+
+        let local = self.open(repo)?;
+        let tracking = format!("refs/remotes/{}/{}", remote, branch);
+        let Some(mut theirs) = local.try_find_reference(tracking.as_str())? else {
+            return Err(GitError::BranchNotFound(branch.to_string()).into());
+        };
+        let theirs = theirs.peel_to_id()?.detach();
+
+        let Some(ours) = head_id(&local)? else {
+            let to = local.find_commit(theirs)?.tree_id()?.detach();
+            update_worktree(&local, None, to)?;
+            return set_head_target(&local, theirs, "pull: fast-forward");
+        };
+
+        if ours == theirs || is_ancestor(&local, theirs, ours) {
+            return Ok(());
+        }
+
+        let from = local.find_commit(ours)?.tree_id()?.detach();
+
+        if is_ancestor(&local, ours, theirs) {
+            let to = local.find_commit(theirs)?.tree_id()?.detach();
+            update_worktree(&local, Some(from), to)?;
+            return set_head_target(&local, theirs, "pull: fast-forward");
+        }
+
+        let labels = Labels {
+            ancestor: None,
+            current: Some("HEAD".into()),
+            other: Some(branch.into()),
+        };
+        let mut outcome =
+            local.merge_commits(ours, theirs, labels, local.tree_merge_options()?.into())?;
+
+        let how = TreatAsUnresolved::git();
+        if outcome.tree_merge.has_unresolved_conflicts(how) {
+            let mut files: Vec<PathBuf> = outcome
+                .tree_merge
+                .conflicts
+                .iter()
+                .filter(|conflict| conflict.is_unresolved(how))
+                .map(|conflict| PathBuf::from(conflict.ours.location().to_str_lossy().as_ref()))
+                .collect();
+            files.sort();
+            files.dedup();
+
+            return Err(GitError::MergeConflict(files).into());
+        }
+
+        let to = outcome.tree_merge.tree.write()?.detach();
+        update_worktree(&local, Some(from), to)?;
+
+        let message = format!("Merge branch '{}' of {}", branch, remote);
+        commit_as(&local, "HEAD", &message, to, [ours, theirs])?;
+        Ok(())
+    }
+}
+
+impl GixClient {
+    fn open(&self, path: &Path) -> Result<Repository> {
+        open_repository(path).map_err(|_| GitError::NotARepository(path.to_path_buf()).into())
+    }
+
+    /// Returns the URL that `remote` fetches from.
+    ///
+    /// # Errors
+    /// This function will return an error if the remote is not configured.
+    fn remote_url(&self, repo: &Repository, remote: &str) -> Result<Url> {
+        let missing = || GitError::RemoteNotFound(remote.to_string());
+
+        if !repo
+            .remote_names()
+            .iter()
+            .any(|name| name.as_bytes() == remote.as_bytes())
+        {
+            return Err(missing().into());
+        }
+
+        let found = repo.find_remote(remote)?;
+        let url = found.url(Direction::Fetch).ok_or_else(missing)?;
+        Ok(url.clone())
+    }
+
+    /// Opens the repository that `remote` points to.
+    ///
+    /// # Errors
+    /// This function will return an error if the remote is not configured, cannot
+    /// be opened, or is not a local path.
+    fn open_remote(&self, repo: &Repository, remote: &str) -> Result<Repository> {
+        let missing = || GitError::RemoteNotFound(remote.to_string());
+        let url = self.remote_url(repo, remote)?;
+
+        if url.scheme != Scheme::File {
+            bail!(unsupported_remote(
+                &format!("push {}", remote),
+                &url.to_bstring().to_string()
+            ));
+        }
+
+        let path = to_path(&url.path).to_path_buf();
+        let path = match repo.workdir() {
+            Some(workdir) if path.is_relative() => workdir.join(path),
+            _ => path,
+        };
+
+        open_repository(&path).map_err(|_| missing().into())
+    }
+}
+
+/// The error for pushing to a remote that is not a local path, which this backend cannot do.
+fn unsupported_remote(command: &str, url: &str) -> GitError {
+    GitError::Failed {
+        command: command.to_string(),
+        message: format!(
+            "The built-in Git backend can only push to local remotes, not '{}'. \
+            Set `git_backend = \"system\"` to use the git binary instead.",
+            url
+        ),
+    }
+}
+
+/// Returns the commit each local branch of `repo` points to, by branch name.
+fn branch_tips(repo: &Repository) -> Result<BTreeMap<String, ObjectId>> {
+    let mut tips = BTreeMap::new();
+    for branch in repo.references()?.local_branches()? {
+        let mut branch = branch?;
+        tips.insert(
+            branch.name().shorten().to_string(),
+            branch.peel_to_id()?.detach(),
+        );
+    }
+
+    Ok(tips)
+}
+
+/// Fetches `remote` of `repo` over the network and returns the commit each of
+/// its branches points to, by branch name.
+fn receive(repo: &Repository, remote: &str) -> Result<BTreeMap<String, ObjectId>> {
+    let outcome = repo
+        .find_remote(remote)?
+        .connect(Direction::Fetch)?
+        .prepare_fetch(progress::Discard, Default::default())?
+        .receive(progress::Discard, &gix::interrupt::IS_INTERRUPTED)?;
+
+    let mut tips = BTreeMap::new();
+    for reference in &outcome.ref_map.remote_refs {
+        if let (name, Some(id), _) = reference.unpack() {
+            if let Some(branch) = name.strip_prefix(b"refs/heads/") {
+                tips.insert(branch.to_str_lossy().to_string(), id.to_owned());
+            }
+        }
+    }
+
+    Ok(tips)
+}
+
+fn branch_ref(name: &str) -> Result<FullName> {
+    Ok(format!("refs/heads/{}", name).try_into()?)
+}
+
+fn to_bstring(path: &Path) -> BString {
+    path.as_os_str().as_bytes().into()
+}
+
+fn to_path(path: &[u8]) -> &Path {
+    Path::new(OsStr::from_bytes(path))
+}
+
+fn workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir()
+        .context("This operation requires a repository with a working tree.")
+}
+
+/// Resolves the revision `rev` to a commit.
+fn resolve_commit(repo: &Repository, rev: &str) -> Result<ObjectId> {
+    let Ok(id) = repo.rev_parse_single(rev) else {
+        return Err(GitError::BranchNotFound(rev.to_string()).into());
+    };
+
+    Ok(id.object()?.peel_to_commit()?.id)
+}
+
+/// Returns the commit `HEAD` points at, or `None` while the current branch is unborn.
+fn head_id(repo: &Repository) -> Result<Option<ObjectId>> {
+    Ok(repo.head()?.id().map(|id| id.detach()))
+}
+
+fn head_tree(repo: &Repository) -> Result<Option<ObjectId>> {
+    match head_id(repo)? {
+        Some(id) => Ok(Some(repo.find_commit(id)?.tree_id()?.detach())),
+        None => Ok(None),
+    }
+}
+
+/// Points `HEAD` itself at `target`, without writing through to the current branch.
+fn set_head(repo: &Repository, target: Target, message: &str) -> Result<()> {
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: message.into(),
+            },
+            expected: PreviousValue::Any,
+            new: target,
+        },
+        name: "HEAD".try_into()?,
+        deref: false,
+    })?;
+    Ok(())
+}
+
+/// Moves the branch `HEAD` points at, or `HEAD` itself when detached, to `id`.
+fn set_head_target(repo: &Repository, id: ObjectId, message: &str) -> Result<()> {
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: message.into(),
+            },
+            expected: PreviousValue::Any,
+            new: Target::Object(id),
+        },
+        name: "HEAD".try_into()?,
+        deref: true,
+    })?;
+    Ok(())
+}
+
+fn commit_as(
+    repo: &Repository,
+    reference: &str,
+    message: &str,
+    tree: ObjectId,
+    parents: impl IntoIterator<Item = ObjectId>,
+) -> Result<ObjectId> {
+    Ok(repo.commit(reference, message, tree, parents)?.detach())
+}
+
+/// Opens the repository at `path`, falling back to a placeholder identity when
+/// the user has not configured one, as commits and reflogs both need it.
+fn open_repository(path: &Path) -> Result<Repository> {
+    let mut repo = gix::open(path)?;
+
+    if repo.committer().is_none() {
+        log::debug!("No Git identity configured, using {}", FALLBACK_NAME);
+
+        let mut config = repo.config_snapshot_mut();
+        config.set_value(&gix::config::tree::User::NAME, FALLBACK_NAME)?;
+        config.set_value(&gix::config::tree::User::EMAIL, FALLBACK_EMAIL)?;
+        config.commit()?;
+    }
+
+    Ok(repo)
+}
+
+fn is_ancestor(repo: &Repository, ancestor: ObjectId, descendant: ObjectId) -> bool {
+    repo.merge_base(ancestor, descendant)
+        .is_ok_and(|base| base.is_some_and(|base| base.detach() == ancestor))
+}
+
+fn load_index(repo: &Repository) -> Result<gix::index::File> {
+    if repo.index_path().exists() {
+        Ok(repo.open_index()?)
+    } else {
+        Ok(gix::index::File::from_state(
+            State::new(repo.object_hash()),
+            repo.index_path(),
+        ))
+    }
+}
+
+/// Lists the files at or below `path`, skipping `.git` directories.
+fn files_under(path: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return Ok(Vec::new());
+    };
+
+    if !metadata.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?.path();
+        if entry.file_name().is_some_and(|name| name == ".git") {
+            continue;
+        }
+        files.extend(files_under(&entry)?);
+    }
+
+    Ok(files)
+}
+
+/// Reads a working tree file as it would be stored in a blob.
+fn read_worktree_file(path: &Path) -> Result<(Mode, Vec<u8>)> {
+    let metadata = path.symlink_metadata()?;
+
+    if metadata.is_symlink() {
+        let target = fs::read_link(path)?;
+        Ok((Mode::SYMLINK, target.into_os_string().into_vec()))
+    } else if metadata.permissions().mode() & 0o111 != 0 {
+        Ok((Mode::FILE_EXECUTABLE, fs::read(path)?))
+    } else {
+        Ok((Mode::FILE, fs::read(path)?))
+    }
+}
+
+/// Returns the blob ID of the file at `path`, if it exists.
+///
+/// Directories are reported as the null ID so that they never match a blob.
+fn worktree_blob_id(repo: &Repository, path: &Path) -> Result<Option<ObjectId>> {
+    match path.symlink_metadata() {
+        Err(_) => Ok(None),
+        Ok(metadata) if metadata.is_dir() => Ok(Some(ObjectId::null(repo.object_hash()))),
+        Ok(_) => {
+            let (_, data) = read_worktree_file(path)?;
+            Ok(Some(gix::objs::compute_hash(
+                repo.object_hash(),
+                Kind::Blob,
+                &data,
+            )?))
+        }
+    }
+}
+
+/// Flattens the tree `id` into a map of file paths to their mode and blob.
+fn tree_files(
+    repo: &Repository,
+    id: Option<ObjectId>,
+) -> Result<BTreeMap<BString, (EntryKind, ObjectId)>> {
+    let Some(id) = id else {
+        return Ok(BTreeMap::new());
+    };
+
+    Ok(repo
+        .find_tree(id)?
+        .traverse()
+        .breadthfirst
+        .files()?
+        .into_iter()
+        .filter(|entry| entry.mode.is_blob_or_symlink())
+        .map(|entry| (entry.filepath, (entry.mode.kind(), entry.oid)))
+        .collect())
+}
+
+/// Moves the working tree and index from the tree `from` to the tree `to`.
+///
+/// Like `git checkout`, this refuses to overwrite local modifications or
+/// untracked files, and checks every path before touching any of them.
+fn update_worktree(repo: &Repository, from: Option<ObjectId>, to: ObjectId) -> Result<()> {
+    let workdir = workdir(repo)?;
+    let old = tree_files(repo, from)?;
+    let new = tree_files(repo, Some(to))?;
+
+    let overwritten = |path: &BString| {
+        GitError::Failed {
+            command: "checkout".to_string(),
+            message: format!("Your local changes to '{}' would be overwritten.", path),
+        }
+        .into()
+    };
+
+    for (path, (kind, id)) in &new {
+        if old.get(path) == Some(&(*kind, *id)) {
+            continue;
+        }
+
+        let current = worktree_blob_id(repo, &workdir.join(to_path(path)))?;
+        let expected = old.get(path).map(|(_, id)| *id);
+        if current.is_some() && current != expected && current != Some(*id) {
+            return Err(overwritten(path));
+        }
+    }
+
+    for (path, (_, id)) in &old {
+        if new.contains_key(path) {
+            continue;
+        }
+
+        let current = worktree_blob_id(repo, &workdir.join(to_path(path)))?;
+        if current.is_some() && current != Some(*id) {
+            return Err(overwritten(path));
+        }
+    }
+
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        let file = workdir.join(to_path(path));
+        if file.symlink_metadata().is_ok() {
+            fs::remove_file(&file)?;
+        }
+
+        // Prune directories that are now empty, like git does.
+        let mut dir = file.parent();
+        while let Some(parent) = dir.filter(|dir| *dir != workdir) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+
+    for (path, (kind, id)) in &new {
+        if old.get(path) == Some(&(*kind, *id)) {
+            continue;
+        }
+
+        let file = workdir.join(to_path(path));
+        let data = repo.find_blob(*id)?.take_data();
+
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if file.symlink_metadata().is_ok() {
+            fs::remove_file(&file)?;
+        }
+
+        match kind {
+            EntryKind::Link => symlink(to_path(&data), &file)?,
+            _ => {
+                fs::write(&file, &data)?;
+
+                let mut permissions = fs::metadata(&file)?.permissions();
+                let mode = match kind {
+                    EntryKind::BlobExecutable => permissions.mode() | 0o111,
+                    _ => permissions.mode() & !0o111,
+                };
+                permissions.set_mode(mode);
+                fs::set_permissions(&file, permissions)?;
+            }
+        }
+    }
+
+    let mut index = repo.index_from_tree(&to)?;
+    for (entry, path) in index.entries_mut_with_paths() {
+        let file = workdir.join(to_path(path));
+        if let Ok(metadata) = Metadata::from_path_no_follow(&file) {
+            entry.stat = Stat::from_fs(&metadata)?;
+        }
+    }
+    index.write(Default::default())?;
+
+    Ok(())
+}
+
+/// Copies every object reachable from `tips` that `to` does not have yet.
+///
+/// Objects are written dependencies-first, so an interrupted copy never leaves
+/// a commit or tree behind whose contents are missing.
+fn copy_objects(
+    from: &Repository,
+    to: &Repository,
+    tips: impl IntoIterator<Item = ObjectId>,
+) -> Result<()> {
+    let mut pending: Vec<ObjectId> = tips.into_iter().collect();
+    let mut seen = HashSet::new();
+    let mut missing = Vec::new();
+
+    while let Some(id) = pending.pop() {
+        if !seen.insert(id) || to.has_object(id) {
+            continue;
+        }
+
+        let commit = from.find_commit(id)?;
+        pending.extend(commit.parent_ids().map(|parent| parent.detach()));
+        missing.push(id);
+    }
+
+    for id in missing.into_iter().rev() {
+        copy_tree(from, to, from.find_commit(id)?.tree_id()?.detach())?;
+        copy_object(from, to, id)?;
+    }
+
+    Ok(())
+}
+
+fn copy_tree(from: &Repository, to: &Repository, id: ObjectId) -> Result<()> {
+    if to.has_object(id) {
+        return Ok(());
+    }
+
+    for entry in from.find_tree(id)?.iter() {
+        let entry = entry?;
+        let oid = entry.object_id();
+
+        match entry.mode().kind() {
+            EntryKind::Tree => copy_tree(from, to, oid)?,
+            // Submodules live in other repositories.
+            EntryKind::Commit => {}
+            _ if !to.has_object(oid) => copy_object(from, to, oid)?,
+            _ => {}
+        }
+    }
+
+    copy_object(from, to, id)
+}
+
+fn copy_object(from: &Repository, to: &Repository, id: ObjectId) -> Result<()> {
+    let object = from.find_object(id)?;
+    to.write_buf(object.kind, &object.data)?;
+    Ok(())
+}
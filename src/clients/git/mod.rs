@@ -0,0 +1,466 @@
+// Standard library imports
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// External crate imports
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+// Submodules
+pub mod client;
+#[cfg(test)]
+mod conformance;
+#[cfg(feature = "gix-backend")]
+pub mod gix;
+
+#[cfg(feature = "gix-backend")]
+pub use self::gix::GixClient;
+pub use client::GitClient;
+
+/// The remote that `clone_repository` configures.
+pub const DEFAULT_REMOTE: &str = "origin";
+
+pub trait Git {
+    /// Checks that `name` is not one of `branches`, saying where it is used if it is.
+    fn is_branch_unique(&self, branches: &[(String, BranchLocation)], name: &str) -> Result<()> {
+        match branches.iter().find(|(branch, _)| branch == name) {
+            Some((_, location)) => Err(anyhow!(
+                "This name {}. Please choose a different one.",
+                location
+            )),
+            None => Ok(()),
+        }
+    }
+    /// Lists the branches of `repo` and of its `origin` remote, as of the last fetch.
+    ///
+    /// Returns nothing when `repo` is not a repository yet.
+    fn existing_branches(&self, repo: &Path) -> Result<Vec<(String, BranchLocation)>> {
+        if !self.is_repository(repo) {
+            return Ok(Vec::new());
+        }
+
+        let local = self
+            .list_branches(repo)?
+            .into_iter()
+            .map(|branch| (branch, BranchLocation::Local));
+        let remote = self
+            .list_remote_branches(repo, DEFAULT_REMOTE)?
+            .into_iter()
+            .map(|branch| (branch, BranchLocation::Remote(DEFAULT_REMOTE.to_string())));
+
+        Ok(local.chain(remote).collect())
+    }
+    /// Checks `name` against the rules of `git check-ref-format --branch`.
+    ///
+    /// The error names the first rule that is broken.
+    fn is_valid_branch_name(&self, name: &str) -> Result<()> {
+        const INVALID_CHARS: [char; 7] = ['~', '^', ':', '?', '*', '[', '\\'];
+
+        if name.trim().is_empty() {
+            return Err(anyhow!("Branch name cannot be empty"));
+        }
+
+        if name == "@" || name == "HEAD" {
+            return Err(anyhow!("Branch name cannot be '{}'", name));
+        }
+
+        if name.starts_with('-') {
+            return Err(anyhow!("Branch name cannot start with '-'"));
+        }
+
+        if name.starts_with('/') || name.ends_with('/') {
+            return Err(anyhow!("Branch name cannot start or end with '/'"));
+        }
+
+        if name.ends_with('.') {
+            return Err(anyhow!("Branch name cannot end with '.'"));
+        }
+
+        if name.contains("//") {
+            return Err(anyhow!(
+                "Branch name cannot contain two consecutive slashes '//'"
+            ));
+        }
+
+        if name.contains("..") {
+            return Err(anyhow!(
+                "Branch name cannot contain two consecutive dots '..'"
+            ));
+        }
+
+        if name.contains("@{") {
+            return Err(anyhow!("Branch name cannot contain '@{{'"));
+        }
+
+        name.split('/').try_for_each(|component| {
+            if component.starts_with('.') {
+                Err(anyhow!("No part of a branch name can start with '.'"))
+            } else if component.ends_with(".lock") {
+                Err(anyhow!("No part of a branch name can end with '.lock'"))
+            } else {
+                Ok(())
+            }
+        })?;
+
+        name.chars().try_for_each(|c| {
+            if c == ' ' {
+                Err(anyhow!("Branch name cannot contain spaces"))
+            } else if c.is_ascii_control() {
+                Err(anyhow!("Branch name cannot contain control characters"))
+            } else if INVALID_CHARS.contains(&c) {
+                Err(anyhow!("Branch name cannot contain '{}'", c))
+            } else {
+                Ok(())
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns whether `path` is the root of a Git repository.
+    fn is_repository(&self, path: &Path) -> bool;
+    /// Creates a new repository at `path` whose unborn `HEAD` points at `branch`.
+    fn init(&self, path: &Path, branch: &str) -> Result<()>;
+    /// Initialises `path`, adds `url` as the `origin` remote and fetches it.
+    ///
+    /// Unlike `git clone`, `path` may already exist and contain files, and no
+    /// branch is checked out; use `checkout` to select one.
+    fn clone_repository(&self, url: &str, path: &Path) -> Result<()>;
+    /// Stages `paths`, relative to the repository root, including deletions.
+    fn add(&self, repo: &Path, paths: &[PathBuf]) -> Result<()>;
+    /// Lists the files whose staged version differs from `HEAD`, sorted by path.
+    fn staged_changes(&self, repo: &Path) -> Result<Vec<(ChangeKind, PathBuf)>>;
+    /// Commits the staged changes and returns the new commit ID.
+    fn commit(&self, repo: &Path, message: &str) -> Result<String>;
+    /// Returns the checked out branch, or `None` when `HEAD` is detached.
+    fn current_branch(&self, repo: &Path) -> Result<Option<String>>;
+    /// Creates `name` pointing at the revision `start` without checking it out.
+    fn create_branch(&self, repo: &Path, name: &str, start: &str) -> Result<()>;
+    /// Renames the local branch `from` to `to`, moving `HEAD` along if it is checked out.
+    fn rename_branch(&self, repo: &Path, from: &str, to: &str) -> Result<()>;
+    /// Returns the ID of the commit that `rev` points at.
+    fn resolve_revision(&self, repo: &Path, rev: &str) -> Result<String>;
+    /// Returns whether the commit `ancestor` points at is reachable from `rev`.
+    fn is_ancestor(&self, repo: &Path, ancestor: &str, rev: &str) -> Result<bool>;
+    /// Checks out `name`, creating it from `origin/<name>` when only the remote branch exists.
+    ///
+    /// Any other revision, such as a commit ID, is checked out with a detached `HEAD`.
+    fn checkout(&self, repo: &Path, name: &str) -> Result<()>;
+    fn delete_branch(&self, repo: &Path, name: &str) -> Result<()>;
+    fn list_branches(&self, repo: &Path) -> Result<Vec<String>>;
+    /// Creates a lightweight tag `name` pointing at the revision `target`.
+    fn create_tag(&self, repo: &Path, name: &str, target: &str) -> Result<()>;
+    fn delete_tag(&self, repo: &Path, name: &str) -> Result<()>;
+    /// Lists the branches of `remote` as of the last fetch, without the remote prefix.
+    fn list_remote_branches(&self, repo: &Path, remote: &str) -> Result<Vec<String>>;
+    /// Lists every file in the tree of `rev`, relative to the repository root.
+    fn list_files(&self, repo: &Path, rev: &str) -> Result<Vec<PathBuf>>;
+    /// Returns the contents of `path` at `rev`, or `None` when it does not exist there.
+    fn show_file(&self, repo: &Path, rev: &str, path: &Path) -> Result<Option<Vec<u8>>>;
+    /// Lists the commits reachable from `rev` that change `path`, newest first.
+    ///
+    /// Merges are only listed when they change the file compared with every parent.
+    fn file_history(&self, repo: &Path, rev: &str, path: &Path) -> Result<Vec<CommitInfo>>;
+    /// Checks that `url` is a repository that can be read, without fetching anything.
+    fn check_remote(&self, url: &str) -> Result<()>;
+    /// Points the remote `name` at `url`, adding the remote if it does not exist.
+    fn set_remote(&self, repo: &Path, name: &str, url: &str) -> Result<()>;
+    fn fetch(&self, repo: &Path, remote: &str) -> Result<()>;
+    fn push(&self, repo: &Path, remote: &str, branch: &str) -> Result<()>;
+    /// Fetches `branch` from `remote` and merges it into the current branch.
+    ///
+    /// When the merge conflicts it is aborted, leaving the working tree as it was,
+    /// and a `GitError::MergeConflict` listing the conflicting files is returned.
+    fn pull(&self, repo: &Path, remote: &str, branch: &str) -> Result<()>;
+}
+
+/// Where a branch name is already in use.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchLocation {
+    /// The branch of the profile with this ID.
+    Profile(String),
+    /// A branch in the local repository.
+    Local,
+    /// A branch on the named remote.
+    Remote(String),
+}
+
+impl fmt::Display for BranchLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Profile(id) => write!(f, "is already used by the '{}' profile", id),
+            Self::Local => write!(f, "already exists in the repository"),
+            Self::Remote(remote) => write!(f, "already exists on {}", remote),
+        }
+    }
+}
+
+/// A commit as listed by `Git::file_history`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub id: String,
+    /// The first parent, or `None` for the root commit.
+    pub parent: Option<String>,
+    pub author: String,
+    /// When the commit was authored, in the author's time zone.
+    pub date: DateTime<FixedOffset>,
+    pub message: String,
+}
+
+/// How a staged file differs from `HEAD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// The `Git` implementation Dotty uses, selected with `git_backend` in `config.toml`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    /// Runs the `git` binary found on the `PATH`.
+    #[default]
+    System,
+    /// Uses the built-in gitoxide implementation, for systems without `git`.
+    Gix,
+}
+
+impl GitBackend {
+    /// Creates the `Git` implementation for this backend.
+    ///
+    /// # Errors
+    /// This function will return an error if the backend was not compiled in.
+    ///
+    pub fn client(self) -> Result<Arc<dyn Git>> {
+        match self {
+            Self::System => Ok(Arc::new(GitClient)),
+            #[cfg(feature = "gix-backend")]
+            Self::Gix => Ok(Arc::new(GixClient)),
+            #[cfg(not(feature = "gix-backend"))]
+            Self::Gix => Err(anyhow!(
+                "This build of Dotty does not include the gix backend. \
+                Rebuild it with `--features gix-backend` or set `git_backend = \"system\"`."
+            )),
+        }
+    }
+}
+
+/// Failures that callers may want to handle differently from a generic error.
+///
+/// These are returned wrapped in an `anyhow::Error`; use `downcast_ref` to
+/// inspect them.
+#[derive(Debug, PartialEq)]
+pub enum GitError {
+    NotARepository(PathBuf),
+    BranchNotFound(String),
+    BranchExists(String),
+    TagExists(String),
+    RemoteNotFound(String),
+    NothingToCommit,
+    MergeConflict(Vec<PathBuf>),
+    PushRejected { remote: String, branch: String },
+    Failed { command: String, message: String },
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotARepository(path) => {
+                write!(f, "'{}' is not a Git repository.", path.display())
+            }
+            Self::BranchNotFound(name) => write!(f, "Branch '{}' does not exist.", name),
+            Self::BranchExists(name) => write!(f, "Branch '{}' already exists.", name),
+            Self::TagExists(name) => write!(f, "Tag '{}' already exists.", name),
+            Self::RemoteNotFound(name) => write!(f, "Remote '{}' could not be found.", name),
+            Self::NothingToCommit => write!(f, "There are no changes to commit."),
+            Self::MergeConflict(files) => {
+                write!(f, "Merge conflict in: ")?;
+                let files: Vec<_> = files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect();
+                write!(f, "{}", files.join(", "))
+            }
+            Self::PushRejected { remote, branch } => write!(
+                f,
+                "Push of '{}' to '{}' was rejected. Pull the latest changes and try again.",
+                branch, remote
+            ),
+            Self::Failed { command, message } => write!(f, "`{}` failed: {}", command, message),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_branch_unique() {
+        let git_client = GitClient;
+        let branches = vec![
+            ("main".to_string(), BranchLocation::Local),
+            (
+                "develop".to_string(),
+                BranchLocation::Profile("work".to_string()),
+            ),
+            (
+                "feature/123".to_string(),
+                BranchLocation::Remote("origin".to_string()),
+            ),
+        ];
+
+        assert!(git_client.is_branch_unique(&branches, "new-branch").is_ok());
+        assert!(git_client.is_branch_unique(&branches, "main").is_err());
+        assert!(git_client.is_branch_unique(&branches, "develop").is_err());
+        assert_eq!(
+            git_client
+                .is_branch_unique(&branches, "feature/123")
+                .unwrap_err()
+                .to_string(),
+            "This name already exists on origin. Please choose a different one."
+        );
+    }
+
+    #[test]
+    fn test_existing_branches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let remote = temp_dir.path().join("remote");
+
+        assert!(GitClient.existing_branches(&repo).unwrap().is_empty());
+
+        GitClient.init(&remote, "main").unwrap();
+        std::fs::write(remote.join("zshrc"), "export EDITOR=nvim").unwrap();
+        GitClient.add(&remote, &[PathBuf::from("zshrc")]).unwrap();
+        GitClient.commit(&remote, "Track zshrc").unwrap();
+
+        GitClient
+            .clone_repository(remote.to_str().unwrap(), &repo)
+            .unwrap();
+        GitClient
+            .create_branch(&repo, "nord", "origin/main")
+            .unwrap();
+
+        assert_eq!(
+            GitClient.existing_branches(&repo).unwrap(),
+            vec![
+                ("nord".to_string(), BranchLocation::Local),
+                (
+                    "main".to_string(),
+                    BranchLocation::Remote("origin".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_git_backend_from_config() {
+        #[derive(Deserialize)]
+        struct Config {
+            git_backend: GitBackend,
+        }
+
+        let config: Config = toml::from_str("git_backend = \"gix\"").unwrap();
+        assert_eq!(config.git_backend, GitBackend::Gix);
+        assert!(GitBackend::System.client().is_ok());
+        assert_eq!(
+            GitBackend::Gix.client().is_ok(),
+            cfg!(feature = "gix-backend")
+        );
+    }
+
+    #[test]
+    fn test_is_valid_branch_name() {
+        let git_client = GitClient;
+
+        // Valid branch names
+        assert!(git_client.is_valid_branch_name("feature/123").is_ok());
+        assert!(git_client.is_valid_branch_name("hotfix-456").is_ok());
+        assert!(git_client.is_valid_branch_name("release_1.0").is_ok());
+
+        // Invalid branch names
+        assert!(git_client.is_valid_branch_name("").is_err());
+        assert!(git_client.is_valid_branch_name(" ").is_err());
+        assert!(git_client
+            .is_valid_branch_name("/start-with-slash")
+            .is_err());
+        assert!(git_client.is_valid_branch_name("end-with-slash/").is_err());
+        assert!(git_client.is_valid_branch_name("double..dot").is_err());
+        assert!(git_client.is_valid_branch_name("contains space").is_err());
+        assert!(git_client.is_valid_branch_name("invalid*char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid?char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid:char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid[char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid\\char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid^char").is_err());
+        assert!(git_client.is_valid_branch_name("invalid~char").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_branch_name_matches_git() {
+        let git_client = GitClient;
+
+        for line in include_str!("fixtures/ref_names.txt").lines() {
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            let (expected, name) = line.split_once(' ').unwrap();
+            assert_eq!(
+                git_client.is_valid_branch_name(name).is_ok(),
+                expected == "valid",
+                "`git check-ref-format` says '{}' is {}",
+                name,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_valid_branch_name_errors() {
+        let git_client = GitClient;
+        let cases = [
+            ("@", "Branch name cannot be '@'"),
+            ("HEAD", "Branch name cannot be 'HEAD'"),
+            ("-nord", "Branch name cannot start with '-'"),
+            ("nord.", "Branch name cannot end with '.'"),
+            (
+                "nord//dark",
+                "Branch name cannot contain two consecutive slashes '//'",
+            ),
+            ("nord@{1}", "Branch name cannot contain '@{'"),
+            ("nord/.dark", "No part of a branch name can start with '.'"),
+            (
+                "nord.lock/dark",
+                "No part of a branch name can end with '.lock'",
+            ),
+            (
+                "nord\tdark",
+                "Branch name cannot contain control characters",
+            ),
+            (
+                "nord\u{7f}",
+                "Branch name cannot contain control characters",
+            ),
+            ("nord~1", "Branch name cannot contain '~'"),
+        ];
+
+        for (name, message) in cases {
+            assert_eq!(
+                git_client
+                    .is_valid_branch_name(name)
+                    .unwrap_err()
+                    .to_string(),
+                message
+            );
+        }
+    }
+}
@@ -1,3 +1,4 @@
 // Submodules
+pub mod dry_run;
 pub mod file_system;
 pub mod git;
@@ -0,0 +1,291 @@
+// Standard library imports
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// External crate imports
+use anstyle::Style;
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use cliclack;
+use crossterm::style::{style, Stylize};
+
+// Local module imports
+use crate::{
+    apply::{apply, relocate},
+    clients::{file_system::FileSystem, git::Git},
+    command::log::paint_patch_line,
+    config::{ConfigLoader, ConflictPolicy, TomlConfig},
+    history::PatchLine,
+    journal::Journal,
+    ui::cli::{paint, use_colour, ERROR, HEADER, LITERAL, PLACEHOLDER},
+};
+
+/// Deploy the active profile's files to their locations on this machine.
+#[derive(Parser, Debug)]
+pub struct ApplyCommand {
+    /// What to do with existing files, instead of the profile's conflict policy.
+    #[arg(long, value_enum)]
+    conflict: Option<ConflictPolicy>,
+
+    /// Finish an apply that did not complete.
+    #[arg(long, conflicts_with_all = ["roll_back", "conflict"])]
+    resume: bool,
+
+    /// Put back the files changed by an apply that did not complete.
+    #[arg(long, conflicts_with = "conflict")]
+    roll_back: bool,
+}
+
+/// What to do with an apply that did not complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    Resume,
+    RollBack,
+    Later,
+}
+
+impl ApplyCommand {
+    /// Writes every file of the active profile to its location and deploys its externals.
+    ///
+    /// The files are first moved to where the profile's mappings place them on
+    /// this machine, saving the new locations. Existing files are handled by the
+    /// conflict policy. Every entry is listed as created, updated, unchanged,
+    /// skipped or failed.
+    ///
+    /// With `--resume` or `--roll-back`, an apply that did not complete is
+    /// finished or undone instead.
+    ///
+    /// # Arguments
+    /// * `self` - The ApplyCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - Nothing has been committed for the profile yet.
+    /// - An earlier apply did not complete, or there is none to resume or roll back.
+    /// - The conflict policy is `ask`, but there is no terminal to ask in.
+    /// - Any entry could not be applied.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        if self.resume || self.roll_back {
+            let Some(journal) = Journal::pending(fs, config.state_dir()).await? else {
+                bail!("There is no unfinished `dotty apply` to resume or roll back.");
+            };
+            let recovery = match self.resume {
+                true => Recovery::Resume,
+                false => Recovery::RollBack,
+            };
+            return recover(journal, recovery, fs).await;
+        }
+
+        if offer_recovery(&config, fs).await? == Some(Recovery::Later) {
+            cliclack::log::info("Nothing was applied.")?;
+            return Ok(());
+        }
+
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        let policy = self.conflict.unwrap_or(config.active_profile()?.conflict);
+        if policy == ConflictPolicy::Ask && !io::stdin().is_terminal() {
+            bail!("Dotty can only ask about existing files in a terminal. Use `--conflict` to choose a policy.");
+        }
+
+        let home = home::home_dir().context("Unable to access the home directory.")?;
+        let moved = relocate(&mut config, &home, git.as_ref())?;
+        if !moved.is_empty() {
+            config.save(fs, loader).await?;
+        }
+        for (from, to) in &moved {
+            cliclack::log::info(format!("{} moved to {}", from.display(), to.display()))?;
+        }
+
+        let colour = use_colour();
+        let report = apply(
+            &config,
+            &home,
+            fs,
+            git.as_ref(),
+            self.conflict,
+            &mut |target, changes| ask(target, changes, colour),
+        )
+        .await?;
+
+        let groups = [
+            ("Created", LITERAL, &report.created),
+            ("Updated", HEADER, &report.updated),
+            ("Unchanged", PLACEHOLDER, &report.unchanged),
+            ("Skipped", PLACEHOLDER, &report.skipped),
+        ];
+        for (label, style, paths) in groups {
+            print_group(
+                label,
+                style,
+                paths.iter().map(|path| (path, None)),
+                &home,
+                colour,
+            );
+        }
+
+        print_group(
+            "Failed",
+            ERROR,
+            report
+                .failed
+                .iter()
+                .map(|(path, error)| (path, Some(format!("{:#}", error)))),
+            &home,
+            colour,
+        );
+
+        let total = report.created.len()
+            + report.updated.len()
+            + report.unchanged.len()
+            + report.skipped.len()
+            + report.failed.len();
+        if total == 0 {
+            println!("No files are tracked yet. Use `dotty track` to add some.");
+        }
+
+        if !report.failed.is_empty() {
+            bail!(
+                "{} of {} entries could not be applied.",
+                report.failed.len(),
+                total
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Offers to resume or roll back an apply that did not complete, if there is one.
+///
+/// Outside of a terminal, this only warns about it.
+///
+/// # Returns
+/// Returns what the user chose, or `None` when there was nothing to choose.
+///
+/// # Errors
+/// This function will return an error if the journal cannot be read, or
+/// resuming or rolling back fails.
+///
+pub async fn offer_recovery(config: &TomlConfig, fs: &impl FileSystem) -> Result<Option<Recovery>> {
+    let Some(journal) = Journal::pending(fs, config.state_dir()).await? else {
+        return Ok(None);
+    };
+
+    let message = format!(
+        "An apply started {} did not complete; {} of {} files are left.",
+        journal.created.format("%Y-%m-%d %H:%M:%S"),
+        journal.remaining(),
+        journal.targets().count()
+    );
+    if !io::stdin().is_terminal() {
+        cliclack::log::warning(format!(
+            "{} Run `dotty apply --resume` or `dotty apply --roll-back`.",
+            message
+        ))?;
+        return Ok(None);
+    }
+
+    cliclack::log::warning(message)?;
+    let recovery = cliclack::select(style("What should be done with it?").bold())
+        .items(&[
+            (Recovery::Resume, "Resume", "Apply the remaining files"),
+            (
+                Recovery::RollBack,
+                "Roll back",
+                "Put back the files it changed",
+            ),
+            (Recovery::Later, "Later", "Leave it for now"),
+        ])
+        .interact()?;
+
+    recover(journal, recovery, fs).await?;
+    Ok(Some(recovery))
+}
+
+async fn recover(journal: Journal, recovery: Recovery, fs: &impl FileSystem) -> Result<()> {
+    match recovery {
+        Recovery::Resume => {
+            journal.complete(fs).await?;
+            cliclack::log::success("Finished the apply.")?;
+        }
+        Recovery::RollBack => {
+            journal.roll_back(fs).await?;
+            cliclack::log::success("Put back the files the apply changed.")?;
+        }
+        Recovery::Later => {}
+    }
+
+    Ok(())
+}
+
+/// Shows how applying would change the existing file at `target` and asks what to do with it.
+fn ask(target: &Path, changes: &[PatchLine], colour: bool) -> Result<ConflictPolicy> {
+    for line in changes {
+        println!("{}", paint_patch_line(line, colour));
+    }
+
+    let policy = cliclack::select(
+        style(format!(
+            "{} already exists. What should be done with it?",
+            target.display()
+        ))
+        .bold(),
+    )
+    .items(&[
+        (
+            ConflictPolicy::Backup,
+            "Back up and replace",
+            "Keep a copy you can restore with `dotty backups`",
+        ),
+        (ConflictPolicy::Overwrite, "Replace", "Without a backup"),
+        (ConflictPolicy::Skip, "Skip", "Keep the existing file"),
+    ])
+    .interact()?;
+
+    Ok(policy)
+}
+
+/// Prints the paths of one kind of outcome under a heading, if there are any.
+fn print_group<'a>(
+    label: &str,
+    style: Style,
+    entries: impl Iterator<Item = (&'a PathBuf, Option<String>)>,
+    home: &Path,
+    colour: bool,
+) {
+    let mut entries = entries.peekable();
+    if entries.peek().is_none() {
+        return;
+    }
+
+    println!("{}", paint(HEADER, &format!("{}:", label), colour));
+    for (path, reason) in entries {
+        let path = match path.strip_prefix(home) {
+            Ok(relative) => format!("~/{}", relative.display()),
+            Err(_) => path.display().to_string(),
+        };
+
+        match reason {
+            Some(reason) => println!("    {}: {}", paint(style, &path, colour), reason),
+            None => println!("    {}", paint(style, &path, colour)),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use cliclack;
+
+// Local module imports
+use crate::{
+    backup::{list, restore},
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    ui::cli::{paint, use_colour, HEADER, PLACEHOLDER},
+};
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+pub enum Command {
+    /// List the files Dotty has backed up, oldest first.
+    #[default]
+    List,
+
+    /// Copy a backup back to where the file was.
+    Restore,
+}
+
+/// List and restore the files Dotty backed up before replacing them.
+#[derive(Parser, Debug)]
+pub struct BackupsCommand {
+    #[clap(default_value_t, value_enum)]
+    command: Command,
+
+    /// The number of the backup to restore, as listed.
+    number: Option<usize>,
+}
+
+impl BackupsCommand {
+    /// Lists the recorded backups, or restores one of them.
+    ///
+    /// # Arguments
+    /// * `self` - The BackupsCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - The backup index cannot be read.
+    /// - There is no backup with the given number, or none was given.
+    /// - The backup cannot be copied back.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        _git: Arc<dyn Git>,
+    ) -> Result<()> {
+        match self.command {
+            Command::List => {
+                let backups = list(fs, config.state_dir()).await?;
+                let colour = use_colour();
+
+                if backups.is_empty() {
+                    println!("Dotty has not backed up any files.");
+                }
+
+                for (index, backup) in backups.iter().enumerate() {
+                    println!(
+                        "{}\t{}\t{}",
+                        paint(HEADER, &format!("#{}", index + 1), colour),
+                        paint(
+                            PLACEHOLDER,
+                            &backup.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+                            colour
+                        ),
+                        backup.source.display()
+                    );
+                }
+            }
+            Command::Restore => {
+                let Some(number) = self.number else {
+                    bail!(
+                        "Give the number of the backup to restore, as listed by `dotty backups`."
+                    );
+                };
+
+                let (backup, replaced) = restore(fs, config.state_dir(), number).await?;
+                if let Some(replaced) = replaced {
+                    cliclack::log::info(format!(
+                        "Backed up the current version to {}",
+                        replaced.display()
+                    ))?;
+                }
+                cliclack::log::success(format!(
+                    "Restored {} from the backup made {}.",
+                    backup.source.display(),
+                    backup.created.format("%Y-%m-%d %H:%M:%S")
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+}
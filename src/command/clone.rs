@@ -0,0 +1,97 @@
+// Standard library imports
+use std::{env, path::Path, sync::Arc};
+
+// External crate imports
+use anyhow::{Context, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{wizard::list_profiles_wizard, ConfigLoader, TomlConfig},
+    manifest::resolve_path,
+    repository,
+};
+
+/// Set up this machine from an existing dotfiles remote.
+#[derive(Parser, Debug)]
+pub struct CloneCommand {
+    /// URL or path of the remote repository.
+    url: String,
+}
+
+impl CloneCommand {
+    /// Clones the remote into the base path and creates a profile for each of its branches.
+    ///
+    /// When more than one profile is available the user picks the active one, and
+    /// its branch is checked out.
+    ///
+    /// # Arguments
+    /// * `self` - The CloneCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - The base path already contains a repository.
+    /// - The remote cannot be cloned or the branch cannot be checked out.
+    /// - Writing the config file fails.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let home = home::home_dir().context("Unable to access the home directory.")?;
+
+        // Relative paths must not be resolved against the base path by Git.
+        let url = match Path::new(&self.url) {
+            path if path.exists() => {
+                let cwd = env::current_dir().context("Unable to access the current directory.")?;
+                resolve_path(path, &cwd).to_string_lossy().to_string()
+            }
+            _ => self.url,
+        };
+
+        let added = repository::clone(&mut config, &url, git.as_ref(), &home)?;
+        cliclack::log::success(format!(
+            "Cloned {} into {}",
+            url,
+            config.base_path.display()
+        ))?;
+
+        if config.profiles.is_empty() {
+            config.save(fs, loader).await?;
+            cliclack::log::warning(
+                "The remote has no branches yet. Create a profile with `dotty config profile`.",
+            )?;
+            return Ok(());
+        }
+
+        for id in &added {
+            cliclack::log::info(format!("Found profile {}", id))?;
+        }
+
+        if config.profiles.len() == 1 {
+            config.active_profile = config.profiles.keys().next().cloned().unwrap_or_default();
+        } else if !config.profiles.contains_key(&config.active_profile) {
+            config = list_profiles_wizard(config).await?;
+        }
+
+        let branch = config.active_profile()?.branch.clone();
+        git.checkout(&config.base_path, &branch)?;
+        config.save(fs, loader).await?;
+
+        cliclack::log::success(format!(
+            "Checked out '{}' for profile {}",
+            branch, config.active_profile
+        ))?;
+
+        Ok(())
+    }
+}
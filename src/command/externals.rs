@@ -0,0 +1,108 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    externals::{load, update},
+    ui::cli::{paint, use_colour, HEADER, PLACEHOLDER},
+};
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+pub enum Command {
+    /// List the externals of the active profile and their pins.
+    #[default]
+    List,
+
+    /// Pin externals to the tip of their branch and commit the new pins.
+    Update,
+}
+
+/// Manage the third-party repositories pinned by the active profile.
+#[derive(Parser, Debug)]
+pub struct ExternalsCommand {
+    #[clap(default_value_t, value_enum)]
+    command: Command,
+
+    /// Only update these externals.
+    names: Vec<String>,
+}
+
+impl ExternalsCommand {
+    /// Lists or updates the externals declared in the profile's `externals.toml`.
+    ///
+    /// # Arguments
+    /// * `self` - The ExternalsCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - `externals.toml` cannot be parsed.
+    /// - An external cannot be fetched or checked out.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        config.active_profile()?;
+
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        match self.command {
+            Command::List => {
+                let externals = load(fs, &config.base_path).await?.externals;
+                let colour = use_colour();
+
+                if externals.is_empty() {
+                    println!("Profile {} has no externals.", config.active_profile);
+                }
+
+                for external in externals {
+                    let rev = match &external.rev {
+                        Some(rev) => format!("{:.7}", rev),
+                        None => paint(PLACEHOLDER, "not pinned", colour),
+                    };
+                    println!(
+                        "{}\t{}\t{}",
+                        paint(HEADER, &external.name, colour),
+                        external.path.display(),
+                        rev
+                    );
+                }
+            }
+            Command::Update => {
+                let home = home::home_dir().context("Unable to access the home directory.")?;
+                let report = update(&config, &home, fs, git.as_ref(), &self.names).await?;
+
+                for bump in &report.bumped {
+                    let from = bump.from.as_deref().unwrap_or("unpinned");
+                    cliclack::log::info(format!("{}: {:.7} -> {:.7}", bump.name, from, bump.to))?;
+                }
+
+                match report.commit {
+                    Some(id) => {
+                        cliclack::log::success(format!("Committed the new pins ({:.7})", id))?
+                    }
+                    None => cliclack::log::info("Every external is already up to date.")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
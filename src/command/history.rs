@@ -0,0 +1,72 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::Result;
+use clap::Parser;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    journal::Journal,
+    repository::message::file_count,
+    ui::cli::{paint, use_colour, HEADER, PLACEHOLDER},
+};
+
+/// List the applies and profile switches that can be undone.
+#[derive(Parser, Debug)]
+pub struct HistoryCommand {}
+
+impl HistoryCommand {
+    /// Lists the snapshots, newest first, with their time, profile and number of files.
+    ///
+    /// # Arguments
+    /// * `self` - The HistoryCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if a snapshot cannot be read.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        _git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let snapshots = Journal::snapshots(fs, config.state_dir()).await?;
+        let colour = use_colour();
+
+        if snapshots.is_empty() {
+            println!("There is nothing to undo yet.");
+        }
+
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            let description = match &snapshot.switched_from {
+                Some(from) => format!("switch\t{} -> {}", from, snapshot.profile),
+                None => format!(
+                    "apply\t{}\t{}",
+                    snapshot.profile,
+                    file_count(snapshot.targets().count())
+                ),
+            };
+
+            println!(
+                "{}\t{}\t{}",
+                paint(HEADER, &format!("#{}", index + 1), colour),
+                paint(
+                    PLACEHOLDER,
+                    &snapshot.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    colour
+                ),
+                description
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::Result;
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    repository,
+};
+
+/// Create the dotfiles repository inside the base path.
+#[derive(Parser, Debug)]
+pub struct InitCommand {}
+
+impl InitCommand {
+    /// Initialises the dotfiles repository, or reuses the one that already exists.
+    ///
+    /// # Arguments
+    /// * `self` - The InitCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile.
+    /// - The repository cannot be created.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let path = config.base_path.display();
+
+        if repository::init(&config, fs, git.as_ref()).await? {
+            cliclack::log::success(format!("Created a dotfiles repository in {}", path))?;
+        } else {
+            cliclack::log::info(format!("Using the existing repository in {}", path))?;
+        }
+
+        Ok(())
+    }
+}
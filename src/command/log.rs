@@ -0,0 +1,118 @@
+// Standard library imports
+use std::{env, path::PathBuf, sync::Arc};
+
+// External crate imports
+use anstyle::Style;
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    history::{history, PatchLine},
+    manifest::resolve_path,
+    ui::cli::{paint, use_colour, ERROR, HEADER, LITERAL, PLACEHOLDER},
+};
+
+/// Show the commits that changed a tracked file.
+#[derive(Parser, Debug)]
+pub struct LogCommand {
+    /// The tracked file.
+    file: PathBuf,
+
+    /// Show the full diff of every commit.
+    #[arg(long)]
+    patch: bool,
+}
+
+impl LogCommand {
+    /// Lists the commits on the active profile's branch that changed the file.
+    ///
+    /// Each commit is shown with its date, author, message and a diffstat, or
+    /// the full diff with `--patch`.
+    ///
+    /// # Arguments
+    /// * `self` - The LogCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - The file is not tracked by the active profile.
+    /// - The repository cannot be read.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        _fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let profile = config.active_profile()?;
+
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        let cwd = env::current_dir().context("Unable to access the current directory.")?;
+        let source = resolve_path(&self.file, &cwd);
+        let changes = history(profile, &config.base_path, git.as_ref(), &source)?;
+        let colour = use_colour();
+
+        if changes.is_empty() {
+            println!("{} has not been committed yet.", source.display());
+        }
+
+        for (index, change) in changes.iter().enumerate() {
+            let commit = &change.commit;
+            if index > 0 {
+                println!();
+            }
+
+            println!(
+                "{}",
+                paint(HEADER, &format!("commit {}", commit.id), colour)
+            );
+            println!("Author: {}", commit.author);
+            println!("Date:   {}", commit.date.format("%a %b %e %H:%M:%S %Y %z"));
+            println!();
+            for line in commit.message.lines() {
+                println!("    {}", line);
+            }
+            println!();
+
+            if self.patch {
+                for line in change.patch() {
+                    println!("{}", paint_patch_line(&line, colour));
+                }
+            } else {
+                let (added, removed) = change.diffstat();
+                println!(
+                    " {} | {} {}",
+                    source.file_name().unwrap_or_default().to_string_lossy(),
+                    paint(LITERAL, &format!("+{}", added), colour),
+                    paint(ERROR, &format!("-{}", removed), colour),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders a line of a unified diff in the colours used by `git diff`.
+pub(crate) fn paint_patch_line(line: &PatchLine, enabled: bool) -> String {
+    let hunk = Style::new().fg_color(HEADER.get_fg_color());
+
+    match line {
+        PatchLine::Header(text) => paint(PLACEHOLDER, text, enabled),
+        PatchLine::Hunk(text) => paint(hunk, text, enabled),
+        PatchLine::Added(text) => paint(LITERAL, text, enabled),
+        PatchLine::Removed(text) => paint(ERROR, text, enabled),
+        PatchLine::Context(text) => text.clone(),
+    }
+}
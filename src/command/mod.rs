@@ -0,0 +1,15 @@
+// Submodules
+pub mod apply;
+pub mod backups;
+pub mod clone;
+pub mod externals;
+pub mod history;
+pub mod init;
+pub mod log;
+pub mod push;
+pub mod restore;
+pub mod status;
+pub mod sync;
+pub mod track;
+pub mod undo;
+pub mod untrack;
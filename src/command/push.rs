@@ -0,0 +1,63 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::{bail, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    repository::sync::push,
+};
+
+/// Push the active profile to its remotes without syncing.
+#[derive(Parser, Debug)]
+pub struct PushCommand {
+    /// Only push to this remote.
+    #[arg(long)]
+    remote: Option<String>,
+}
+
+impl PushCommand {
+    /// Pushes the active profile's branch to every remote not set to `never`.
+    ///
+    /// Unlike `dotty sync`, nothing is committed or pulled, so this also reaches
+    /// remotes whose push policy is `manual`.
+    ///
+    /// # Arguments
+    /// * `self` - The PushCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - The remote is unknown or set to never be pushed to.
+    /// - A push is rejected or fails.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        _fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        let pushed = push(&config, git.as_ref(), self.remote.as_deref())?;
+
+        match pushed.is_empty() {
+            true => cliclack::log::warning("Every remote is set to never be pushed to.")?,
+            false => cliclack::log::success(format!("Pushed to {}.", pushed.join(", ")))?,
+        }
+
+        Ok(())
+    }
+}
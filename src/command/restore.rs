@@ -0,0 +1,79 @@
+// Standard library imports
+use std::{env, path::PathBuf, sync::Arc};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    history::restore,
+    manifest::resolve_path,
+};
+
+/// Restore a tracked file to an earlier version.
+#[derive(Parser, Debug)]
+pub struct RestoreCommand {
+    /// The tracked file.
+    file: PathBuf,
+
+    /// A commit or tag on the profile's branch, or a date such as "2 days ago" or "2024-01-31".
+    #[arg(long)]
+    at: String,
+}
+
+impl RestoreCommand {
+    /// Deploys an earlier version of the file to its location on disk.
+    ///
+    /// The current contents are backed up first, and `dotty undo` reverts the
+    /// restore. The restored version is not committed; run `dotty sync` to keep it.
+    ///
+    /// # Arguments
+    /// * `self` - The RestoreCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - The file is not tracked, or did not exist at the given revision.
+    /// - The revision cannot be resolved, or is not on the profile's branch.
+    /// - The file cannot be backed up or deployed.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        config.active_profile()?;
+
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        let cwd = env::current_dir().context("Unable to access the current directory.")?;
+        let source = resolve_path(&self.file, &cwd);
+        let restored = restore(&config, fs, git.as_ref(), &source, &self.at).await?;
+
+        if let Some(backup) = restored.backup {
+            cliclack::log::info(format!(
+                "Backed up the current version to {}",
+                backup.display()
+            ))?;
+        }
+        cliclack::log::success(format!(
+            "Restored {} from {:.12}. Run `dotty sync` to keep this version.",
+            source.display(),
+            restored.revision
+        ))?;
+
+        Ok(())
+    }
+}
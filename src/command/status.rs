@@ -0,0 +1,95 @@
+// Standard library imports
+use std::{path::Path, sync::Arc};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    status::{status, FileReport},
+    ui::cli::{paint, use_colour, HEADER},
+};
+
+/// Show how the deployed dotfiles differ from the active profile.
+#[derive(Parser, Debug)]
+pub struct StatusCommand {
+    /// Print one tab-separated `<status> <path>` line per file, for scripts.
+    #[arg(long)]
+    porcelain: bool,
+}
+
+impl StatusCommand {
+    /// Compares every file of the active profile with its committed version.
+    ///
+    /// # Arguments
+    /// * `self` - The StatusCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile.
+    /// - The base path is not a repository.
+    /// - A tracked file or the repository cannot be read.
+    ///
+    pub async fn execute(
+        self,
+        config: TomlConfig,
+        fs: &impl FileSystem,
+        _loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let profile = config.active_profile()?;
+
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        let reports = status(profile, &config.base_path, fs, git.as_ref()).await?;
+
+        if self.porcelain {
+            for report in &reports {
+                println!("{}\t{}", report.status.code(), report.source.display());
+            }
+            return Ok(());
+        }
+
+        let home = home::home_dir().context("Unable to access the home directory.")?;
+        let colour = use_colour();
+
+        println!(
+            "On profile {} (branch '{}')",
+            config.active_profile, profile.branch
+        );
+
+        for group in reports.chunk_by(|a, b| a.status == b.status) {
+            let status = group[0].status;
+            println!();
+            println!("{}", paint(HEADER, &format!("{}:", status.label()), colour));
+
+            for FileReport { source, .. } in group {
+                let path = display_path(source, &home);
+                println!("    {}", paint(status.style(), &path, colour));
+            }
+        }
+
+        if reports.is_empty() {
+            println!("No files are tracked yet. Use `dotty track` to add some.");
+        }
+
+        Ok(())
+    }
+}
+
+/// Shortens paths under `home` to start with `~`.
+fn display_path(path: &Path, home: &Path) -> String {
+    match path.strip_prefix(home) {
+        Ok(relative) => format!("~/{}", relative.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
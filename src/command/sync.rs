@@ -0,0 +1,104 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError},
+    },
+    config::{ConfigLoader, TomlConfig},
+    mappings,
+    repository::sync::sync,
+};
+
+/// Commit local changes, pull the remote and push the active profile.
+#[derive(Parser, Debug)]
+pub struct SyncCommand {}
+
+impl SyncCommand {
+    /// Runs a full sync of the active profile.
+    ///
+    /// When the remote has conflicting changes the sync stops before pushing and
+    /// lists the conflicting files. The repository is left as it was after the
+    /// local commit, so nothing is half-merged.
+    ///
+    /// # Arguments
+    /// * `self` - The SyncCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile or no repository.
+    /// - The remote has conflicting changes.
+    /// - Copying the files or any Git operation fails.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        if !git.is_repository(&config.base_path) {
+            bail!("There is no dotfiles repository yet. Run `dotty init` or `dotty clone` first.");
+        }
+
+        // Files tracked before modes were recorded get the mode they have now.
+        if config.active_profile_mut()?.record_modes(fs).await? {
+            config.save(fs, loader).await?;
+        }
+
+        let report = match sync(&config, fs, git.as_ref()).await {
+            Ok(report) => report,
+            Err(error) => {
+                let Some(GitError::MergeConflict(files)) = error.downcast_ref() else {
+                    return Err(error);
+                };
+
+                let home = home::home_dir().context("Unable to access the home directory.")?;
+                let mappings = mappings::load(fs, &config.base_path).await?;
+                for file in files {
+                    let path = mappings
+                        .source_for(file, &home)
+                        .unwrap_or_else(|| file.clone());
+                    cliclack::log::error(format!("Conflict in {}", path.display()))?;
+                }
+
+                bail!(
+                    "Sync stopped because the remote changed the same files. \
+                    Your changes are committed locally; nothing was pushed."
+                );
+            }
+        };
+
+        match report.commit {
+            Some(id) => cliclack::log::success(format!("Committed local changes ({:.7})", id))?,
+            None => cliclack::log::info("No local changes to commit.")?,
+        }
+
+        if report.pulled.is_empty() {
+            cliclack::log::warning("No remote is configured, so the changes were only committed.")?;
+        } else if report.pushed.is_empty() {
+            cliclack::log::success(format!(
+                "Pulled the latest changes from {}. No remote is set to push on sync.",
+                report.pulled.join(", ")
+            ))?;
+        } else {
+            cliclack::log::success(format!(
+                "Pulled the latest changes and pushed to {}.",
+                report.pushed.join(", ")
+            ))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+// Standard library imports
+use std::{env, path::PathBuf, sync::Arc};
+
+// External crate imports
+use anyhow::{Context, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, Strategy, TomlConfig},
+    manifest::resolve_path,
+    mappings,
+};
+
+/// Start tracking one or more files in the active profile.
+#[derive(Parser, Debug)]
+pub struct TrackCommand {
+    /// Files or directories to track. Directories are tracked recursively.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// How the files are deployed, instead of the profile's default strategy.
+    /// Also changes the strategy of files that are already tracked.
+    #[arg(long, value_enum)]
+    strategy: Option<Strategy>,
+}
+
+impl TrackCommand {
+    /// Adds the given paths to the active profile's manifest.
+    ///
+    /// Every path is resolved against the current directory and validated before
+    /// the configuration is written, so a single bad path leaves the manifest
+    /// unchanged.
+    ///
+    /// # Arguments
+    /// * `self` - The TrackCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile.
+    /// - A path does not exist or is outside of the home directory.
+    /// - Writing the config file fails.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        _git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let cwd = env::current_dir().context("Unable to access the current directory.")?;
+        let home = home::home_dir().context("Unable to access the home directory.")?;
+
+        let mappings = mappings::load(fs, &config.base_path).await?;
+        let profile = config.active_profile_mut()?;
+        let paths: Vec<_> = self
+            .paths
+            .iter()
+            .map(|path| resolve_path(path, &cwd))
+            .collect();
+        let mut tracked = Vec::new();
+
+        for path in &paths {
+            tracked.extend(profile.track(fs, path, &home, &mappings).await?);
+        }
+
+        let mut changed = 0;
+        if let Some(strategy) = self.strategy {
+            for file in &mut profile.files {
+                if paths.iter().any(|path| file.source.starts_with(path)) {
+                    changed += usize::from(file.strategy != Some(strategy));
+                    file.strategy = Some(strategy);
+                }
+            }
+        }
+
+        config.save(fs, loader).await?;
+
+        for file in &tracked {
+            cliclack::log::success(format!("Tracking {}", file.source.display()))?;
+        }
+
+        if let Some(strategy) = self.strategy.filter(|_| changed > tracked.len()) {
+            cliclack::log::success(format!(
+                "Deploying {} already tracked files with the {} strategy",
+                changed - tracked.len(),
+                strategy
+            ))?;
+        } else if tracked.is_empty() {
+            cliclack::log::info("All of the given files are already tracked.")?;
+        }
+
+        Ok(())
+    }
+}
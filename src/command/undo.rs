@@ -0,0 +1,68 @@
+// Standard library imports
+use std::sync::Arc;
+
+// External crate imports
+use anyhow::Result;
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    journal::undo,
+    repository::message::file_count,
+};
+
+/// Undo the latest applies and profile switches.
+#[derive(Parser, Debug)]
+pub struct UndoCommand {
+    /// How many snapshots to undo, newest first.
+    #[arg(long, default_value_t = 1)]
+    steps: usize,
+}
+
+impl UndoCommand {
+    /// Rolls back the newest snapshots, as listed by `dotty history`.
+    ///
+    /// # Arguments
+    /// * `self` - The UndoCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There are fewer snapshots than steps to undo.
+    /// - A snapshot cannot be rolled back.
+    /// - The configuration cannot be saved.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let undone = undo(&mut config, fs, loader, git.as_ref(), self.steps).await?;
+
+        for snapshot in &undone {
+            let created = snapshot.created.format("%Y-%m-%d %H:%M:%S");
+            match &snapshot.switched_from {
+                Some(from) => cliclack::log::success(format!(
+                    "Switched back from profile {} to {} ({})",
+                    snapshot.profile, from, created
+                ))?,
+                None => cliclack::log::success(format!(
+                    "Put back {} changed by applying profile {} ({})",
+                    file_count(snapshot.targets().count()),
+                    snapshot.profile,
+                    created
+                ))?,
+            }
+        }
+
+        Ok(())
+    }
+}
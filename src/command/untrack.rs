@@ -0,0 +1,66 @@
+// Standard library imports
+use std::{env, path::PathBuf, sync::Arc};
+
+// External crate imports
+use anyhow::{Context, Result};
+use clap::Parser;
+use cliclack;
+
+// Local module imports
+use crate::{
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, TomlConfig},
+    manifest::resolve_path,
+};
+
+/// Stop tracking one or more files in the active profile.
+#[derive(Parser, Debug)]
+pub struct UntrackCommand {
+    /// Files or directories to stop tracking.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+impl UntrackCommand {
+    /// Removes the given paths from the active profile's manifest.
+    ///
+    /// The files themselves are left untouched on disk.
+    ///
+    /// # Arguments
+    /// * `self` - The UntrackCommand instance.
+    /// * `config` - The current TomlConfig.
+    /// * `fs` - An implementation of FileSystem for file operations.
+    /// * `loader` - An implementation of ConfigLoader for serializing the config.
+    /// * `git` - An implementation of Git for interacting with the Git API.
+    ///
+    /// # Errors
+    /// This function may return an error if:
+    /// - There is no active profile.
+    /// - A path is not tracked by the active profile.
+    /// - Writing the config file fails.
+    ///
+    pub async fn execute(
+        self,
+        mut config: TomlConfig,
+        fs: &impl FileSystem,
+        loader: &impl ConfigLoader,
+        _git: Arc<dyn Git>,
+    ) -> Result<()> {
+        let cwd = env::current_dir().context("Unable to access the current directory.")?;
+
+        let profile = config.active_profile_mut()?;
+        let mut removed = Vec::new();
+
+        for path in &self.paths {
+            removed.extend(profile.untrack(&resolve_path(path, &cwd))?);
+        }
+
+        config.save(fs, loader).await?;
+
+        for file in &removed {
+            cliclack::log::success(format!("No longer tracking {}", file.source.display()))?;
+        }
+
+        Ok(())
+    }
+}
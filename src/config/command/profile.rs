@@ -16,6 +16,8 @@ use crate::{
         },
         ConfigLoader, TomlConfig,
     },
+    journal::Journal,
+    repository::branches::update_branches,
 };
 
 #[derive(ValueEnum, Default, Debug, Clone)]
@@ -60,6 +62,13 @@ impl ProfileCommand {
                 if config.active_profile != current_profile {
                     fs.write(&config.base_path.join("config.toml"), &contents)
                         .await?;
+                    Journal::record_switch(
+                        fs,
+                        config.state_dir(),
+                        &current_profile,
+                        &config.active_profile,
+                    )
+                    .await?;
 
                     cliclack::outro(
                         style(format!(
@@ -72,7 +81,9 @@ impl ProfileCommand {
                 }
             }
             Command::Create => {
-                let config = new_profile_wizard(config, git).await?;
+                let previous = config.clone();
+                let config = new_profile_wizard(config, git.clone()).await?;
+                update_branches(&previous, &config, git.as_ref()).map_err(unchanged)?;
                 let contents = loader.config_to_string(&config)?;
 
                 fs.write(&config.base_path.join("config.toml"), &contents)
@@ -81,10 +92,12 @@ impl ProfileCommand {
                 cliclack::outro(style("A new profile has been created!").green().bold())?;
             }
             Command::Delete => {
+                let previous = config.clone();
                 let profiles = select_profiles_wizard(&config).await?;
                 for profile in &profiles {
                     config.profiles.remove(profile);
                 }
+                update_branches(&previous, &config, git.as_ref()).map_err(unchanged)?;
 
                 let contents = loader.config_to_string(&config)?;
 
@@ -98,7 +111,9 @@ impl ProfileCommand {
                 )?;
             }
             Command::Update => {
-                let config = update_profile_wizard(config, git).await?;
+                let previous = config.clone();
+                let config = update_profile_wizard(config, git.clone()).await?;
+                update_branches(&previous, &config, git.as_ref()).map_err(unchanged)?;
 
                 let contents = loader.config_to_string(&config)?;
 
@@ -112,3 +127,10 @@ impl ProfileCommand {
         Ok(())
     }
 }
+
+/// Notes on a failed branch update that `config.toml` was not touched.
+fn unchanged(error: anyhow::Error) -> anyhow::Error {
+    error.context(
+        "The profile branches could not be updated, so the configuration was left unchanged",
+    )
+}
@@ -45,7 +45,7 @@ impl SetupCommand {
         loader: &impl ConfigLoader,
         git: Arc<dyn Git>,
     ) -> Result<()> {
-        let config = initial_setup_wizard(config, git).await?;
+        let config = initial_setup_wizard(config, fs, git).await?;
         let contents = loader.config_to_string(&config)?;
 
         fs.write(&config.base_path.join("config.toml"), &contents)
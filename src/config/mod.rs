@@ -1,13 +1,14 @@
 // Standard library imports
 use std::{
     collections::{BTreeMap, HashSet},
-    env, fs,
-    path::PathBuf,
+    env, fmt, fs,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 // External crate imports
 use anyhow::{Context, Error, Result};
+use clap::ValueEnum;
 use colored::Colorize;
 use log::{self, LevelFilter};
 use log4rs::{
@@ -19,7 +20,10 @@ use serde::{Deserialize, Serialize};
 use toml;
 
 // Local module imports
-use crate::clients::file_system::FileSystem;
+use crate::{
+    clients::{file_system::FileSystem, git::GitBackend},
+    manifest::TrackedFile,
+};
 
 // Submodules
 pub mod command;
@@ -28,21 +32,121 @@ pub mod wizard;
 pub type ProfileId = String;
 pub type ProfilesMap = BTreeMap<ProfileId, ProfileConfig>;
 
+/// Reads an environment variable.
+type EnvReader = fn(&str) -> Option<String>;
+
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
 pub struct ProfileConfig {
     pub branch: String,
+    #[serde(default)]
+    pub files: Vec<TrackedFile>,
+    /// Directories tracked as a whole, so that new files inside them can be reported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<PathBuf>,
+    /// Remotes the branch is exchanged with. `origin` is used when there are none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remotes: Vec<RemoteConfig>,
+    /// How files are deployed unless a file sets its own strategy.
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// What `dotty apply` does with existing files it would replace.
+    #[serde(default)]
+    pub conflict: ConflictPolicy,
+}
+
+/// A remote that a profile's branch is pulled from and pushed to.
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
+pub struct RemoteConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub push: PushPolicy,
+}
+
+/// When Dotty pushes a profile's branch to a remote.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PushPolicy {
+    /// On every `dotty sync`.
+    #[default]
+    Always,
+    /// Only when running `dotty push`.
+    Manual,
+    /// Never; the remote is only pulled from.
+    Never,
+}
+
+/// How `dotty apply` places a tracked file at its location on disk.
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Serialize, Deserialize, Clone, Copy, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// A symbolic link to the file in the repository.
+    #[default]
+    Symlink,
+    /// A copy of the committed file, for applications that replace or refuse symlinks.
+    Copy,
+    /// A hard link to the file in the repository.
+    Hardlink,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Symlink => "symlink",
+            Self::Copy => "copy",
+            Self::Hardlink => "hardlink",
+        })
+    }
+}
+
+/// What `dotty apply` does when a file it deploys would replace one that is
+/// already on disk and not linked to the repository.
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Serialize, Deserialize, Clone, Copy, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Move the existing file to a backup directory first.
+    #[default]
+    Backup,
+    /// Keep the existing file and leave it out.
+    Skip,
+    /// Replace the existing file without a backup.
+    Overwrite,
+    /// Show the differences and ask.
+    Ask,
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Backup => "backup",
+            Self::Skip => "skip",
+            Self::Overwrite => "overwrite",
+            Self::Ask => "ask",
+        })
+    }
 }
 
 impl Default for ProfileConfig {
     fn default() -> Self {
         Self {
             branch: String::from("main"),
+            files: Vec::new(),
+            directories: Vec::new(),
+            remotes: Vec::new(),
+            strategy: Strategy::default(),
+            conflict: ConflictPolicy::default(),
         }
     }
 }
 
 pub trait ConfigLoader {
     fn get_base_path(&self) -> Result<PathBuf>;
+    fn get_state_path(&self) -> Result<PathBuf>;
+    fn get_cache_path(&self) -> Result<PathBuf>;
     fn config_from_str(&self, content: &str) -> Result<TomlConfig>;
     fn config_to_string(&self, config: &TomlConfig) -> Result<String>;
 }
@@ -54,6 +158,8 @@ impl ConfigLoader for ConfigLoaderClient {
     ///
     /// This function decides the appropriate base path for Dotty's configuration files
     /// based on whether the application is running under Cargo (development mode) or not.
+    /// Outside of development mode, this is `dotty` inside `XDG_CONFIG_HOME`, unless
+    /// only an install that predates XDG support exists in `~/.config/dotty`.
     ///
     /// After determining the path, this function attempts to create all necessary
     /// directories in the path if they don't already exist.
@@ -69,22 +175,30 @@ impl ConfigLoader for ConfigLoaderClient {
     /// - It cannot access the home directory (in production mode).
     /// - It fails to create the necessary directories.
     ///
-    /// # Panics
-    /// This function will panic if it fails to create the required directories.
-    ///
     fn get_base_path(&self) -> Result<PathBuf> {
-        let path = match Self::is_running_under_cargo() {
-            true => env::current_dir()
-                .context("Unable to access the current directory.")?
-                .join(".config/dotty"),
-            _ => home::home_dir()
-                .context("Unable to access the home directory.")?
-                .join(".config/dotty"),
-        };
+        create_dir(Self::base_path()?)
+    }
 
-        fs::create_dir_all(&path).expect("Unable to create directories required by Dotty.");
+    /// Determines and creates the directory for Dotty's log and backups, which
+    /// is `dotty` inside `XDG_STATE_HOME`.
+    ///
+    /// # Errors
+    /// This function will return an error if the home or current directory
+    /// cannot be accessed, or the directory cannot be created.
+    ///
+    fn get_state_path(&self) -> Result<PathBuf> {
+        create_dir(Self::state_path()?)
+    }
 
-        Ok(path)
+    /// Determines and creates the directory for files Dotty can recreate, which
+    /// is `dotty` inside `XDG_CACHE_HOME`.
+    ///
+    /// # Errors
+    /// This function will return an error if the home or current directory
+    /// cannot be accessed, or the directory cannot be created.
+    ///
+    fn get_cache_path(&self) -> Result<PathBuf> {
+        create_dir(Self::cache_path()?)
     }
 
     /// Parses a TOML configuration string into a `TomlConfig` struct.
@@ -136,6 +250,89 @@ impl ConfigLoaderClient {
     pub fn is_running_under_cargo() -> bool {
         env::var("CARGO").is_ok()
     }
+
+    /// Returns the directory `get_base_path` creates, without creating it.
+    pub fn base_path() -> Result<PathBuf> {
+        let (home, env) = Self::root()?;
+
+        Ok(config_dir(&home, &env))
+    }
+
+    /// Returns the directory `get_state_path` creates, without creating it.
+    pub fn state_path() -> Result<PathBuf> {
+        let (home, env) = Self::root()?;
+
+        Ok(xdg_dir("XDG_STATE_HOME", ".local/state", &home, &env).join("dotty"))
+    }
+
+    /// Returns the directory `get_cache_path` creates, without creating it.
+    pub fn cache_path() -> Result<PathBuf> {
+        let (home, env) = Self::root()?;
+
+        Ok(xdg_dir("XDG_CACHE_HOME", ".cache", &home, &env).join("dotty"))
+    }
+
+    /// Returns the directory the XDG directories are resolved under, and how
+    /// environment variables are read.
+    ///
+    /// In development mode, everything is kept inside the current directory and
+    /// the XDG variables are ignored.
+    fn root() -> Result<(PathBuf, EnvReader)> {
+        if Self::is_running_under_cargo() {
+            let env: EnvReader = |_| None;
+            let cwd = env::current_dir().context("Unable to access the current directory.")?;
+            Ok((cwd, env))
+        } else {
+            let env: EnvReader = |name| env::var(name).ok();
+            let home = home::home_dir().context("Unable to access the home directory.")?;
+            Ok((home, env))
+        }
+    }
+}
+
+/// Returns the directory named by the XDG base directory `variable`.
+///
+/// The specification requires `default`, relative to `home`, to be used when
+/// the variable is unset or does not hold an absolute path.
+///
+/// # Arguments
+/// * `variable` - The name of the variable, such as `XDG_CONFIG_HOME`.
+/// * `default` - The directory used instead, relative to `home`.
+/// * `home` - The home directory.
+/// * `env` - Reads an environment variable.
+///
+pub fn xdg_dir(
+    variable: &str,
+    default: &str,
+    home: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> PathBuf {
+    env(variable)
+        .map(PathBuf::from)
+        .filter(|value| value.is_absolute())
+        .unwrap_or_else(|| home.join(default))
+}
+
+/// Returns the directory holding Dotty's configuration.
+///
+/// Installs that predate XDG support keep using `~/.config/dotty` for as long
+/// as `XDG_CONFIG_HOME` points elsewhere and holds no configuration.
+fn config_dir(home: &Path, env: &dyn Fn(&str) -> Option<String>) -> PathBuf {
+    let path = xdg_dir("XDG_CONFIG_HOME", ".config", home, env).join("dotty");
+    let legacy = home.join(".config/dotty");
+
+    if !path.join("config.toml").exists() && legacy.join("config.toml").exists() {
+        return legacy;
+    }
+
+    path
+}
+
+fn create_dir(path: PathBuf) -> Result<PathBuf> {
+    fs::create_dir_all(&path)
+        .with_context(|| format!("Unable to create the directory {}.", path.display()))?;
+
+    Ok(path)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -144,6 +341,46 @@ pub struct TomlConfig {
     pub log_level: LevelFilter,
     pub profiles: ProfilesMap,
     pub active_profile: ProfileId,
+    #[serde(default)]
+    pub git_backend: GitBackend,
+    /// Template for the messages of commits made by Dotty. See `repository::message`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_template: Option<String>,
+    #[serde(default)]
+    pub assets: AssetConfig,
+    /// Where the log and backups are kept. The base path is used when unset.
+    #[serde(skip)]
+    pub state_path: Option<PathBuf>,
+    /// Where files Dotty can recreate are kept. The base path is used when unset.
+    #[serde(skip)]
+    pub cache_path: Option<PathBuf>,
+}
+
+/// Where large files are kept instead of the Git history. See `assets`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AssetConfig {
+    /// Files larger than this many bytes are moved to the asset store.
+    #[serde(default = "AssetConfig::default_size_limit")]
+    pub size_limit: u64,
+    /// The asset store, which defaults to `assets` inside the base path. It may
+    /// be on another drive, as long as it is mounted when Dotty runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<PathBuf>,
+}
+
+impl AssetConfig {
+    fn default_size_limit() -> u64 {
+        1024 * 1024
+    }
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        Self {
+            size_limit: Self::default_size_limit(),
+            store: None,
+        }
+    }
 }
 
 impl Default for TomlConfig {
@@ -153,6 +390,11 @@ impl Default for TomlConfig {
             log_level: LevelFilter::Warn,
             profiles: BTreeMap::new(),
             active_profile: String::new(),
+            git_backend: GitBackend::default(),
+            commit_template: None,
+            assets: AssetConfig::default(),
+            state_path: None,
+            cache_path: None,
         }
     }
 }
@@ -173,11 +415,12 @@ impl TomlConfig {
     ///
     /// If the file exists, it is read and parsed.
     /// If the file doesn't exist, we create (and save) a default configuration.
+    /// Either way, the state and cache directories are taken from `loader`.
     ///
     /// # Arguments
     /// * `fs` - An implementation of the `FileSystem` trait used for file operations.
     /// * `loader` - An implementation of the `ConfigLoader` trait used to get the base
-    ///              path and parse the configuration.
+    ///   path and parse the configuration.
     ///
     /// # Returns
     /// Returns a `Result<Self>` where:
@@ -196,10 +439,10 @@ impl TomlConfig {
     ) -> Result<Self> {
         let path = loader.get_base_path()?.join("config.toml");
 
-        if fs.exists(&path) {
+        let mut config = if fs.exists(&path) {
             match fs.read_to_string(&path).await {
                 Ok(content) => match loader.config_from_str(&content) {
-                    Ok(config) => Ok(config),
+                    Ok(config) => config,
                     Err(error) => {
                         log::error!("Error parsing config: {} :: {}", path.display(), error);
                         anyhow::bail!(
@@ -219,14 +462,19 @@ impl TomlConfig {
         } else {
             let config = Self::default_with_base_path(loader.get_base_path()?);
             fs.write(&path, &toml::to_string(&config)?).await?;
-            Ok(config)
-        }
+            config
+        };
+
+        config.state_path = Some(loader.get_state_path()?);
+        config.cache_path = Some(loader.get_cache_path()?);
+
+        Ok(config)
     }
 
     /// Configures the logging system for Dotty based on the current configuration and runtime environment.
     ///
     /// This function sets up logging using log4rs, creating a file appender that writes to 'dotty.log'
-    /// in the base path directory. The logging format and level are determined by the configuration
+    /// in the state directory. The logging format and level are determined by the configuration
     /// and whether the application is running in development mode.
     ///
     /// # Arguments
@@ -253,7 +501,7 @@ impl TomlConfig {
         };
         let logfile = FileAppender::builder()
             .encoder(Box::new(pattern))
-            .build(self.base_path.join("dotty.log"))?;
+            .build(self.state_dir().join("dotty.log"))?;
 
         let config = Config::builder()
             .appender(Appender::builder().build("logfile", Box::new(logfile)))
@@ -268,35 +516,87 @@ impl TomlConfig {
         Ok(())
     }
 
+    /// Returns the directory holding the log and backups.
+    pub fn state_dir(&self) -> &Path {
+        self.state_path.as_deref().unwrap_or(&self.base_path)
+    }
+
+    /// Returns the directory holding files Dotty can recreate.
+    pub fn cache_dir(&self) -> &Path {
+        self.cache_path.as_deref().unwrap_or(&self.base_path)
+    }
+
     pub async fn get_profile_ids(&self) -> HashSet<String> {
         self.profiles.keys().cloned().collect()
     }
+
+    /// Returns the currently active profile.
+    ///
+    /// # Errors
+    /// This function will return an error if no profile is active, or if the
+    /// active profile ID does not match any configured profile.
+    ///
+    pub fn active_profile(&self) -> Result<&ProfileConfig> {
+        self.ensure_active_profile()?;
+
+        self.profiles
+            .get(&self.active_profile)
+            .with_context(|| format!("Active profile '{}' does not exist.", self.active_profile))
+    }
+
+    /// Returns a mutable reference to the currently active profile.
+    ///
+    /// # Errors
+    /// This function will return an error if no profile is active, or if the
+    /// active profile ID does not match any configured profile.
+    ///
+    pub fn active_profile_mut(&mut self) -> Result<&mut ProfileConfig> {
+        self.ensure_active_profile()?;
+
+        let id = self.active_profile.clone();
+        self.profiles
+            .get_mut(&id)
+            .with_context(|| format!("Active profile '{}' does not exist.", id))
+    }
+
+    fn ensure_active_profile(&self) -> Result<()> {
+        if self.active_profile.is_empty() {
+            anyhow::bail!("No active profile. Run `dotty config profile` to select one.");
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the configuration and writes it to `<base_path>/config.toml`.
+    ///
+    /// # Errors
+    /// This function will return an error if the configuration cannot be
+    /// serialized or the file cannot be written.
+    ///
+    pub async fn save(&self, fs: &impl FileSystem, loader: &impl ConfigLoader) -> Result<()> {
+        let contents = loader.config_to_string(self)?;
+
+        fs.write(&self.base_path.join("config.toml"), &contents)
+            .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use async_trait::async_trait;
+    use crate::clients::file_system::MockFileSystem;
     use mockall::{mock, predicate::*};
-    use std::{path::Path, sync::Arc};
+    use std::sync::Arc;
 
     mod test_from_path_or_default {
         use super::*;
 
-        mock! {
-            FileSystem {}
-            #[async_trait]
-            impl FileSystem for FileSystem {
-                fn exists(&self, path: &Path) -> bool;
-                async fn read_to_string(&self, path: &Path) -> Result<String>;
-                async fn write(&self, path: &Path, contents: &str) -> Result<()>;
-            }
-        }
-
         mock! {
             ConfigLoader {}
             impl ConfigLoader for ConfigLoader {
                 fn get_base_path(&self) -> Result<PathBuf>;
+                fn get_state_path(&self) -> Result<PathBuf>;
+                fn get_cache_path(&self) -> Result<PathBuf>;
                 fn config_from_str(&self, content: &str) -> Result<TomlConfig>;
                 fn config_to_string(&self, config: &TomlConfig) -> Result<String>;
             }
@@ -313,6 +613,12 @@ mod tests {
             mock_loader
                 .expect_get_base_path()
                 .returning(move || Ok((*base_path_clone).clone()));
+            mock_loader
+                .expect_get_state_path()
+                .returning(|| Ok(PathBuf::from("/test/state")));
+            mock_loader
+                .expect_get_cache_path()
+                .returning(|| Ok(PathBuf::from("/test/cache")));
 
             (mock_fs, mock_loader, base_path, config_path)
         }
@@ -397,9 +703,34 @@ mod tests {
 
             let result = TomlConfig::from_path_or_default(&mock_fs, &mock_loader).await;
             assert!(result.is_ok());
+            assert_eq!(result.unwrap().state_dir(), Path::new("/test/state"));
         }
     }
 
+    #[test]
+    fn test_config_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let home = temp_dir.path();
+        let xdg = home.join("xdg");
+        let env = |name: &str| (name == "XDG_CONFIG_HOME").then(|| xdg.display().to_string());
+
+        assert_eq!(config_dir(home, &env), xdg.join("dotty"));
+        assert_eq!(config_dir(home, &|_| None), home.join(".config/dotty"));
+        assert_eq!(
+            config_dir(home, &|_| Some(String::from("relative"))),
+            home.join(".config/dotty")
+        );
+
+        // An existing install is found where Dotty used to keep it.
+        fs::create_dir_all(home.join(".config/dotty")).unwrap();
+        fs::write(home.join(".config/dotty/config.toml"), "").unwrap();
+        assert_eq!(config_dir(home, &env), home.join(".config/dotty"));
+
+        fs::create_dir_all(xdg.join("dotty")).unwrap();
+        fs::write(xdg.join("dotty/config.toml"), "").unwrap();
+        assert_eq!(config_dir(home, &env), xdg.join("dotty"));
+    }
+
     mod test_configure_logging {
         use super::*;
         use log::{max_level, LevelFilter};
@@ -416,6 +747,11 @@ mod tests {
                 log_level: LevelFilter::Info,
                 profiles: BTreeMap::new(),
                 active_profile: String::new(),
+                git_backend: GitBackend::System,
+                commit_template: None,
+                assets: AssetConfig::default(),
+                state_path: Some(temp_dir.path().join("state")),
+                cache_path: None,
             };
 
             (config, temp_dir)
@@ -440,7 +776,7 @@ mod tests {
             assert!(result.is_ok());
 
             // Check if the log file was created
-            let log_file_path = config.base_path.join("dotty.log");
+            let log_file_path = temp_dir.path().join("state/dotty.log");
             assert!(log_file_path.exists());
 
             // Verify that the logging is configured correctly
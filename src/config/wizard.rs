@@ -9,8 +9,14 @@ use log::LevelFilter;
 
 // Local module imports
 use crate::{
-    clients::git::Git,
-    config::{ProfileConfig, ProfileId, TomlConfig},
+    clients::{
+        file_system::FileSystem,
+        git::{BranchLocation, Git},
+    },
+    config::{
+        ConflictPolicy, ProfileConfig, ProfileId, PushPolicy, RemoteConfig, Strategy, TomlConfig,
+    },
+    repository,
 };
 
 /// Guides the user through the initial setup of Dotty.
@@ -21,6 +27,7 @@ use crate::{
 ///
 /// # Parameters
 /// * `config`: A `TomlConfig` struct, typically starting with default values.
+/// * `fs` - An implementation of `FileSystem` for creating the repository.
 /// * `git` - An implementation of Git for interacting with the Git API.
 ///
 /// # Returns
@@ -31,15 +38,21 @@ use crate::{
 /// 1. Configures system-wide settings by calling `system_settings`.
 /// 2. If no profiles exist, guides the user through creating an initial profile
 ///    by calling `new_profile_wizard`.
+/// 3. If a profile is active, offers to create the dotfiles repository.
 ///
 /// # Errors
 /// This function may return an error if:
 /// * The screen cannot be cleared.
 /// * The system settings configuration fails.
 /// * The profile creation process fails (when needed).
+/// * The repository cannot be created.
 /// * Any I/O or user interaction errors occur during the setup process.
 ///
-pub async fn initial_setup_wizard(mut config: TomlConfig, git: Arc<dyn Git>) -> Result<TomlConfig> {
+pub async fn initial_setup_wizard(
+    mut config: TomlConfig,
+    fs: &impl FileSystem,
+    git: Arc<dyn Git>,
+) -> Result<TomlConfig> {
     cliclack::clear_screen()?;
     cliclack::intro(style(" Configure Dotty ").on_dark_green().black().bold())?;
 
@@ -56,7 +69,23 @@ pub async fn initial_setup_wizard(mut config: TomlConfig, git: Arc<dyn Git>) ->
             return Ok(config);
         };
 
-        config = new_profile_wizard(config, git).await?;
+        config = new_profile_wizard(config, git.clone()).await?;
+    }
+
+    if config.active_profile.is_empty() {
+        return Ok(config);
+    }
+
+    if git.is_repository(&config.base_path) {
+        cliclack::log::info("Dotty will use the existing Git repository in this folder.")?;
+    } else if cliclack::confirm(
+        style("Would you like to create the dotfiles repository now?").bold(),
+    )
+    .initial_value(true)
+    .interact()?
+    {
+        repository::init(&config, fs, git.as_ref()).await?;
+        cliclack::log::success("Created the dotfiles repository.")?;
     }
 
     Ok(config)
@@ -73,7 +102,7 @@ pub async fn initial_setup_wizard(mut config: TomlConfig, git: Arc<dyn Git>) ->
 ///
 /// # Returns
 /// * `Result<TomlConfig>`: The updated configuration if successful, or an error if
-///                         the user interaction fails or invalid input is provided.
+///   the user interaction fails or invalid input is provided.
 ///
 /// # Errors
 /// This function may return an error if:
@@ -137,7 +166,7 @@ async fn system_settings(mut config: TomlConfig) -> Result<TomlConfig> {
 ///
 /// # Returns
 /// * `Result<TomlConfig>`: The updated configuration if successful, or an error if
-///                         the user interaction fails or invalid input is provided.
+///   the user interaction fails or invalid input is provided.
 ///
 ///
 /// # Errors
@@ -171,14 +200,10 @@ pub async fn new_profile_wizard(mut config: TomlConfig, git: Arc<dyn Git>) -> Re
         This is like having a separate folder for each set of settings, keeping everything organized.",
     )?;
 
-    // Collect now because `set_profile` is used elsewhere.
-    let branches: Vec<String> = config
-        .profiles
-        .values()
-        .map(|profile| profile.branch.to_string())
-        .collect();
+    let branches = taken_branches(&config, git.as_ref(), None)?;
 
-    let profile = set_profile(None, git, branches).await?;
+    let profile = set_profile(None, git.clone(), branches).await?;
+    let profile = set_remotes(profile, git).await?;
 
     config.profiles.insert(profile_id.clone(), profile);
 
@@ -193,16 +218,38 @@ pub async fn new_profile_wizard(mut config: TomlConfig, git: Arc<dyn Git>) -> Re
     Ok(config)
 }
 
+/// Collects the branch names a profile cannot use.
+///
+/// These are the branches of the other profiles, and the branches that exist in
+/// the repository or on its remote. `except` is left out, so a profile can keep
+/// its own branch.
+fn taken_branches(
+    config: &TomlConfig,
+    git: &dyn Git,
+    except: Option<&str>,
+) -> Result<Vec<(String, BranchLocation)>> {
+    let mut branches: Vec<_> = config
+        .profiles
+        .iter()
+        .map(|(id, profile)| (profile.branch.clone(), BranchLocation::Profile(id.clone())))
+        .collect();
+    branches.extend(git.existing_branches(&config.base_path)?);
+    branches.retain(|(branch, _)| Some(branch.as_str()) != except);
+
+    Ok(branches)
+}
+
 /// Configures and sets up a profile for the application.
 ///
 /// This function prompts the user to input a unique name for the profile's storage space in Git
 /// (referred to as a 'branch'). It validates the input against Git branch naming rules and ensures
-/// the branch name is unique among existing branches.
+/// the branch name is unique among existing branches. It then asks how the profile's files are
+/// deployed by default, and what happens to existing files they would replace.
 ///
 /// # Arguments
 /// * `profile_` - An optional `ProfileConfig` to start with. If None, a default profile is created.
 /// * `git` - An `Arc<dyn Git>` representing the Git interface for validation.
-/// * `branches` - A vector of existing branch names to check for uniqueness.
+/// * `branches` - The branch names already in use, and where they are used.
 ///
 /// # Returns
 /// Returns a `Result<ProfileConfig>` containing the configured profile if successful,
@@ -216,7 +263,7 @@ pub async fn new_profile_wizard(mut config: TomlConfig, git: Arc<dyn Git>) -> Re
 async fn set_profile(
     profile_: Option<ProfileConfig>,
     git: Arc<dyn Git>,
-    branches: Vec<String>,
+    branches: Vec<(String, BranchLocation)>,
 ) -> Result<ProfileConfig> {
     let mut profile = profile_.unwrap_or_default();
 
@@ -232,7 +279,7 @@ async fn set_profile(
             return Err(e.to_string());
         }
 
-        if let Err(e) = git.is_branch_unique(branches.clone(), input) {
+        if let Err(e) = git.is_branch_unique(&branches, input) {
             return Err(e.to_string());
         }
 
@@ -240,9 +287,182 @@ async fn set_profile(
     })
     .interact()?;
 
+    profile.strategy =
+        cliclack::select(style("How should this Profile's files be deployed?").bold())
+            .initial_value(profile.strategy)
+            .items(&[
+                (
+                    Strategy::Symlink,
+                    "Symlink",
+                    "Link to the file in the repository",
+                ),
+                (
+                    Strategy::Copy,
+                    "Copy",
+                    "For applications that replace or refuse symlinks",
+                ),
+                (
+                    Strategy::Hardlink,
+                    "Hardlink",
+                    "Hard link to the file in the repository",
+                ),
+            ])
+            .interact()?;
+
+    profile.conflict = cliclack::select(
+        style("What should happen to existing files this Profile would replace?").bold(),
+    )
+    .initial_value(profile.conflict)
+    .items(&[
+        (
+            ConflictPolicy::Backup,
+            "Back up",
+            "Keep a copy you can restore with `dotty backups`",
+        ),
+        (ConflictPolicy::Skip, "Skip", "Keep the existing file"),
+        (
+            ConflictPolicy::Overwrite,
+            "Overwrite",
+            "Replace it without a backup",
+        ),
+        (ConflictPolicy::Ask, "Ask", "Show the differences and ask"),
+    ])
+    .interact()?;
+
     Ok(profile)
 }
 
+/// An entry of the menu shown by `set_remotes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RemoteAction {
+    Done,
+    Add,
+    Edit(usize),
+    Remove(usize),
+}
+
+/// Lets the user add, edit and remove the remotes of a profile.
+///
+/// The menu is shown again after every change until the user picks "Done".
+///
+/// # Arguments
+/// * `profile` - The profile whose remotes are edited.
+/// * `git` - An `Arc<dyn Git>` used to check the URLs.
+///
+/// # Returns
+/// Returns the profile with its updated remotes.
+///
+/// # Errors
+/// This function can return an error if user input cannot be read.
+///
+async fn set_remotes(mut profile: ProfileConfig, git: Arc<dyn Git>) -> Result<ProfileConfig> {
+    loop {
+        let mut options = vec![
+            (RemoteAction::Done, "Done".to_string(), String::new()),
+            (RemoteAction::Add, "Add a remote".to_string(), String::new()),
+        ];
+        for (index, remote) in profile.remotes.iter().enumerate() {
+            options.push((
+                RemoteAction::Edit(index),
+                format!("Edit {}", remote.name),
+                remote.url.clone(),
+            ));
+            options.push((
+                RemoteAction::Remove(index),
+                format!("Remove {}", remote.name),
+                String::new(),
+            ));
+        }
+
+        let action = cliclack::select(
+            style("Where should this profile be pushed? Without remotes, Dotty uses 'origin'.")
+                .bold(),
+        )
+        .items(&options)
+        .interact()?;
+
+        match action {
+            RemoteAction::Done => return Ok(profile),
+            RemoteAction::Add => {
+                let remote = set_remote(None, &profile.remotes, git.clone())?;
+                profile.remotes.push(remote);
+            }
+            RemoteAction::Edit(index) => {
+                let remote = profile.remotes.remove(index);
+                let remote = set_remote(Some(remote), &profile.remotes, git.clone())?;
+                profile.remotes.insert(index, remote);
+            }
+            RemoteAction::Remove(index) => {
+                profile.remotes.remove(index);
+            }
+        }
+    }
+}
+
+/// Asks for the name, URL and push policy of a remote.
+///
+/// The URL is only accepted once the repository behind it can be read.
+///
+/// # Arguments
+/// * `remote` - The remote being edited, if any, whose values are the defaults.
+/// * `others` - The profile's other remotes, whose names cannot be reused.
+/// * `git` - An `Arc<dyn Git>` used to check the URL.
+///
+/// # Returns
+/// Returns the new or edited remote.
+///
+/// # Errors
+/// This function can return an error if user input cannot be read.
+///
+fn set_remote(
+    remote: Option<RemoteConfig>,
+    others: &[RemoteConfig],
+    git: Arc<dyn Git>,
+) -> Result<RemoteConfig> {
+    let names: HashSet<_> = others.iter().map(|remote| remote.name.clone()).collect();
+
+    let name: String = cliclack::input(style("Name the remote").bold())
+        .placeholder("backup")
+        .default_input(remote.as_ref().map_or("", |remote| &remote.name))
+        .validate(move |input: &String| {
+            if input.is_empty() || input.contains(char::is_whitespace) {
+                Err("Remote names cannot be empty or contain spaces.")
+            } else if names.contains(input) {
+                Err("This profile already has a remote with this name.")
+            } else {
+                Ok(())
+            }
+        })
+        .interact()?;
+
+    let url: String = cliclack::input(style("Enter the URL of the remote").bold())
+        .placeholder("git@github.com:you/dotfiles.git")
+        .default_input(remote.as_ref().map_or("", |remote| &remote.url))
+        .validate(move |input: &String| {
+            if input.trim().is_empty() {
+                return Err("The URL cannot be empty.".to_string());
+            }
+
+            match git.check_remote(input.trim()) {
+                Ok(()) => Ok(()),
+                Err(error) => Err(format!("Unable to reach this remote: {:#}", error)),
+            }
+        })
+        .interact()?;
+    let url = url.trim().to_string();
+
+    let push = cliclack::select(style("When should Dotty push to this remote?").bold())
+        .initial_value(remote.map_or(PushPolicy::default(), |remote| remote.push))
+        .items(&[
+            (PushPolicy::Always, "Always", "Push on every `dotty sync`"),
+            (PushPolicy::Manual, "Manual", "Only push with `dotty push`"),
+            (PushPolicy::Never, "Never", "Only pull from this remote"),
+        ])
+        .interact()?;
+
+    Ok(RemoteConfig { name, url, push })
+}
+
 /// Presents a wizard for listing and selecting Dotty profiles.
 ///
 /// This function displays all configured Dotty profiles to the user and allows
@@ -363,14 +583,10 @@ pub async fn update_profile_wizard(
         .cloned()
         .ok_or_else(|| anyhow::anyhow!("No profile found with ID: {}", selected_profile_id))?;
 
-    let branches: Vec<String> = config
-        .profiles
-        .values()
-        .map(|profile| profile.branch.to_string())
-        .filter(|branch| branch != &selected_profile.branch)
-        .collect();
+    let branches = taken_branches(&config, git.as_ref(), Some(&selected_profile.branch))?;
 
-    let profile = set_profile(Some(selected_profile), git, branches).await?;
+    let profile = set_profile(Some(selected_profile), git.clone(), branches).await?;
+    let profile = set_remotes(profile, git).await?;
 
     config.profiles.insert(selected_profile_id, profile);
 
@@ -0,0 +1,502 @@
+//! Third-party repositories, such as plugin managers and themes, pinned by a profile.
+//!
+//! A profile lists its externals in `externals.toml` at the root of its branch,
+//! so the pinned revisions are committed and shared like any other change:
+//!
+//! ```toml
+//! [[external]]
+//! name = "tpm"
+//! url = "https://github.com/tmux-plugins/tpm"
+//! path = ".tmux/plugins/tpm"
+//! branch = "master"
+//! rev = "99469c4a9b1ccf77fade25842dc7bafbc8ce9946"
+//! ```
+//!
+//! Each external is cloned to `path`, relative to the home directory, and kept
+//! on the pinned commit with a detached `HEAD`.
+
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError, DEFAULT_REMOTE},
+    },
+    config::TomlConfig,
+    repository::{
+        message::{commit_message, DEFAULT_TEMPLATE},
+        sync::checkout_branch,
+    },
+};
+
+/// File at the root of the repository listing the profile's externals.
+pub const EXTERNALS_FILE: &str = "externals.toml";
+
+/// Branches followed, in order, by an external that does not set `branch`.
+const DEFAULT_BRANCHES: [&str; 2] = ["main", "master"];
+
+/// The contents of `externals.toml`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Externals {
+    #[serde(default, rename = "external")]
+    pub externals: Vec<External>,
+}
+
+/// A repository checked out at a pinned commit.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct External {
+    pub name: String,
+    /// Anything `git clone` accepts, including local paths and `file://` URLs.
+    pub url: String,
+    /// Where the repository is checked out, relative to the home directory.
+    pub path: PathBuf,
+    /// The branch `dotty externals update` moves the pin along.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The pinned commit. Without one, the tip of the branch is checked out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+/// What deploying an external did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalState {
+    /// The repository did not exist and was cloned.
+    Cloned,
+    /// The repository was moved to the pinned commit.
+    Updated,
+    /// The repository was already on the pinned commit.
+    Unchanged,
+}
+
+/// A pin moved by `update`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bump {
+    pub name: String,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// What an `update` did.
+#[derive(Debug, Default, PartialEq)]
+pub struct UpdateReport {
+    pub bumped: Vec<Bump>,
+    /// The ID of the commit recording the new pins, if any moved.
+    pub commit: Option<String>,
+}
+
+impl External {
+    /// Returns where the external is checked out, accepting paths that start with `~/`.
+    pub fn destination(&self, home: &Path) -> PathBuf {
+        home.join(self.path.strip_prefix("~").unwrap_or(&self.path))
+    }
+}
+
+/// Reads `externals.toml` from the repository, returning no externals when it does not exist.
+///
+/// # Errors
+/// This function will return an error if the file cannot be read or parsed.
+///
+pub async fn load(fs: &impl FileSystem, repo: &Path) -> Result<Externals> {
+    let path = repo.join(EXTERNALS_FILE);
+    if !fs.exists(&path) {
+        return Ok(Externals::default());
+    }
+
+    let contents = fs.read_to_string(&path).await?;
+    toml::from_str(&contents).with_context(|| format!("Unable to parse {}.", path.display()))
+}
+
+/// Reads `externals.toml` as committed on `branch`, returning no externals when it was never committed.
+///
+/// # Errors
+/// This function will return an error if the branch cannot be read or the file cannot be parsed.
+///
+pub fn committed(repo: &Path, branch: &str, git: &dyn Git) -> Result<Externals> {
+    let Some(contents) = git.show_file(repo, branch, Path::new(EXTERNALS_FILE))? else {
+        return Ok(Externals::default());
+    };
+
+    let contents = String::from_utf8(contents)?;
+    toml::from_str(&contents)
+        .with_context(|| format!("Unable to parse {} on branch '{}'.", EXTERNALS_FILE, branch))
+}
+
+/// Clones `external` if needed and checks out its pinned commit.
+///
+/// The remote is only fetched when the pinned commit is not available locally,
+/// or when the external is not pinned and so follows its branch.
+///
+/// # Arguments
+/// * `external` - The external to deploy.
+/// * `home` - The user's home directory.
+/// * `fs` - An implementation of `FileSystem` for inspecting the destination.
+/// * `git` - An implementation of `Git` for cloning and checking out the external.
+///
+/// # Returns
+/// Returns whether the external was cloned, updated or already up to date.
+///
+/// # Errors
+/// This function will return an error if:
+/// - The destination exists and is not a Git repository.
+/// - The pinned commit does not exist on the remote.
+/// - Any Git operation fails.
+///
+pub async fn deploy(
+    external: &External,
+    home: &Path,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+) -> Result<ExternalState> {
+    let dest = external.destination(home);
+    let cloned = prepare(external, &dest, fs, git).await?;
+
+    let target = match &external.rev {
+        Some(rev) => match git.resolve_revision(&dest, rev) {
+            Ok(id) => id,
+            Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+                git.fetch(&dest, DEFAULT_REMOTE)?;
+                git.resolve_revision(&dest, rev)?
+            }
+            Err(error) => return Err(error),
+        },
+        None => {
+            log::warn!("External '{}' is not pinned to a commit", external.name);
+            if !cloned {
+                git.fetch(&dest, DEFAULT_REMOTE)?;
+            }
+            branch_tip(external, &dest, git)?
+        }
+    };
+
+    if !cloned && git.resolve_revision(&dest, "HEAD").ok().as_ref() == Some(&target) {
+        return Ok(ExternalState::Unchanged);
+    }
+
+    git.checkout(&dest, &target)?;
+    log::info!("Checked out {} at {}", dest.display(), target);
+
+    Ok(match cloned {
+        true => ExternalState::Cloned,
+        false => ExternalState::Updated,
+    })
+}
+
+/// Moves the pins of the active profile's externals to the tip of their branches.
+///
+/// The profile's branch is checked out, the new pins are written to
+/// `externals.toml` and committed, and the externals are moved to them.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the base path and active profile.
+/// * `home` - The user's home directory.
+/// * `fs` - An implementation of `FileSystem` for reading and writing `externals.toml`.
+/// * `git` - An implementation of `Git` for fetching the externals and committing the pins.
+/// * `names` - The externals to update, or every external when empty.
+///
+/// # Returns
+/// Returns an `UpdateReport` listing the pins that moved.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile.
+/// - One of `names` is not an external of the profile.
+/// - An external's branch cannot be found on its remote.
+/// - Any Git operation fails.
+///
+pub async fn update(
+    config: &TomlConfig,
+    home: &Path,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+    names: &[String],
+) -> Result<UpdateReport> {
+    let repo = &config.base_path;
+    let profile = config.active_profile()?;
+
+    checkout_branch(git, repo, &profile.branch)?;
+    let mut externals = load(fs, repo).await?;
+
+    for name in names {
+        if !externals
+            .externals
+            .iter()
+            .any(|external| external.name == *name)
+        {
+            bail!(
+                "Profile '{}' has no external named '{}'.",
+                config.active_profile,
+                name
+            );
+        }
+    }
+
+    let mut report = UpdateReport::default();
+    for external in &mut externals.externals {
+        if !names.is_empty() && !names.contains(&external.name) {
+            continue;
+        }
+
+        let dest = external.destination(home);
+        if !prepare(external, &dest, fs, git).await? {
+            git.fetch(&dest, DEFAULT_REMOTE)?;
+        }
+
+        let tip = branch_tip(external, &dest, git)?;
+        if external.rev.as_ref() != Some(&tip) {
+            report.bumped.push(Bump {
+                name: external.name.clone(),
+                from: external.rev.replace(tip.clone()),
+                to: tip.clone(),
+            });
+        }
+
+        git.checkout(&dest, &tip)?;
+    }
+
+    if report.bumped.is_empty() {
+        return Ok(report);
+    }
+
+    fs.write(&repo.join(EXTERNALS_FILE), &toml::to_string(&externals)?)
+        .await?;
+    git.add(repo, &[PathBuf::from(EXTERNALS_FILE)])?;
+
+    let changes = git.staged_changes(repo)?;
+    let template = config
+        .commit_template
+        .as_deref()
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let host = gethostname::gethostname().to_string_lossy().to_string();
+    let message = commit_message(template, &config.active_profile, &host, &changes);
+    report.commit = Some(git.commit(repo, &message)?);
+
+    Ok(report)
+}
+
+/// Makes sure `dest` is a clone of `external`, returning whether it was cloned.
+async fn prepare(
+    external: &External,
+    dest: &Path,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+) -> Result<bool> {
+    if git.is_repository(dest) {
+        git.set_remote(dest, DEFAULT_REMOTE, &external.url)?;
+        return Ok(false);
+    }
+
+    if fs.exists(dest) && (!fs.is_dir(dest) || !fs.read_dir(dest).await?.is_empty()) {
+        bail!(
+            "'{}' already exists and is not a Git repository, so external '{}' cannot be cloned there.",
+            dest.display(),
+            external.name
+        );
+    }
+
+    git.clone_repository(&external.url, dest)?;
+    log::info!("Cloned {} to {}", external.url, dest.display());
+
+    Ok(true)
+}
+
+/// Returns the commit at the tip of the external's branch on its remote.
+fn branch_tip(external: &External, dest: &Path, git: &dyn Git) -> Result<String> {
+    let branches = match &external.branch {
+        Some(branch) => vec![branch.as_str()],
+        None => DEFAULT_BRANCHES.to_vec(),
+    };
+
+    for branch in &branches {
+        match git.resolve_revision(dest, &format!("{}/{}", DEFAULT_REMOTE, branch)) {
+            Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {}
+            result => return result,
+        }
+    }
+
+    match &external.branch {
+        Some(branch) => bail!(
+            "Branch '{}' of external '{}' does not exist on {}.",
+            branch,
+            external.name,
+            external.url
+        ),
+        None => bail!(
+            "External '{}' has no '{}' branch. Set `branch` in {}.",
+            external.name,
+            DEFAULT_BRANCHES.join("' or '"),
+            EXTERNALS_FILE
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::ProfileConfig,
+        repository::init,
+    };
+    use std::{fs, process::Command};
+    use tempfile::tempdir;
+
+    /// Creates a repository with a commit on `main`, standing in for a plugin.
+    fn plugin(root: &Path) -> (String, PathBuf) {
+        let repo = root.join("tpm");
+        GitClient.init(&repo, "main").unwrap();
+        let url = format!("file://{}", repo.display());
+        (url, repo)
+    }
+
+    fn release(repo: &Path, version: &str) -> String {
+        fs::write(repo.join("VERSION"), version).unwrap();
+        GitClient.add(repo, &[PathBuf::from("VERSION")]).unwrap();
+        GitClient.commit(repo, version).unwrap()
+    }
+
+    async fn repository(base_path: &Path, externals: &Externals) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path: base_path.to_path_buf(),
+            active_profile: "default".to_string(),
+            ..TomlConfig::default()
+        };
+        config.profiles.insert(
+            "default".to_string(),
+            ProfileConfig {
+                branch: "main".to_string(),
+                ..ProfileConfig::default()
+            },
+        );
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+        fs::write(
+            base_path.join(EXTERNALS_FILE),
+            toml::to_string(externals).unwrap(),
+        )
+        .unwrap();
+        config
+    }
+
+    #[tokio::test]
+    async fn test_deploy() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let (url, plugin) = plugin(temp_dir.path());
+        let first = release(&plugin, "1.0");
+        release(&plugin, "2.0");
+
+        let mut external = External {
+            name: "tpm".to_string(),
+            url,
+            path: PathBuf::from("~/.tmux/plugins/tpm"),
+            rev: Some(first.clone()),
+            ..External::default()
+        };
+        let dest = home.join(".tmux/plugins/tpm");
+
+        let state = deploy(&external, &home, &FileSystemClient, &GitClient).await;
+        assert_eq!(state.unwrap(), ExternalState::Cloned);
+        assert_eq!(fs::read_to_string(dest.join("VERSION")).unwrap(), "1.0");
+
+        let state = deploy(&external, &home, &FileSystemClient, &GitClient).await;
+        assert_eq!(state.unwrap(), ExternalState::Unchanged);
+
+        // A pin that is not available locally is fetched.
+        let third = release(&plugin, "3.0");
+        external.rev = Some(third);
+        let state = deploy(&external, &home, &FileSystemClient, &GitClient).await;
+        assert_eq!(state.unwrap(), ExternalState::Updated);
+        assert_eq!(fs::read_to_string(dest.join("VERSION")).unwrap(), "3.0");
+
+        // Without a pin, the tip of the branch is used.
+        external.rev = None;
+        release(&plugin, "4.0");
+        let state = deploy(&external, &home, &FileSystemClient, &GitClient).await;
+        assert_eq!(state.unwrap(), ExternalState::Updated);
+        assert_eq!(fs::read_to_string(dest.join("VERSION")).unwrap(), "4.0");
+
+        // Files that are not a clone are never overwritten.
+        let other = External {
+            path: PathBuf::from(".tmux.conf"),
+            ..external
+        };
+        fs::write(home.join(".tmux.conf"), "set -g mouse on").unwrap();
+        assert!(deploy(&other, &home, &FileSystemClient, &GitClient)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let (url, plugin) = plugin(temp_dir.path());
+        let first = release(&plugin, "1.0");
+
+        let externals = Externals {
+            externals: vec![External {
+                name: "tpm".to_string(),
+                url,
+                path: PathBuf::from(".tmux/plugins/tpm"),
+                rev: Some(first.clone()),
+                ..External::default()
+            }],
+        };
+        let config = repository(&temp_dir.path().join("dotty"), &externals).await;
+        let repo = &config.base_path;
+
+        // Nothing to bump.
+        let report = update(&config, &home, &FileSystemClient, &GitClient, &[])
+            .await
+            .unwrap();
+        assert_eq!(report, UpdateReport::default());
+
+        let second = release(&plugin, "2.0");
+        let report = update(&config, &home, &FileSystemClient, &GitClient, &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            report.bumped,
+            vec![Bump {
+                name: "tpm".to_string(),
+                from: Some(first),
+                to: second.clone(),
+            }]
+        );
+        assert_eq!(
+            fs::read_to_string(home.join(".tmux/plugins/tpm/VERSION")).unwrap(),
+            "2.0"
+        );
+
+        // The new pin is committed on the profile's branch.
+        let committed = GitClient
+            .show_file(repo, "main", Path::new(EXTERNALS_FILE))
+            .unwrap()
+            .unwrap();
+        let committed: Externals = toml::from_str(&String::from_utf8(committed).unwrap()).unwrap();
+        assert_eq!(committed.externals[0].rev, Some(second));
+        assert!(Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["diff", "--quiet", "HEAD"])
+            .status()
+            .unwrap()
+            .success());
+
+        let unknown = update(
+            &config,
+            &home,
+            &FileSystemClient,
+            &GitClient,
+            &["nvim-nord".to_string()],
+        )
+        .await;
+        assert!(unknown.is_err());
+    }
+}
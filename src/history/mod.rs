@@ -0,0 +1,594 @@
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use similar::{ChangeTag, TextDiff};
+
+// Local module imports
+use crate::{
+    assets::{self, Pointer},
+    backup::backup,
+    clients::{
+        file_system::FileSystem,
+        git::{CommitInfo, Git, GitError},
+    },
+    config::{ProfileConfig, Strategy, TomlConfig},
+    journal::{Change, Journal, Planned},
+    manifest::TrackedFile,
+    repository::sync::checkout_branch,
+};
+
+/// Lines of unchanged context shown around each change in a patch.
+const CONTEXT_LINES: usize = 3;
+
+/// A commit that changed a tracked file, with the file as it was before and after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub commit: CommitInfo,
+    /// The file's location inside the repository.
+    pub path: PathBuf,
+    before: String,
+    after: String,
+}
+
+/// A line of a unified diff, classified so it can be coloured.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchLine {
+    /// The `---` and `+++` lines naming the file.
+    Header(String),
+    /// An `@@` line starting a hunk.
+    Hunk(String),
+    Added(String),
+    Removed(String),
+    Context(String),
+}
+
+impl FileChange {
+    /// Returns the number of lines added and removed by the commit.
+    pub fn diffstat(&self) -> (usize, usize) {
+        let diff = TextDiff::from_lines(&self.before, &self.after);
+
+        diff.iter_all_changes()
+            .fold((0, 0), |(added, removed), change| match change.tag() {
+                ChangeTag::Insert => (added + 1, removed),
+                ChangeTag::Delete => (added, removed + 1),
+                ChangeTag::Equal => (added, removed),
+            })
+    }
+
+    /// Returns the change as a unified diff.
+    pub fn patch(&self) -> Vec<PatchLine> {
+        let path = self.path.display();
+
+        patch(
+            &format!("a/{}", path),
+            &format!("b/{}", path),
+            &self.before,
+            &self.after,
+        )
+    }
+}
+
+/// Returns the unified diff turning `before`, named `old`, into `after`, named `new`.
+pub fn patch(old: &str, new: &str, before: &str, after: &str) -> Vec<PatchLine> {
+    let diff = TextDiff::from_lines(before, after);
+
+    let mut lines = vec![
+        PatchLine::Header(format!("--- {}", old)),
+        PatchLine::Header(format!("+++ {}", new)),
+    ];
+
+    for hunk in diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .iter_hunks()
+    {
+        lines.push(PatchLine::Hunk(hunk.header().to_string()));
+
+        for change in hunk.iter_changes() {
+            let text = change.value().trim_end_matches('\n');
+            lines.push(match change.tag() {
+                ChangeTag::Insert => PatchLine::Added(format!("+{}", text)),
+                ChangeTag::Delete => PatchLine::Removed(format!("-{}", text)),
+                ChangeTag::Equal => PatchLine::Context(format!(" {}", text)),
+            });
+        }
+    }
+
+    lines
+}
+
+/// What `restore` did.
+#[derive(Debug, PartialEq)]
+pub struct Restored {
+    /// The revision the file was restored from.
+    pub revision: String,
+    /// Where the replaced contents were backed up to, if the file existed.
+    pub backup: Option<PathBuf>,
+}
+
+/// Lists the commits on the profile's branch that changed the tracked file `source`.
+///
+/// # Arguments
+/// * `profile` - The profile tracking the file.
+/// * `repo` - The dotfiles repository.
+/// * `git` - An implementation of `Git` for reading the history.
+/// * `source` - The location of the file on disk.
+///
+/// # Returns
+/// Returns the changes, newest first. The history is empty while the branch has
+/// no commits.
+///
+/// # Errors
+/// This function will return an error if `source` is not tracked by `profile`
+/// or the repository cannot be read.
+///
+pub fn history(
+    profile: &ProfileConfig,
+    repo: &Path,
+    git: &dyn Git,
+    source: &Path,
+) -> Result<Vec<FileChange>> {
+    let file = tracked_file(profile, source)?;
+    let commits = match git.file_history(repo, &profile.branch, &file.repo_path) {
+        Ok(commits) => commits,
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            return Ok(Vec::new());
+        }
+        Err(error) => return Err(error),
+    };
+
+    let contents = |rev: &str| -> Result<String> {
+        let data = git.show_file(repo, rev, &file.repo_path)?;
+        Ok(String::from_utf8_lossy(&data.unwrap_or_default()).into_owned())
+    };
+
+    commits
+        .into_iter()
+        .map(|commit| {
+            let before = match &commit.parent {
+                Some(parent) => contents(parent)?,
+                None => String::new(),
+            };
+            let after = contents(&commit.id)?;
+
+            Ok(FileChange {
+                commit,
+                path: file.repo_path.clone(),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Deploys the version of the tracked file `source` from `at` to its location.
+///
+/// `at` can be a commit or tag on the profile's branch, or a date. For a date,
+/// the file is restored as it was on the profile's branch at that time. The
+/// current file is backed up into the state directory first.
+///
+/// The file is then deployed the way `apply` does it: with the `symlink` and
+/// `hardlink` strategies the old version is written into the repository, on
+/// the profile's branch, and linked to; with `copy`, and for files kept in the
+/// asset store, it is copied into place. The file gets the mode recorded in the
+/// manifest. The changes are made through a `Journal`, so `dotty undo` can
+/// revert them.
+///
+/// # Arguments
+/// * `config` - The current configuration, whose active profile tracks the file.
+/// * `fs` - An implementation of `FileSystem` for writing the file.
+/// * `git` - An implementation of `Git` for reading the old version.
+/// * `source` - The location of the file on disk.
+/// * `at` - A revision, or a date such as `2 days ago` or `2024-01-31`.
+///
+/// # Returns
+/// Returns the revision the file was restored from and the backup location.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile, or it does not track `source`.
+/// - An earlier apply did not finish.
+/// - `at` is neither a revision nor a date, or is not on the profile's branch.
+/// - The file did not exist at `at`.
+/// - The file is kept in the asset store, but the store does not have it.
+/// - The file cannot be backed up or deployed, after the changes already made
+///   were put back.
+///
+pub async fn restore(
+    config: &TomlConfig,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+    source: &Path,
+    at: &str,
+) -> Result<Restored> {
+    // Checked before anything changes, including the branch that is checked out.
+    if Journal::pending(fs, config.state_dir()).await?.is_some() {
+        bail!("An earlier `dotty apply` did not finish. Run `dotty apply --resume` or `dotty apply --roll-back` first.");
+    }
+
+    let profile = config.active_profile()?;
+    let repo = &config.base_path;
+    let file = tracked_file(profile, source)?;
+
+    let revision = match git.show_file(repo, at, &file.repo_path) {
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            let Some(date) = parse_date(at, Local::now()) else {
+                bail!("'{}' is not a commit, tag or date.", at);
+            };
+            commit_at(profile, repo, git, file, date)?
+        }
+        result => {
+            result?;
+            at.to_string()
+        }
+    };
+
+    if !git.is_ancestor(repo, &revision, &profile.branch)? {
+        bail!(
+            "'{}' is not on branch '{}' of profile {}.",
+            at,
+            profile.branch,
+            config.active_profile
+        );
+    }
+
+    let Some(committed) = git.show_file(repo, &revision, &file.repo_path)? else {
+        bail!("{} did not exist at '{}'.", source.display(), at);
+    };
+
+    let mut strategy = profile.strategy_for(file);
+    if Pointer::parse(&committed).is_some() {
+        strategy = Strategy::Copy;
+    }
+
+    let branch = git.current_branch(repo)?;
+    let linked = repo.join(&file.repo_path);
+    let mut planned = Vec::new();
+    let mode_target = match strategy {
+        Strategy::Copy => {
+            let contents = assets::resolve(fs, &assets::store_dir(config), committed).await?;
+            planned.push(Planned {
+                target: source.to_path_buf(),
+                change: Change::Copy(contents),
+                backup: false,
+            });
+            source
+        }
+        Strategy::Symlink | Strategy::Hardlink => {
+            checkout_branch(git, repo, &profile.branch)?;
+            let link = match strategy {
+                Strategy::Symlink => Change::Symlink(linked.clone()),
+                _ => Change::Hardlink(linked.clone()),
+            };
+            planned.push(Planned {
+                target: linked.clone(),
+                change: Change::Copy(committed),
+                backup: false,
+            });
+            planned.push(Planned {
+                target: source.to_path_buf(),
+                change: link,
+                backup: false,
+            });
+            &linked
+        }
+    };
+    if let Some(mode) = file.mode {
+        planned.push(Planned {
+            target: mode_target.to_path_buf(),
+            change: Change::Mode(mode),
+            backup: false,
+        });
+    }
+
+    let backup = backup(fs, config.state_dir(), source).await?;
+    Journal::begin(
+        fs,
+        config.state_dir(),
+        &config.active_profile,
+        branch,
+        planned,
+    )
+    .await?
+    .complete(fs)
+    .await?;
+
+    Ok(Restored { revision, backup })
+}
+
+fn tracked_file<'a>(profile: &'a ProfileConfig, source: &Path) -> Result<&'a TrackedFile> {
+    match profile.files.iter().find(|file| file.source == source) {
+        Some(file) => Ok(file),
+        None => bail!("'{}' is not tracked by this profile.", source.display()),
+    }
+}
+
+/// Returns the newest commit on the profile's branch that changed `file` no later than `date`.
+fn commit_at(
+    profile: &ProfileConfig,
+    repo: &Path,
+    git: &dyn Git,
+    file: &TrackedFile,
+    date: DateTime<Local>,
+) -> Result<String> {
+    let commits = git.file_history(repo, &profile.branch, &file.repo_path)?;
+
+    match commits.into_iter().find(|commit| commit.date <= date) {
+        Some(commit) => Ok(commit.id),
+        None => bail!(
+            "{} had not been committed yet on {}.",
+            file.source.display(),
+            date.format("%Y-%m-%d %H:%M")
+        ),
+    }
+}
+
+/// Parses an absolute or relative date, relative to `now`.
+///
+/// Accepted are `now`, `today`, `yesterday`, `<n> <unit>s ago` with units from
+/// seconds to years, and dates like `2024-01-31` or `2024-01-31 18:30`. A date
+/// without a time means the end of that day.
+fn parse_date(text: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let text = text.trim().to_lowercase();
+
+    match text.as_str() {
+        "now" | "today" => return Some(now),
+        "yesterday" => return Some(now - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Some(relative) = text.strip_suffix(" ago") {
+        let (count, unit) = relative.split_once(' ')?;
+        let count: i32 = count.parse().ok()?;
+        let unit = match unit.trim_end_matches('s') {
+            "second" => Duration::seconds(1),
+            "minute" => Duration::minutes(1),
+            "hour" => Duration::hours(1),
+            "day" => Duration::days(1),
+            "week" => Duration::weeks(1),
+            "month" => Duration::days(30),
+            "year" => Duration::days(365),
+            _ => return None,
+        };
+        return now.checked_sub_signed(unit.checked_mul(count)?);
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(&text) {
+        return Some(date.with_timezone(&Local));
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 59))
+        })?;
+
+    Local.from_local_datetime(&naive).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::{file_system::FileSystemClient, git::GitClient};
+    use crate::{config::ConfigLoaderClient, journal::undo, mappings::Mappings};
+    use std::{fs, process::Command};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_history() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".zshrc"), "export EDITOR=nvim\n").unwrap();
+        let mut profile = ProfileConfig::default();
+        profile
+            .track(
+                &FileSystemClient,
+                &home.join(".zshrc"),
+                &home,
+                &Mappings::default(),
+            )
+            .await
+            .unwrap();
+
+        GitClient.init(&repo, "main").unwrap();
+        assert!(history(&profile, &repo, &GitClient, &home.join(".zshrc"))
+            .unwrap()
+            .is_empty());
+
+        fs::create_dir_all(repo.join("home")).unwrap();
+        for (contents, message) in [
+            ("export EDITOR=nvim\nalias ll='ls -l'\n", "Track zshrc"),
+            ("export EDITOR=helix\nalias ll='ls -l'\n", "Switch to helix"),
+        ] {
+            fs::write(repo.join("home/.zshrc"), contents).unwrap();
+            GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+            GitClient.commit(&repo, message).unwrap();
+        }
+
+        let changes = history(&profile, &repo, &GitClient, &home.join(".zshrc")).unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].commit.message, "Switch to helix");
+        assert_eq!(changes[0].diffstat(), (1, 1));
+        assert_eq!(changes[1].diffstat(), (2, 0));
+        assert_eq!(
+            changes[0].patch(),
+            vec![
+                PatchLine::Header("--- a/home/.zshrc".to_string()),
+                PatchLine::Header("+++ b/home/.zshrc".to_string()),
+                PatchLine::Hunk("@@ -1,2 +1,2 @@".to_string()),
+                PatchLine::Removed("-export EDITOR=nvim".to_string()),
+                PatchLine::Added("+export EDITOR=helix".to_string()),
+                PatchLine::Context(" alias ll='ls -l'".to_string()),
+            ]
+        );
+
+        assert!(history(&profile, &repo, &GitClient, &home.join(".bashrc")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+        let zshrc = home.join(".zshrc");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(&zshrc, "export EDITOR=nvim").unwrap();
+        let mut profile = ProfileConfig::default();
+        profile
+            .track(&FileSystemClient, &zshrc, &home, &Mappings::default())
+            .await
+            .unwrap();
+
+        GitClient.init(&repo, "main").unwrap();
+        fs::create_dir_all(repo.join("home")).unwrap();
+        let mut commits = Vec::new();
+        for contents in ["export EDITOR=nvim", "export EDITOR=helix"] {
+            fs::write(repo.join("home/.zshrc"), contents).unwrap();
+            GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+            commits.push(GitClient.commit(&repo, "Update zshrc").unwrap());
+        }
+        Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["tag", "before-helix", &commits[0]])
+            .status()
+            .unwrap();
+
+        fs::write(&zshrc, "export EDITOR=emacs").unwrap();
+        let mut config = TomlConfig {
+            base_path: repo.clone(),
+            profiles: [(String::from("main"), profile)].into(),
+            active_profile: String::from("main"),
+            state_path: Some(temp_dir.path().join("state")),
+            ..TomlConfig::default()
+        };
+        let restored = restore(
+            &config,
+            &FileSystemClient,
+            &GitClient,
+            &zshrc,
+            "before-helix",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(restored.revision, "before-helix");
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=nvim");
+        // The profile links its files, so the old version is linked from the repository.
+        assert_eq!(fs::read_link(&zshrc).unwrap(), repo.join("home/.zshrc"));
+        let backup = restored.backup.unwrap();
+        assert!(backup.starts_with(temp_dir.path().join("state")));
+        assert_eq!(fs::read_to_string(backup).unwrap(), "export EDITOR=emacs");
+
+        // A date picks the newest commit up to then.
+        let restored = restore(&config, &FileSystemClient, &GitClient, &zshrc, "0 days ago")
+            .await
+            .unwrap();
+        assert_eq!(restored.revision, commits[1]);
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=helix");
+
+        // Restoring goes through the journal, so it can be undone.
+        undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=nvim");
+
+        // Only revisions on the profile's branch can be restored.
+        GitClient.create_branch(&repo, "other", "main").unwrap();
+        GitClient.checkout(&repo, "other").unwrap();
+        fs::write(repo.join("home/.zshrc"), "export EDITOR=nano").unwrap();
+        GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+        GitClient.commit(&repo, "Try nano").unwrap();
+        GitClient.checkout(&repo, "main").unwrap();
+
+        for at in ["1 year ago", "someday", "other"] {
+            assert!(restore(&config, &FileSystemClient, &GitClient, &zshrc, at)
+                .await
+                .is_err());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restore_copy() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+        let zshrc = home.join(".zshrc");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(&zshrc, "export EDITOR=nvim").unwrap();
+        let mut profile = ProfileConfig {
+            strategy: Strategy::Copy,
+            ..ProfileConfig::default()
+        };
+        profile
+            .track(&FileSystemClient, &zshrc, &home, &Mappings::default())
+            .await
+            .unwrap();
+        profile.files[0].mode = Some(0o600);
+
+        GitClient.init(&repo, "main").unwrap();
+        fs::create_dir_all(repo.join("home")).unwrap();
+        fs::write(repo.join("home/.zshrc"), "export EDITOR=nvim").unwrap();
+        GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+        let commit = GitClient.commit(&repo, "Track zshrc").unwrap();
+
+        fs::remove_file(&zshrc).unwrap();
+        let config = TomlConfig {
+            base_path: repo.clone(),
+            profiles: [(String::from("main"), profile)].into(),
+            active_profile: String::from("main"),
+            state_path: Some(temp_dir.path().join("state")),
+            ..TomlConfig::default()
+        };
+        restore(&config, &FileSystemClient, &GitClient, &zshrc, &commit)
+            .await
+            .unwrap();
+
+        // A copy is written in place, with the mode recorded in the manifest.
+        assert!(!FileSystemClient.is_symlink(&zshrc));
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=nvim");
+        assert_eq!(FileSystemClient.metadata(&zshrc).await.unwrap().mode, 0o600);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let now = Local.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+
+        assert_eq!(parse_date("yesterday", now), Some(now - Duration::days(1)));
+        assert_eq!(parse_date("2 days ago", now), Some(now - Duration::days(2)));
+        assert_eq!(
+            parse_date("1 week ago", now),
+            Some(now - Duration::weeks(1))
+        );
+        assert_eq!(
+            parse_date("2024-03-01", now),
+            Local.with_ymd_and_hms(2024, 3, 1, 23, 59, 59).single()
+        );
+        assert_eq!(
+            parse_date("2024-03-01 08:15", now),
+            Local.with_ymd_and_hms(2024, 3, 1, 8, 15, 0).single()
+        );
+        assert_eq!(parse_date("2 fortnights ago", now), None);
+        assert_eq!(parse_date("2000000000 years ago", now), None);
+        assert_eq!(parse_date("a1b2c3d", now), None);
+    }
+}
@@ -0,0 +1,684 @@
+//! The operation journal that makes `dotty apply` all or nothing.
+//!
+//! Before anything is changed, every step of a deploy is written to
+//! `journal/journal.toml` in the state directory, together with copies of the
+//! files the steps replace and of the contents they write. Each step is marked
+//! as done once it has run. When a step fails, the steps already run are undone
+//! in reverse order. When Dotty stops partway, the journal is left behind so
+//! that the next run can resume or roll back the deploy.
+//!
+//! A finished journal is kept in `snapshots` as a record of the deploy, so that
+//! `dotty undo` can roll it back later. Profile switches are recorded there as
+//! snapshots without steps.
+
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+// Local module imports
+use crate::{
+    backup::backup,
+    clients::{file_system::FileSystem, git::Git},
+    config::{ConfigLoader, ProfileId, TomlConfig},
+    repository::sync::checkout_branch,
+};
+
+/// Directory inside the state directory that holds the journal of an unfinished deploy.
+pub const JOURNAL_DIR: &str = "journal";
+
+/// Directory inside the state directory that holds the finished journals, one directory each.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+/// How many snapshots are kept for `dotty undo`.
+const SNAPSHOT_LIMIT: usize = 20;
+
+const JOURNAL_FILE: &str = "journal.toml";
+
+/// What a planned step puts at its target.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A symbolic link to the path.
+    Symlink(PathBuf),
+    /// A hard link to the path.
+    Hardlink(PathBuf),
+    /// A file with these contents.
+    Copy(Vec<u8>),
+    /// The same file, with these Unix permission bits.
+    Mode(u32),
+}
+
+/// A change to a single target, decided before anything is changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Planned {
+    pub target: PathBuf,
+    pub change: Change,
+    /// Whether the file at the target is backed up before it is replaced.
+    pub backup: bool,
+}
+
+/// What a step puts at its target, or what was there before it ran.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "lowercase")]
+enum Entry {
+    Nothing,
+    /// Whatever is at the target, left in place.
+    Unchanged,
+    /// A symbolic link to the path.
+    Symlink(PathBuf),
+    /// A hard link to the path.
+    Hardlink(PathBuf),
+    /// A copy of the file at the path, relative to the journal directory.
+    Copy(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Step {
+    target: PathBuf,
+    change: Entry,
+    previous: Entry,
+    backup: bool,
+    /// The permission bits the step sets, and those the target had before.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_mode: Option<u32>,
+    #[serde(default)]
+    done: bool,
+}
+
+/// The steps of a deploy, and how far it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    pub created: DateTime<Local>,
+    /// The profile that was applied, or switched to.
+    pub profile: ProfileId,
+    /// The profile that was active before, when this records a profile switch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub switched_from: Option<ProfileId>,
+    /// The branch that was checked out in the repository before.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, rename = "step")]
+    steps: Vec<Step>,
+    #[serde(skip)]
+    dir: PathBuf,
+    #[serde(skip)]
+    state_path: PathBuf,
+}
+
+impl Journal {
+    /// Returns the journal of a deploy that did not finish, if there is one.
+    ///
+    /// # Errors
+    /// This function will return an error if the journal cannot be read or parsed.
+    ///
+    pub async fn pending(fs: &impl FileSystem, state_path: &Path) -> Result<Option<Journal>> {
+        let dir = state_path.join(JOURNAL_DIR);
+        if !fs.exists(&dir.join(JOURNAL_FILE)) {
+            return Ok(None);
+        }
+
+        Self::load(fs, &dir, state_path).await.map(Some)
+    }
+
+    /// Lists the snapshots of earlier deploys and profile switches, newest first.
+    ///
+    /// # Errors
+    /// This function will return an error if a snapshot cannot be read or parsed.
+    ///
+    pub async fn snapshots(fs: &impl FileSystem, state_path: &Path) -> Result<Vec<Journal>> {
+        let dir = state_path.join(SNAPSHOT_DIR);
+        if !fs.is_dir(&dir) {
+            return Ok(Vec::new());
+        }
+
+        let mut dirs = fs.read_dir(&dir).await?;
+        dirs.sort();
+
+        let mut snapshots = Vec::new();
+        for dir in dirs.iter().rev() {
+            snapshots.push(Self::load(fs, dir, state_path).await?);
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Records a switch from the profile `from` to `to` as a snapshot.
+    ///
+    /// # Errors
+    /// This function will return an error if the snapshot cannot be written.
+    ///
+    pub async fn record_switch(
+        fs: &impl FileSystem,
+        state_path: &Path,
+        from: &str,
+        to: &str,
+    ) -> Result<()> {
+        let created = Local::now();
+        let journal = Journal {
+            created,
+            profile: to.to_string(),
+            switched_from: Some(from.to_string()),
+            branch: None,
+            steps: Vec::new(),
+            dir: snapshot_dir(state_path, created),
+            state_path: state_path.to_path_buf(),
+        };
+
+        journal.save(fs).await
+    }
+
+    async fn load(fs: &impl FileSystem, dir: &Path, state_path: &Path) -> Result<Journal> {
+        let path = dir.join(JOURNAL_FILE);
+        let mut journal: Journal = toml::from_str(&fs.read_to_string(&path).await?)
+            .with_context(|| format!("Unable to parse {}.", path.display()))?;
+        journal.dir = dir.to_path_buf();
+        journal.state_path = state_path.to_path_buf();
+
+        Ok(journal)
+    }
+
+    /// Writes the journal for `planned` without changing any of the targets.
+    ///
+    /// What is at each target now is copied into the journal, so that it can be
+    /// put back, and so are the contents that will be written.
+    ///
+    /// # Arguments
+    /// * `fs` - An implementation of `FileSystem` for writing the journal.
+    /// * `state_path` - The state directory, which holds the journal and backups.
+    /// * `profile` - The profile being applied.
+    /// * `branch` - The branch checked out in the repository before the deploy.
+    /// * `planned` - The steps, in the order they are run.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The journal of an earlier deploy is still pending.
+    /// - The journal cannot be written.
+    ///
+    pub async fn begin(
+        fs: &impl FileSystem,
+        state_path: &Path,
+        profile: &str,
+        branch: Option<String>,
+        planned: Vec<Planned>,
+    ) -> Result<Journal> {
+        if Self::pending(fs, state_path).await?.is_some() {
+            bail!("An earlier `dotty apply` did not finish. Resume or roll it back first.");
+        }
+
+        let dir = state_path.join(JOURNAL_DIR);
+        let mut steps = Vec::new();
+        for (index, plan) in planned.into_iter().enumerate() {
+            let target = plan.target;
+            if let Change::Mode(mode) = plan.change {
+                let previous_mode = match fs.exists(&target) {
+                    true => Some(fs.metadata(&target).await?.mode),
+                    false => None,
+                };
+                steps.push(Step {
+                    target,
+                    change: Entry::Unchanged,
+                    previous: Entry::Unchanged,
+                    backup: false,
+                    mode: Some(mode),
+                    previous_mode,
+                    done: false,
+                });
+                continue;
+            }
+
+            let previous = if fs.is_symlink(&target) {
+                Entry::Symlink(fs.read_link(&target).await?)
+            } else if fs.exists(&target) {
+                let copy = Path::new("previous").join(index.to_string());
+                fs.copy(&target, &dir.join(&copy)).await?;
+                Entry::Copy(copy)
+            } else {
+                Entry::Nothing
+            };
+
+            let change = match plan.change {
+                Change::Symlink(path) => Entry::Symlink(path),
+                Change::Hardlink(path) => Entry::Hardlink(path),
+                Change::Copy(contents) => {
+                    let copy = Path::new("changes").join(index.to_string());
+                    fs.write_bytes(&dir.join(&copy), &contents).await?;
+                    Entry::Copy(copy)
+                }
+                Change::Mode(_) => unreachable!("Mode changes are planned above."),
+            };
+
+            steps.push(Step {
+                target,
+                change,
+                previous,
+                backup: plan.backup,
+                mode: None,
+                previous_mode: None,
+                done: false,
+            });
+        }
+
+        let journal = Journal {
+            created: Local::now(),
+            profile: profile.to_string(),
+            switched_from: None,
+            branch,
+            steps,
+            dir,
+            state_path: state_path.to_path_buf(),
+        };
+        journal.save(fs).await?;
+
+        Ok(journal)
+    }
+
+    /// Returns the targets of the steps, in the order they are run, each once.
+    pub fn targets(&self) -> impl Iterator<Item = &Path> {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|(index, step)| {
+                !self.steps[..*index]
+                    .iter()
+                    .any(|earlier| earlier.target == step.target)
+            })
+            .map(|(_, step)| step.target.as_path())
+    }
+
+    /// Returns how many targets still have steps that have not run yet.
+    pub fn remaining(&self) -> usize {
+        self.targets()
+            .filter(|target| {
+                self.steps
+                    .iter()
+                    .any(|step| step.target == *target && !step.done)
+            })
+            .count()
+    }
+
+    /// Runs the steps that have not run yet, in order, marking each as done.
+    ///
+    /// # Errors
+    /// This function will return an error if a step fails, which leaves the
+    /// steps before it done.
+    ///
+    pub async fn run(&mut self, fs: &impl FileSystem) -> Result<()> {
+        for index in 0..self.steps.len() {
+            let step = &self.steps[index];
+            if step.done {
+                continue;
+            }
+
+            self.perform(fs, step)
+                .await
+                .with_context(|| format!("Unable to apply {}.", step.target.display()))?;
+            log::info!("Applied {}", step.target.display());
+
+            self.steps[index].done = true;
+            self.save(fs).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the steps that have not run yet and keeps the journal as a snapshot,
+    /// or rolls everything back when a step fails.
+    ///
+    /// # Errors
+    /// This function will return an error if a step fails, after the roll back,
+    /// or if the roll back fails as well.
+    ///
+    pub async fn complete(mut self, fs: &impl FileSystem) -> Result<()> {
+        if let Err(error) = self.run(fs).await {
+            self.roll_back(fs)
+                .await
+                .context("The files already applied could not be put back.")?;
+            return Err(error.context("The files already applied were put back."));
+        }
+
+        self.finish(fs).await
+    }
+
+    /// Moves the journal to the snapshots once every step has run, dropping the
+    /// oldest snapshots beyond `SNAPSHOT_LIMIT`. A journal without steps changed
+    /// nothing, so it is removed instead.
+    async fn finish(self, fs: &impl FileSystem) -> Result<()> {
+        if self.steps.is_empty() {
+            return fs.remove_dir_all(&self.dir).await;
+        }
+
+        fs.rename(&self.dir, &snapshot_dir(&self.state_path, self.created))
+            .await?;
+
+        let mut dirs = fs.read_dir(&self.state_path.join(SNAPSHOT_DIR)).await?;
+        dirs.sort();
+        for dir in dirs.iter().rev().skip(SNAPSHOT_LIMIT) {
+            fs.remove_dir_all(dir).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Puts back what was at the targets of the steps that ran, newest first,
+    /// and removes the journal or snapshot.
+    ///
+    /// The first step that is not marked as done is undone as well, as it may
+    /// have been interrupted.
+    ///
+    /// # Errors
+    /// This function will return an error if a target cannot be put back. The
+    /// journal is kept so that the roll back can be tried again.
+    ///
+    pub async fn roll_back(self, fs: &impl FileSystem) -> Result<()> {
+        let done = self.steps.iter().filter(|step| step.done).count();
+        let started = (done + 1).min(self.steps.len());
+
+        for step in self.steps[..started].iter().rev() {
+            if let Some(mode) = step.previous_mode {
+                if fs.exists(&step.target) {
+                    fs.set_permissions(&step.target, mode).await?;
+                }
+            }
+            if step.previous == Entry::Unchanged {
+                log::info!("Rolled back the mode of {}", step.target.display());
+                continue;
+            }
+
+            clear(fs, &step.target).await?;
+            match &step.previous {
+                Entry::Nothing | Entry::Unchanged => {}
+                Entry::Symlink(path) => fs.symlink(path, &step.target).await?,
+                Entry::Hardlink(path) => fs.hard_link(path, &step.target).await?,
+                Entry::Copy(path) => fs.copy(&self.dir.join(path), &step.target).await?,
+            }
+            log::info!("Rolled back {}", step.target.display());
+        }
+
+        fs.remove_dir_all(&self.dir).await
+    }
+
+    async fn perform(&self, fs: &impl FileSystem, step: &Step) -> Result<()> {
+        if step.backup {
+            backup(fs, &self.state_path, &step.target).await?;
+        }
+
+        if step.change != Entry::Unchanged {
+            clear(fs, &step.target).await?;
+        }
+        match &step.change {
+            Entry::Nothing | Entry::Unchanged => {}
+            Entry::Symlink(path) => fs.symlink(path, &step.target).await?,
+            Entry::Hardlink(path) => fs.hard_link(path, &step.target).await?,
+            Entry::Copy(path) => fs.copy(&self.dir.join(path), &step.target).await?,
+        }
+
+        if let Some(mode) = step.mode {
+            fs.set_permissions(&step.target, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, fs: &impl FileSystem) -> Result<()> {
+        fs.write(&self.dir.join(JOURNAL_FILE), &toml::to_string(self)?)
+            .await
+    }
+}
+
+/// Rolls back the `count` newest snapshots, newest first.
+///
+/// The files of an apply are put back as they were, and the branch that was
+/// checked out before it is checked out again. A profile switch makes the
+/// previous profile active again in `config`, which is saved before its
+/// snapshot is removed, so that a later snapshot failing to roll back does not
+/// lose the switch.
+///
+/// # Arguments
+/// * `config` - The current configuration.
+/// * `fs` - An implementation of `FileSystem` for putting back the files.
+/// * `loader` - An implementation of `ConfigLoader` for saving the configuration.
+/// * `git` - An implementation of `Git` for checking out the earlier branch.
+/// * `count` - How many snapshots to roll back.
+///
+/// # Returns
+/// Returns the snapshots that were rolled back, newest first.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There are fewer than `count` snapshots.
+/// - A switch is undone to a profile that no longer exists.
+/// - The configuration cannot be saved.
+/// - The files cannot be put back, or the branch cannot be checked out.
+///
+pub async fn undo(
+    config: &mut TomlConfig,
+    fs: &impl FileSystem,
+    loader: &impl ConfigLoader,
+    git: &dyn Git,
+    count: usize,
+) -> Result<Vec<Journal>> {
+    let mut snapshots = Journal::snapshots(fs, config.state_dir()).await?;
+    if snapshots.len() < count {
+        bail!(
+            "There are only {} snapshots to undo. Run `dotty history` to list them.",
+            snapshots.len()
+        );
+    }
+    snapshots.truncate(count);
+
+    let mut undone = Vec::new();
+    for snapshot in snapshots {
+        if let Some(from) = &snapshot.switched_from {
+            if !config.profiles.contains_key(from) {
+                bail!(
+                    "Profile '{}' no longer exists, so the switch to '{}' cannot be undone.",
+                    from,
+                    snapshot.profile
+                );
+            }
+            config.active_profile = from.clone();
+            config.save(fs, loader).await?;
+        }
+
+        snapshot.clone().roll_back(fs).await?;
+        if let Some(branch) = &snapshot.branch {
+            checkout_branch(git, &config.base_path, branch)?;
+        }
+        undone.push(snapshot);
+    }
+
+    Ok(undone)
+}
+
+/// Returns the directory a snapshot made at `created` is kept in.
+fn snapshot_dir(state_path: &Path, created: DateTime<Local>) -> PathBuf {
+    state_path
+        .join(SNAPSHOT_DIR)
+        .join(created.format("%Y%m%dT%H%M%S%.3f").to_string())
+}
+
+/// Removes the file or symbolic link at `target`, if there is one.
+async fn clear(fs: &impl FileSystem, target: &Path) -> Result<()> {
+    if fs.is_symlink(target) || fs.exists(target) {
+        fs.remove_file(target).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::{file_system::FileSystemClient, git::GitClient};
+    use crate::config::ConfigLoaderClient;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_journal() {
+        let temp_dir = tempdir().unwrap();
+        let state_path = temp_dir.path().join("state");
+        let home = temp_dir.path().join("home");
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".zshrc"), "export EDITOR=nano").unwrap();
+
+        let planned = vec![
+            Planned {
+                target: home.join(".zshrc"),
+                change: Change::Copy(b"export EDITOR=nvim".to_vec()),
+                backup: false,
+            },
+            Planned {
+                target: home.join(".vimrc"),
+                change: Change::Copy(b"set number".to_vec()),
+                backup: false,
+            },
+        ];
+        let mut journal = Journal::begin(
+            &FileSystemClient,
+            &state_path,
+            "default",
+            None,
+            planned.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(journal.remaining(), 2);
+        assert!(
+            Journal::begin(&FileSystemClient, &state_path, "default", None, planned,)
+                .await
+                .is_err()
+        );
+
+        // Dotty stops after the first step.
+        journal
+            .perform(&FileSystemClient, &journal.steps[0])
+            .await
+            .unwrap();
+        journal.steps[0].done = true;
+        journal.save(&FileSystemClient).await.unwrap();
+
+        let mut pending = Journal::pending(&FileSystemClient, &state_path)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.remaining(), 1);
+        pending.run(&FileSystemClient).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(home.join(".vimrc")).unwrap(),
+            "set number"
+        );
+
+        pending.roll_back(&FileSystemClient).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(home.join(".zshrc")).unwrap(),
+            "export EDITOR=nano"
+        );
+        assert!(!home.join(".vimrc").exists());
+        assert!(Journal::pending(&FileSystemClient, &state_path)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_undo_switch() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            active_profile: "work".to_string(),
+            state_path: Some(temp_dir.path().join("state")),
+            ..TomlConfig::default()
+        };
+        for id in ["home", "work"] {
+            config.profiles.insert(id.to_string(), Default::default());
+        }
+
+        Journal::record_switch(&FileSystemClient, config.state_dir(), "home", "work")
+            .await
+            .unwrap();
+        let snapshots = Journal::snapshots(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap();
+        assert_eq!(snapshots[0].switched_from.as_deref(), Some("home"));
+
+        undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(config.active_profile, "home");
+        assert!(Journal::snapshots(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undo_saves_each_switch() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            active_profile: "work".to_string(),
+            state_path: Some(temp_dir.path().join("state")),
+            ..TomlConfig::default()
+        };
+        for id in ["home", "work"] {
+            config.profiles.insert(id.to_string(), Default::default());
+        }
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".zshrc"), "export EDITOR=nano").unwrap();
+
+        // An apply whose snapshot cannot be rolled back, followed by a switch.
+        let planned = vec![Planned {
+            target: home.join(".zshrc"),
+            change: Change::Copy(b"export EDITOR=nvim".to_vec()),
+            backup: false,
+        }];
+        Journal::begin(&FileSystemClient, config.state_dir(), "work", None, planned)
+            .await
+            .unwrap()
+            .complete(&FileSystemClient)
+            .await
+            .unwrap();
+        let snapshots = Journal::snapshots(&FileSystemClient, config.state_dir())
+            .await
+            .unwrap();
+        fs::remove_dir_all(snapshots[0].dir.join("previous")).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        Journal::record_switch(&FileSystemClient, config.state_dir(), "home", "work")
+            .await
+            .unwrap();
+
+        assert!(undo(
+            &mut config,
+            &FileSystemClient,
+            &ConfigLoaderClient,
+            &GitClient,
+            2,
+        )
+        .await
+        .is_err());
+
+        // The switch was undone and saved before the apply failed.
+        let saved = fs::read_to_string(config.base_path.join("config.toml")).unwrap();
+        assert!(saved.contains("active_profile = \"home\""));
+        assert_eq!(
+            Journal::snapshots(&FileSystemClient, config.state_dir())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -1,5 +1,16 @@
 // Submodules
+pub mod apply;
+pub mod assets;
+pub mod backup;
 pub mod cli;
 pub mod clients;
+pub mod command;
 pub mod config;
+pub mod externals;
+pub mod history;
+pub mod journal;
+pub mod manifest;
+pub mod mappings;
+pub mod repository;
+pub mod status;
 pub mod ui;
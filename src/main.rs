@@ -6,22 +6,48 @@ use anyhow::Result;
 use clap::{self, Parser};
 
 // Local module imports
-use dotty::cli::Cli;
-use dotty::clients::{file_system::FileSystemClient, git::GitClient};
+use dotty::cli::{print_plan, Cli};
+use dotty::clients::dry_run::{Plan, RecordingConfigLoader, RecordingFileSystem, RecordingGit};
+use dotty::clients::file_system::FileSystemClient;
+use dotty::clients::git::Git;
 use dotty::config::{ConfigLoaderClient, TomlConfig};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // A dry run reads through recording clients, which keep every change in the plan.
+    if cli.dry_run {
+        let plan = Arc::new(Plan::default());
+        let fs = RecordingFileSystem::new(FileSystemClient, plan.clone());
+        let loader = RecordingConfigLoader::new(plan.clone());
+
+        let config = TomlConfig::from_path_or_default(&fs, &loader).await?;
+        // Dotty's own journal, snapshots and cache are not part of what the user asked for.
+        plan.exclude(config.state_dir());
+        plan.exclude(config.cache_dir());
+        // Like the client it wraps, the recording client is never shared between threads.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let git: Arc<dyn Git> = Arc::new(RecordingGit::new(
+            config.git_backend.client()?,
+            plan.clone(),
+        ));
+
+        let result = cli.command.execute(config, &fs, &loader, git).await;
+        print_plan(&plan);
+
+        return result;
+    }
+
     let fs = FileSystemClient;
     let loader = ConfigLoaderClient;
-    let git = Arc::new(GitClient);
 
     let config = TomlConfig::from_path_or_default(&fs, &loader).await?;
     config
         .configure_logging(ConfigLoaderClient::is_running_under_cargo)
         .await?;
 
-    let cli = Cli::parse();
+    let git = config.git_backend.client()?;
 
     cli.command.execute(config, &fs, &loader, git).await?;
 
@@ -0,0 +1,478 @@
+// Standard library imports
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError},
+    },
+    config::{ProfileConfig, Strategy},
+    mappings::Mappings,
+};
+
+/// Directory inside the repository that mirrors the user's home directory.
+pub const HOME_DIR: &str = "home";
+
+/// File at the root of the repository recording the mode of every tracked file.
+///
+/// Git only keeps the executable bit, so this carries the rest to other machines.
+pub const MODES_FILE: &str = "modes.toml";
+
+/// A single file tracked by a profile.
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
+pub struct TrackedFile {
+    /// The original location of the file on the system.
+    pub source: PathBuf,
+    /// The location of the file inside the dotfiles repository, relative to its root.
+    pub repo_path: PathBuf,
+    /// When the file was first tracked.
+    pub added: DateTime<Utc>,
+    /// How the file is deployed, overriding the profile's strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<Strategy>,
+    /// The Unix permission bits of the file, which `dotty apply` restores.
+    #[serde(default, with = "octal", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+}
+
+/// Writes modes as octal strings, such as `"600"`, the way `chmod` takes them.
+mod octal {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(mode: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+        match mode {
+            Some(mode) => serializer.serialize_str(&format!("{:o}", mode)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u32>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(mode) => parse(&mode).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    pub fn parse(mode: &str) -> anyhow::Result<u32> {
+        match u32::from_str_radix(mode, 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(mode),
+            _ => bail!("'{}' is not a file mode, such as \"600\".", mode),
+        }
+    }
+}
+
+/// Reads the modes recorded in `modes.toml` on `branch`, by path in the repository.
+///
+/// # Errors
+/// This function will return an error if the file cannot be read or holds an invalid mode.
+///
+pub fn committed_modes(repo: &Path, branch: &str, git: &dyn Git) -> Result<BTreeMap<PathBuf, u32>> {
+    let contents = match git.show_file(repo, branch, Path::new(MODES_FILE)) {
+        Ok(contents) => contents.unwrap_or_default(),
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            Vec::new()
+        }
+        Err(error) => return Err(error),
+    };
+
+    let modes: BTreeMap<PathBuf, String> = toml::from_str(&String::from_utf8(contents)?)
+        .with_context(|| format!("Unable to read {} on branch '{}'.", MODES_FILE, branch))?;
+    modes
+        .into_iter()
+        .map(|(path, mode)| Ok((path, octal::parse(&mode)?)))
+        .collect()
+}
+
+/// Resolves `path` against `cwd` and lexically removes `.` and `..` components.
+///
+/// Symbolic links are deliberately not followed, as a deployed dotfile is often
+/// a symlink into the repository and we want to record where it lives, not what
+/// it points at.
+///
+pub fn resolve_path(path: &Path, cwd: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+
+    resolved
+}
+
+impl ProfileConfig {
+    pub fn is_tracked(&self, source: &Path) -> bool {
+        self.files.iter().any(|file| file.source == source)
+    }
+
+    /// Returns how `file` is deployed.
+    pub fn strategy_for(&self, file: &TrackedFile) -> Strategy {
+        file.strategy.unwrap_or(self.strategy)
+    }
+
+    /// Adds `source` to the profile's manifest, recording the mode of every file.
+    ///
+    /// Directories are walked recursively and every file inside them is tracked
+    /// individually. Files that are already tracked only have their mode updated.
+    ///
+    /// # Arguments
+    /// * `fs` - An implementation of `FileSystem` used to inspect the path.
+    /// * `source` - The absolute, normalised path to track.
+    /// * `home` - The user's home directory.
+    /// * `mappings` - The rules deciding where the files are kept in the repository.
+    ///
+    /// # Returns
+    /// Returns the newly tracked files.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - `source` does not exist.
+    /// - `source` is outside of `home` and not covered by a mapping.
+    /// - A directory or the mode of a file cannot be read.
+    ///
+    pub async fn track(
+        &mut self,
+        fs: &impl FileSystem,
+        source: &Path,
+        home: &Path,
+        mappings: &Mappings,
+    ) -> Result<Vec<TrackedFile>> {
+        if !fs.exists(source) {
+            bail!("'{}' does not exist.", source.display());
+        }
+
+        mappings.repo_path(source, home)?;
+
+        if fs.is_dir(source) && !self.directories.iter().any(|dir| dir == source) {
+            self.directories.push(source.to_path_buf());
+        }
+
+        let mut pending = vec![source.to_path_buf()];
+        let mut tracked = Vec::new();
+
+        while let Some(path) = pending.pop() {
+            if fs.is_dir(&path) {
+                let mut entries = fs.read_dir(&path).await?;
+                entries.reverse();
+                pending.extend(entries);
+                continue;
+            }
+
+            let mode = fs.metadata(&path).await?.mode;
+            if let Some(file) = self.files.iter_mut().find(|file| file.source == path) {
+                log::info!("Already tracked: {}", path.display());
+                file.mode = Some(mode);
+                continue;
+            }
+
+            let file = TrackedFile {
+                repo_path: mappings.repo_path(&path, home)?,
+                source: path,
+                added: Utc::now(),
+                strategy: None,
+                mode: Some(mode),
+            };
+
+            self.files.push(file.clone());
+            tracked.push(file);
+        }
+
+        Ok(tracked)
+    }
+
+    /// Records the mode of every file that was tracked without one and exists on disk.
+    ///
+    /// # Returns
+    /// Returns whether any mode was recorded.
+    ///
+    /// # Errors
+    /// This function will return an error if the mode of a file cannot be read.
+    ///
+    pub async fn record_modes(&mut self, fs: &impl FileSystem) -> Result<bool> {
+        let mut recorded = false;
+
+        for file in &mut self.files {
+            if file.mode.is_none() && fs.exists(&file.source) {
+                file.mode = Some(fs.metadata(&file.source).await?.mode);
+                recorded = true;
+            }
+        }
+
+        Ok(recorded)
+    }
+
+    /// Serializes the recorded modes for `modes.toml`, or returns `None` when there are none.
+    ///
+    /// # Errors
+    /// This function will return an error if the modes cannot be serialized.
+    ///
+    pub fn modes_file(&self) -> Result<Option<String>> {
+        let modes: BTreeMap<&Path, String> = self
+            .files
+            .iter()
+            .filter_map(|file| Some((file.repo_path.as_path(), format!("{:o}", file.mode?))))
+            .collect();
+
+        match modes.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(toml::to_string(&modes)?)),
+        }
+    }
+
+    /// Moves the profile's files to where `mappings` now places them on this machine.
+    ///
+    /// # Returns
+    /// Returns the old and new location of every file that moved.
+    ///
+    pub fn relocate(&mut self, mappings: &Mappings, home: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let mut moved = Vec::new();
+
+        for file in &mut self.files {
+            match mappings.source_for(&file.repo_path, home) {
+                Some(source) if source != file.source => {
+                    moved.push((std::mem::replace(&mut file.source, source.clone()), source));
+                }
+                _ => {}
+            }
+        }
+
+        moved
+    }
+
+    /// Removes `source` from the profile's manifest.
+    ///
+    /// When `source` is a directory, every tracked file inside it is removed, and
+    /// it is no longer watched for new files.
+    ///
+    /// # Returns
+    /// Returns the files that are no longer tracked.
+    ///
+    /// # Errors
+    /// This function will return an error if nothing matching `source` is tracked.
+    ///
+    pub fn untrack(&mut self, source: &Path) -> Result<Vec<TrackedFile>> {
+        let (removed, kept) = self
+            .files
+            .drain(..)
+            .partition(|file| file.source.starts_with(source));
+
+        self.files = kept;
+        self.directories.retain(|dir| !dir.starts_with(source));
+
+        if removed.is_empty() {
+            bail!("'{}' is not tracked.", source.display());
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::file_system::{Metadata, MockFileSystem};
+    use mockall::predicate::*;
+
+    fn home() -> PathBuf {
+        PathBuf::from("/home/dotty")
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let cwd = Path::new("/home/dotty/.config");
+
+        assert_eq!(
+            resolve_path(Path::new("alacritty/alacritty.toml"), cwd),
+            PathBuf::from("/home/dotty/.config/alacritty/alacritty.toml")
+        );
+        assert_eq!(
+            resolve_path(Path::new("../.zshrc"), cwd),
+            PathBuf::from("/home/dotty/.zshrc")
+        );
+        assert_eq!(
+            resolve_path(Path::new("/etc/./hosts"), cwd),
+            PathBuf::from("/etc/hosts")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_track_file() {
+        let mut mock_fs = MockFileSystem::new();
+        let source = home().join(".zshrc");
+
+        mock_fs
+            .expect_exists()
+            .with(eq(source.clone()))
+            .return_const(true);
+        mock_fs
+            .expect_is_dir()
+            .with(eq(source.clone()))
+            .return_const(false);
+        mock_fs
+            .expect_metadata()
+            .with(eq(source.clone()))
+            .returning(|_| Ok(Metadata { mode: 0o600 }));
+
+        let mut profile = ProfileConfig::default();
+        let tracked = profile
+            .track(&mock_fs, &source, &home(), &Mappings::default())
+            .await
+            .unwrap();
+
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(profile.files[0].source, source);
+        assert_eq!(profile.files[0].repo_path, PathBuf::from("home/.zshrc"));
+        assert_eq!(profile.files[0].mode, Some(0o600));
+
+        // Tracking the same file again is a no-op.
+        let tracked = profile
+            .track(&mock_fs, &source, &home(), &Mappings::default())
+            .await
+            .unwrap();
+        assert!(tracked.is_empty());
+        assert_eq!(profile.files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_track_directory() {
+        let mut mock_fs = MockFileSystem::new();
+        let dir = home().join(".config/waybar");
+        let config = dir.join("config");
+        let style = dir.join("style.css");
+
+        mock_fs.expect_exists().return_const(true);
+        mock_fs
+            .expect_is_dir()
+            .returning(|path| path.ends_with("waybar"));
+        mock_fs
+            .expect_metadata()
+            .returning(|_| Ok(Metadata { mode: 0o644 }));
+        let entries = vec![config.clone(), style.clone()];
+        mock_fs
+            .expect_read_dir()
+            .with(eq(dir.clone()))
+            .returning(move |_| Ok(entries.clone()));
+
+        let mut profile = ProfileConfig::default();
+        profile
+            .track(&mock_fs, &dir, &home(), &Mappings::default())
+            .await
+            .unwrap();
+
+        let sources: Vec<_> = profile.files.iter().map(|f| f.source.clone()).collect();
+        assert_eq!(sources, vec![config, style]);
+        assert_eq!(profile.directories, vec![dir.clone()]);
+
+        profile.untrack(&dir).unwrap();
+        assert!(profile.directories.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_track_missing_file() {
+        let mut mock_fs = MockFileSystem::new();
+        mock_fs.expect_exists().return_const(false);
+
+        let mut profile = ProfileConfig::default();
+        let result = profile
+            .track(
+                &mock_fs,
+                &home().join(".missing"),
+                &home(),
+                &Mappings::default(),
+            )
+            .await;
+
+        assert!(result.unwrap_err().to_string().contains("does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_track_outside_home() {
+        let mut mock_fs = MockFileSystem::new();
+        mock_fs.expect_exists().return_const(true);
+
+        let mut profile = ProfileConfig::default();
+        let result = profile
+            .track(
+                &mock_fs,
+                Path::new("/etc/hosts"),
+                &home(),
+                &Mappings::default(),
+            )
+            .await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("outside of your home directory"));
+        assert!(profile.files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_untrack() {
+        let mut mock_fs = MockFileSystem::new();
+        mock_fs.expect_exists().return_const(true);
+        mock_fs.expect_is_dir().return_const(false);
+        mock_fs
+            .expect_metadata()
+            .returning(|_| Ok(Metadata { mode: 0o644 }));
+
+        let mut profile = ProfileConfig::default();
+        for name in [
+            ".zshrc",
+            ".config/waybar/config",
+            ".config/waybar/style.css",
+        ] {
+            profile
+                .track(&mock_fs, &home().join(name), &home(), &Mappings::default())
+                .await
+                .unwrap();
+        }
+
+        let removed = profile.untrack(&home().join(".config/waybar")).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(profile.files.len(), 1);
+
+        assert!(profile.untrack(&home().join(".bashrc")).is_err());
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut profile = ProfileConfig::default();
+        for (name, mode) in [(".netrc", Some(0o600)), (".zshrc", None)] {
+            profile.files.push(TrackedFile {
+                source: home().join(name),
+                repo_path: Path::new(HOME_DIR).join(name),
+                added: Utc::now(),
+                strategy: None,
+                mode,
+            });
+        }
+
+        assert_eq!(
+            profile.modes_file().unwrap().unwrap(),
+            "\"home/.netrc\" = \"600\"\n"
+        );
+
+        let serialized = toml::to_string(&profile.files[0]).unwrap();
+        assert!(serialized.contains("mode = \"600\""));
+        let file: TrackedFile = toml::from_str(&serialized).unwrap();
+        assert_eq!(file.mode, Some(0o600));
+        assert!(toml::from_str::<TrackedFile>(&serialized.replace("600", "800")).is_err());
+    }
+}
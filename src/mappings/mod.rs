@@ -0,0 +1,415 @@
+//! Rules mapping paths inside the repository to locations on the system.
+//!
+//! A profile can add its own rules in `mappings.toml` at the root of its branch:
+//!
+//! ```toml
+//! "alacritty/" = "${XDG_CONFIG_HOME}/alacritty/"
+//! "zsh/zshrc" = "~/.zshrc"
+//! ```
+//!
+//! A rule ending in `/` maps a whole directory, any other rule a single file.
+//! System paths may start with `~` and use `$HOME` and the XDG base directory
+//! variables, which fall back to their defaults under the home directory when
+//! they are not set. The built-in rules for well-known applications apply
+//! unless a rule for the same repository path replaces them, and everything
+//! else under the home directory is kept in `home/`.
+
+// Standard library imports
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Component, Path, PathBuf},
+};
+
+// External crate imports
+use anyhow::{bail, Context, Result};
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError},
+    },
+    config::xdg_dir,
+    manifest::HOME_DIR,
+};
+
+/// File at the root of the repository holding the profile's own rules.
+pub const MAPPINGS_FILE: &str = "mappings.toml";
+
+/// Rules shipped with Dotty, checked after the profile's own rules.
+const DEFAULT_MAPPINGS: &[(&str, &str)] = &[
+    ("alacritty/", "${XDG_CONFIG_HOME}/alacritty/"),
+    ("dunst/", "${XDG_CONFIG_HOME}/dunst/"),
+    ("fish/", "${XDG_CONFIG_HOME}/fish/"),
+    ("git/", "${XDG_CONFIG_HOME}/git/"),
+    ("helix/", "${XDG_CONFIG_HOME}/helix/"),
+    ("hypr/", "${XDG_CONFIG_HOME}/hypr/"),
+    ("i3/", "${XDG_CONFIG_HOME}/i3/"),
+    ("kitty/", "${XDG_CONFIG_HOME}/kitty/"),
+    ("nvim/", "${XDG_CONFIG_HOME}/nvim/"),
+    ("rofi/", "${XDG_CONFIG_HOME}/rofi/"),
+    ("starship.toml", "${XDG_CONFIG_HOME}/starship.toml"),
+    ("sway/", "${XDG_CONFIG_HOME}/sway/"),
+    ("waybar/", "${XDG_CONFIG_HOME}/waybar/"),
+    ("wezterm/", "${XDG_CONFIG_HOME}/wezterm/"),
+];
+
+/// Variables that can be used in system paths, with their default when unset.
+const VARIABLES: &[(&str, &str)] = &[
+    ("HOME", ""),
+    ("XDG_CONFIG_HOME", ".config"),
+    ("XDG_DATA_HOME", ".local/share"),
+    ("XDG_STATE_HOME", ".local/state"),
+    ("XDG_CACHE_HOME", ".cache"),
+];
+
+/// A single rule, with the system path not yet expanded.
+#[derive(Debug, Clone, PartialEq)]
+struct Mapping {
+    repo: PathBuf,
+    system: String,
+    is_dir: bool,
+}
+
+/// The rules of a profile, followed by the built-in ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mappings {
+    rules: Vec<Mapping>,
+}
+
+impl Default for Mappings {
+    fn default() -> Self {
+        Self::parse("").unwrap()
+    }
+}
+
+impl Mappings {
+    /// Reads the rules in `contents`, the text of a `mappings.toml`.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - `contents` is not a TOML table of strings.
+    /// - A repository path is absolute, contains `..` or is inside `.git`.
+    /// - Only one side of a rule ends in `/`.
+    /// - A system path uses an unknown variable or is not absolute once expanded.
+    ///
+    pub fn parse(contents: &str) -> Result<Self> {
+        let user: BTreeMap<String, String> = toml::from_str(contents)?;
+        let defaults = DEFAULT_MAPPINGS
+            .iter()
+            .map(|(repo, system)| (repo.to_string(), system.to_string()))
+            .filter(|(repo, _)| !user.contains_key(repo))
+            .collect::<Vec<_>>();
+        let home = (format!("{}/", HOME_DIR), "~/".to_string());
+
+        let mut rules = Vec::new();
+        for (repo, system) in user.into_iter().chain(defaults).chain([home]) {
+            rules.push(
+                Mapping::new(&repo, &system)
+                    .with_context(|| format!("Invalid mapping '{}'", repo))?,
+            );
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Maps a file on the system to its location inside the repository.
+    ///
+    /// The rule with the longest matching system path wins, and the profile's
+    /// own rules win over the built-in ones.
+    ///
+    /// # Errors
+    /// This function will return an error if `source` is the home directory, or
+    /// is outside of it and not covered by any rule.
+    ///
+    pub fn repo_path(&self, source: &Path, home: &Path) -> Result<PathBuf> {
+        if source == home {
+            bail!("Your home directory itself cannot be tracked.");
+        }
+
+        let mut best: Option<(usize, PathBuf)> = None;
+        for rule in &self.rules {
+            let system = expand(&rule.system, home, &|name| env::var(name).ok());
+            let repo_path = match rule.is_dir {
+                true => match source.strip_prefix(&system) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => rule.repo.join(relative),
+                    _ => continue,
+                },
+                false if source == system => rule.repo.clone(),
+                false => continue,
+            };
+
+            let length = system.components().count();
+            if best.as_ref().is_none_or(|(best, _)| length > *best) {
+                best = Some((length, repo_path));
+            }
+        }
+
+        match best {
+            Some((_, repo_path)) => Ok(repo_path),
+            None => bail!(
+                "'{}' is outside of your home directory ({}) and no mapping covers it.",
+                source.display(),
+                home.display()
+            ),
+        }
+    }
+
+    /// Maps a file inside the repository back to its location on the system.
+    ///
+    /// Returns `None` for files no rule covers, such as the `.gitignore`.
+    ///
+    pub fn source_for(&self, repo_path: &Path, home: &Path) -> Option<PathBuf> {
+        let (rule, relative) = self.rule_for(repo_path)?;
+        let system = expand(&rule.system, home, &|name| env::var(name).ok());
+
+        Some(match rule.is_dir {
+            true => system.join(relative),
+            false => system,
+        })
+    }
+
+    /// Returns whether a rule covers `repo_path`.
+    pub fn covers(&self, repo_path: &Path) -> bool {
+        self.rule_for(repo_path).is_some()
+    }
+
+    /// Returns the rule with the longest repository path matching `repo_path`.
+    fn rule_for<'a>(&self, repo_path: &'a Path) -> Option<(&Mapping, &'a Path)> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match rule.is_dir {
+                true => match repo_path.strip_prefix(&rule.repo) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => Some((rule, relative)),
+                    _ => None,
+                },
+                false if repo_path == rule.repo => Some((rule, Path::new(""))),
+                false => None,
+            })
+            .rev()
+            .max_by_key(|(rule, _)| rule.repo.components().count())
+    }
+}
+
+impl Mapping {
+    fn new(repo: &str, system: &str) -> Result<Self> {
+        let is_dir = repo.ends_with('/');
+        if is_dir != system.ends_with('/') {
+            bail!("Either both sides or neither must end in '/'.");
+        }
+
+        let repo = PathBuf::from(repo);
+        let is_normal = repo
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_normal || repo.as_os_str().is_empty() || repo.starts_with(".git") {
+            bail!("The repository path must be relative and stay inside the repository.");
+        }
+
+        if let Some((name, _)) = split_variable(system) {
+            if !VARIABLES.iter().any(|(known, _)| *known == name) {
+                bail!("Unknown variable '{}'.", name);
+            }
+        }
+        if !expand(system, Path::new("/"), &|_| None).is_absolute() {
+            bail!("The system path must start with '~', '/' or a variable.");
+        }
+
+        Ok(Self {
+            repo,
+            system: system.to_string(),
+            is_dir,
+        })
+    }
+}
+
+/// Splits a leading `~`, `$NAME` or `${NAME}` off `path`, returning the variable name and the rest.
+fn split_variable(path: &str) -> Option<(&str, &str)> {
+    if path == "~" || path.starts_with("~/") {
+        return Some(("HOME", &path[1..]));
+    }
+
+    if let Some(rest) = path.strip_prefix("${") {
+        return rest.split_once('}');
+    }
+
+    let rest = path.strip_prefix('$')?;
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    Some(rest.split_at(end))
+}
+
+/// Expands `~`, `$HOME` or an XDG variable at the start of `path`.
+///
+/// An XDG variable that is unset or not absolute falls back to its default
+/// under `home`, as the XDG base directory specification requires.
+fn expand(path: &str, home: &Path, env: &dyn Fn(&str) -> Option<String>) -> PathBuf {
+    let Some((name, rest)) = split_variable(path) else {
+        return PathBuf::from(path);
+    };
+
+    let value = match VARIABLES.iter().find(|(known, _)| *known == name) {
+        Some(("HOME", _)) => home.to_path_buf(),
+        Some((_, default)) => xdg_dir(name, default, home, env),
+        None => return PathBuf::from(path),
+    };
+
+    value.join(rest.trim_start_matches('/'))
+}
+
+/// Reads the rules committed on `branch`, using only the built-in rules when
+/// there is no `mappings.toml` or nothing has been committed yet.
+///
+/// # Errors
+/// This function will return an error if the branch cannot be read or the rules are invalid.
+///
+pub fn committed(repo: &Path, branch: &str, git: &dyn Git) -> Result<Mappings> {
+    let contents = match git.show_file(repo, branch, Path::new(MAPPINGS_FILE)) {
+        Ok(contents) => contents.unwrap_or_default(),
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            Vec::new()
+        }
+        Err(error) => return Err(error),
+    };
+
+    Mappings::parse(&String::from_utf8(contents)?)
+        .with_context(|| format!("Unable to read {} on branch '{}'.", MAPPINGS_FILE, branch))
+}
+
+/// Reads the rules from `mappings.toml` in the repository's working tree.
+///
+/// # Errors
+/// This function will return an error if the file cannot be read or the rules are invalid.
+///
+pub async fn load(fs: &impl FileSystem, repo: &Path) -> Result<Mappings> {
+    let path = repo.join(MAPPINGS_FILE);
+    if !fs.exists(&path) {
+        return Ok(Mappings::default());
+    }
+
+    Mappings::parse(&fs.read_to_string(&path).await?)
+        .with_context(|| format!("Unable to read {}.", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        PathBuf::from("/home/dotty")
+    }
+
+    #[test]
+    fn test_expand() {
+        let env = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some("/etc/xdg-home".to_string()),
+            "XDG_DATA_HOME" => Some("relative/share".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(expand("~/.zshrc", &home(), &env), home().join(".zshrc"));
+        assert_eq!(expand("$HOME/.zshrc", &home(), &env), home().join(".zshrc"));
+        assert_eq!(
+            expand("${XDG_CONFIG_HOME}/alacritty/", &home(), &env),
+            PathBuf::from("/etc/xdg-home/alacritty")
+        );
+        // Relative and unset XDG variables use their defaults.
+        assert_eq!(
+            expand("$XDG_DATA_HOME/fonts", &home(), &env),
+            home().join(".local/share/fonts")
+        );
+        assert_eq!(
+            expand("${XDG_CACHE_HOME}", &home(), &env),
+            home().join(".cache")
+        );
+        assert_eq!(
+            expand("/etc/hosts", &home(), &env),
+            PathBuf::from("/etc/hosts")
+        );
+    }
+
+    #[test]
+    fn test_repo_path() {
+        let mappings = Mappings::default();
+
+        assert_eq!(
+            mappings
+                .repo_path(Path::new("/home/dotty/.zshrc"), &home())
+                .unwrap(),
+            PathBuf::from("home/.zshrc")
+        );
+        assert!(mappings
+            .repo_path(Path::new("/etc/hosts"), &home())
+            .is_err());
+        assert!(mappings.repo_path(&home(), &home()).is_err());
+    }
+
+    #[test]
+    fn test_source_for() {
+        let mappings = Mappings::default();
+
+        assert_eq!(
+            mappings.source_for(Path::new("home/.config/nvim/init.lua"), &home()),
+            Some(PathBuf::from("/home/dotty/.config/nvim/init.lua"))
+        );
+        assert_eq!(mappings.source_for(Path::new(".gitignore"), &home()), None);
+        assert_eq!(mappings.source_for(Path::new("home"), &home()), None);
+        assert!(mappings.covers(Path::new("alacritty/alacritty.toml")));
+        assert!(!mappings.covers(Path::new(MAPPINGS_FILE)));
+    }
+
+    #[test]
+    fn test_user_mappings() {
+        let mappings = Mappings::parse(
+            r#"
+            "zsh/zshrc" = "~/.zshrc"
+            "zsh/" = "$HOME/.zsh/"
+            "fonts/" = "/usr/local/share/fonts/"
+            "nvim/" = "~/.vim/"
+            "#,
+        )
+        .unwrap();
+
+        for (source, repo_path) in [
+            ("/home/dotty/.zshrc", "zsh/zshrc"),
+            ("/home/dotty/.zsh/aliases.zsh", "zsh/aliases.zsh"),
+            ("/home/dotty/.vim/init.vim", "nvim/init.vim"),
+            ("/usr/local/share/fonts/Iosevka.ttf", "fonts/Iosevka.ttf"),
+            ("/home/dotty/.bashrc", "home/.bashrc"),
+        ] {
+            let source = Path::new(source);
+            assert_eq!(
+                mappings.repo_path(source, &home()).unwrap(),
+                PathBuf::from(repo_path)
+            );
+            assert_eq!(
+                mappings.source_for(Path::new(repo_path), &home()),
+                Some(source.to_path_buf())
+            );
+        }
+
+        // The built-in rule for `nvim/` was replaced.
+        assert_eq!(
+            mappings
+                .repo_path(&home().join(".config/nvim/init.lua"), &home())
+                .unwrap(),
+            PathBuf::from("home/.config/nvim/init.lua")
+        );
+    }
+
+    #[test]
+    fn test_invalid_mappings() {
+        for contents in [
+            r#""zsh/" = "~/.zshrc""#,
+            r#""../zshrc" = "~/.zshrc""#,
+            r#""/zshrc" = "~/.zshrc""#,
+            r#""zshrc" = "$ZDOTDIR/.zshrc""#,
+            r#""zshrc" = ".zshrc""#,
+            r#""zshrc" = 1"#,
+        ] {
+            assert!(Mappings::parse(contents).is_err(), "{}", contents);
+        }
+    }
+}
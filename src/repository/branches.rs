@@ -0,0 +1,282 @@
+// Standard library imports
+use std::path::Path;
+
+// External crate imports
+use anyhow::{bail, Result};
+use chrono::Utc;
+
+// Local module imports
+use crate::{
+    clients::git::{Git, GitError},
+    config::TomlConfig,
+};
+
+/// A completed branch operation, kept so it can be undone.
+#[derive(Debug)]
+enum Step {
+    Created(String),
+    Renamed { from: String, to: String },
+    Archived { branch: String, tag: String },
+}
+
+/// Brings the repository's branches in line with a change to the profiles.
+///
+/// Comparing `previous` with `next`:
+/// - A new profile gets its branch, created from the current `HEAD`.
+/// - A profile whose branch name changed has its branch renamed.
+/// - A removed profile has its branch tagged as `archive/<profile>-<date>` and
+///   then deleted, so it can still be restored from the tag.
+///
+/// Nothing is done when `base_path` does not contain a repository yet. If a step
+/// fails, the steps already taken are undone so the repository matches
+/// `previous` again.
+///
+/// # Arguments
+/// * `previous` - The configuration as it is saved in `config.toml`.
+/// * `next` - The configuration about to be saved.
+/// * `git` - An implementation of `Git` for changing the branches.
+///
+/// # Errors
+/// This function will return an error if:
+/// - A removed profile's branch is checked out.
+/// - Any Git operation fails.
+///
+pub fn update_branches(previous: &TomlConfig, next: &TomlConfig, git: &dyn Git) -> Result<()> {
+    let repo = &next.base_path;
+    if !git.is_repository(repo) {
+        return Ok(());
+    }
+
+    let mut steps = Vec::new();
+    let result = apply(previous, next, git, repo, &mut steps);
+
+    if result.is_err() {
+        for step in steps.iter().rev() {
+            if let Err(error) = undo(git, repo, step) {
+                log::warn!("Could not undo {:?}: {}", step, error);
+            }
+        }
+    }
+
+    result
+}
+
+fn apply(
+    previous: &TomlConfig,
+    next: &TomlConfig,
+    git: &dyn Git,
+    repo: &Path,
+    steps: &mut Vec<Step>,
+) -> Result<()> {
+    let branches = git.list_branches(repo)?;
+    let date = Utc::now().format("%Y-%m-%d");
+
+    // Archive first, so a new or renamed profile can reuse a freed branch name.
+    for (id, profile) in &previous.profiles {
+        if next.profiles.contains_key(id) || !branches.contains(&profile.branch) {
+            continue;
+        }
+
+        let branch = &profile.branch;
+        if git.current_branch(repo)?.as_deref() == Some(branch.as_str()) {
+            bail!(
+                "Branch '{}' is checked out. Switch to another profile before deleting '{}'.",
+                branch,
+                id
+            );
+        }
+
+        let tag = archive_tag(git, repo, &format!("archive/{}-{}", id, date), branch)?;
+        steps.push(Step::Archived {
+            branch: branch.clone(),
+            tag: tag.clone(),
+        });
+        git.delete_branch(repo, branch)?;
+        log::info!("Archived branch '{}' as '{}'", branch, tag);
+    }
+
+    for (id, profile) in &next.profiles {
+        let branch = &profile.branch;
+
+        match previous.profiles.get(id) {
+            Some(old) if old.branch == *branch => {}
+            Some(old) if branches.contains(&old.branch) => {
+                git.rename_branch(repo, &old.branch, branch)?;
+                steps.push(Step::Renamed {
+                    from: old.branch.clone(),
+                    to: branch.clone(),
+                });
+                log::info!("Renamed branch '{}' to '{}'", old.branch, branch);
+            }
+            _ if branches.contains(branch) => {}
+            _ => match git.create_branch(repo, branch, "HEAD") {
+                Ok(()) => {
+                    steps.push(Step::Created(branch.clone()));
+                    log::info!("Created branch '{}'", branch);
+                }
+                // Nothing has been committed yet, so there is nothing to branch from.
+                Err(error)
+                    if error.downcast_ref() == Some(&GitError::BranchNotFound("HEAD".into())) =>
+                {
+                    log::info!("Not creating '{}' before the first commit", branch);
+                }
+                Err(error) => return Err(error),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Tags `branch` as `name`, adding a counter to the name if it is already taken.
+fn archive_tag(git: &dyn Git, repo: &Path, name: &str, branch: &str) -> Result<String> {
+    let mut tag = name.to_string();
+
+    for attempt in 2.. {
+        match git.create_tag(repo, &tag, branch) {
+            Err(error) if matches!(error.downcast_ref(), Some(GitError::TagExists(_))) => {
+                tag = format!("{}-{}", name, attempt);
+            }
+            result => return result.map(|()| tag),
+        }
+    }
+
+    unreachable!()
+}
+
+fn undo(git: &dyn Git, repo: &Path, step: &Step) -> Result<()> {
+    match step {
+        Step::Created(branch) => git.delete_branch(repo, branch),
+        Step::Renamed { from, to } => git.rename_branch(repo, to, from),
+        Step::Archived { branch, tag } => {
+            if !git.list_branches(repo)?.contains(branch) {
+                git.create_branch(repo, branch, tag)?;
+            }
+            git.delete_tag(repo, tag)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::ProfileConfig,
+        repository::init,
+    };
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn profile(branch: &str) -> ProfileConfig {
+        ProfileConfig {
+            branch: branch.to_string(),
+            ..ProfileConfig::default()
+        }
+    }
+
+    async fn repository(base_path: &Path) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path: base_path.to_path_buf(),
+            active_profile: "default".to_string(),
+            ..TomlConfig::default()
+        };
+        config
+            .profiles
+            .insert("default".to_string(), profile("main"));
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+        config
+    }
+
+    fn tags(repo: &Path) -> Vec<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .arg("tag")
+            .output()
+            .unwrap()
+            .stdout;
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_update_branches() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        let config = repository(repo).await;
+
+        // Creating a profile creates its branch.
+        let mut created = config.clone();
+        created
+            .profiles
+            .insert("nord-theme".to_string(), profile("nord"));
+        update_branches(&config, &created, &GitClient).unwrap();
+        assert_eq!(GitClient.list_branches(repo).unwrap(), vec!["main", "nord"]);
+
+        // Changing the branch name renames it.
+        let mut renamed = created.clone();
+        renamed.profiles.get_mut("nord-theme").unwrap().branch = "nord-dark".to_string();
+        update_branches(&created, &renamed, &GitClient).unwrap();
+        assert_eq!(
+            GitClient.list_branches(repo).unwrap(),
+            vec!["main", "nord-dark"]
+        );
+
+        // Deleting the profile archives the branch, twice on the same day.
+        let mut deleted = renamed.clone();
+        deleted.profiles.remove("nord-theme");
+        update_branches(&renamed, &deleted, &GitClient).unwrap();
+        update_branches(&deleted, &renamed, &GitClient).unwrap();
+        update_branches(&renamed, &deleted, &GitClient).unwrap();
+
+        let tag = format!("archive/nord-theme-{}", Utc::now().format("%Y-%m-%d"));
+        assert_eq!(GitClient.list_branches(repo).unwrap(), vec!["main"]);
+        assert_eq!(tags(repo), vec![tag.clone(), format!("{}-2", tag)]);
+    }
+
+    #[tokio::test]
+    async fn test_update_branches_rolls_back() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path();
+        let mut config = repository(repo).await;
+        for branch in ["gruvbox", "nord"] {
+            config.profiles.insert(branch.to_string(), profile(branch));
+            GitClient.create_branch(repo, branch, "HEAD").unwrap();
+        }
+
+        // The archive succeeds, but the rename onto an existing branch fails.
+        let mut next = config.clone();
+        next.profiles.remove("nord");
+        next.profiles.get_mut("gruvbox").unwrap().branch = "main".to_string();
+
+        assert!(update_branches(&config, &next, &GitClient).is_err());
+        assert_eq!(
+            GitClient.list_branches(repo).unwrap(),
+            vec!["gruvbox", "main", "nord"]
+        );
+        assert!(tags(repo).is_empty());
+
+        // The checked out branch cannot be archived.
+        let mut next = config.clone();
+        next.profiles.remove("default");
+        assert!(update_branches(&config, &next, &GitClient).is_err());
+    }
+
+    #[test]
+    fn test_update_branches_without_repository() {
+        let temp_dir = tempdir().unwrap();
+        let previous = TomlConfig {
+            base_path: temp_dir.path().to_path_buf(),
+            ..TomlConfig::default()
+        };
+        let mut next = previous.clone();
+        next.profiles
+            .insert("nord-theme".to_string(), profile("nord"));
+
+        assert!(update_branches(&previous, &next, &GitClient).is_ok());
+    }
+}
@@ -0,0 +1,194 @@
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// Local module imports
+use crate::clients::git::ChangeKind;
+
+/// Template used when `commit_template` is not set in `config.toml`.
+pub const DEFAULT_TEMPLATE: &str = "{profile}: {summary} ({file_count})\n\nSynced from {host}.";
+
+/// How many file names are listed per kind of change before the rest are counted.
+const MAX_NAMES: usize = 3;
+
+/// Builds a commit message for `changes` from `template`.
+///
+/// The following placeholders are replaced:
+/// - `{profile}` - The ID of the profile being committed.
+/// - `{host}` - The host name of this machine.
+/// - `{summary}` - The changes grouped by kind, e.g. `update alacritty.toml, add waybar/config`.
+/// - `{files}` - The names of all changed files, separated by commas.
+/// - `{count}` - The number of changed files.
+/// - `{file_count}` - The same number followed by "file" or "files".
+/// - `{added}`, `{updated}` and `{removed}` - The number of files of each kind.
+///
+/// Any other text in braces is copied unchanged, as is anything a placeholder
+/// is replaced with.
+///
+/// # Arguments
+/// * `template` - The message template.
+/// * `profile` - The ID of the profile being committed.
+/// * `host` - The host name of this machine.
+/// * `changes` - The staged changes, as returned by `Git::staged_changes`.
+///
+/// # Returns
+/// Returns the commit message.
+///
+pub fn commit_message(
+    template: &str,
+    profile: &str,
+    host: &str,
+    changes: &[(ChangeKind, PathBuf)],
+) -> String {
+    let count_of = |kind| changes.iter().filter(|(k, _)| *k == kind).count();
+    let names_of = |kind| -> Vec<String> {
+        changes
+            .iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, path)| display_name(path))
+            .collect()
+    };
+
+    let summary: Vec<String> = [
+        ("update", ChangeKind::Modified),
+        ("add", ChangeKind::Added),
+        ("remove", ChangeKind::Removed),
+    ]
+    .into_iter()
+    .filter_map(|(verb, kind)| {
+        let names = names_of(kind);
+        match names.len() {
+            0 => None,
+            len if len > MAX_NAMES => Some(format!(
+                "{} {} and {} more",
+                verb,
+                names[..MAX_NAMES].join(", "),
+                len - MAX_NAMES
+            )),
+            _ => Some(format!("{} {}", verb, names.join(", "))),
+        }
+    })
+    .collect();
+
+    let files: Vec<String> = changes.iter().map(|(_, path)| display_name(path)).collect();
+    let file_count = file_count(changes.len());
+
+    // The template is scanned once, so substituted names are never expanded again.
+    let mut message = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let value = match &rest[1..end] {
+            "profile" => profile.to_string(),
+            "host" => host.to_string(),
+            "summary" => summary.join(", "),
+            "files" => files.join(", "),
+            "count" => changes.len().to_string(),
+            "file_count" => file_count.clone(),
+            "added" => count_of(ChangeKind::Added).to_string(),
+            "updated" => count_of(ChangeKind::Modified).to_string(),
+            "removed" => count_of(ChangeKind::Removed).to_string(),
+            // Unknown placeholders are kept as written, and the scan resumes
+            // inside them in case the brace opens a placeholder after all.
+            _ => {
+                message.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        message.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    message.push_str(rest);
+
+    message
+}
+
+/// Returns `count` followed by "file" or "files".
+pub fn file_count(count: usize) -> String {
+    match count {
+        1 => "1 file".to_string(),
+        count => format!("{} files", count),
+    }
+}
+
+/// Returns a short, recognisable name for a file.
+///
+/// Files without an extension, such as `waybar/config`, are shown with their
+/// parent directory, as the name alone says little. Dotfiles like `.zshrc` and
+/// files with an extension are shown by name only.
+fn display_name(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    match path.parent().and_then(Path::file_name) {
+        Some(parent) if path.extension().is_none() && !name.starts_with('.') => {
+            format!("{}/{}", parent.to_string_lossy(), name)
+        }
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_message() {
+        let changes = vec![
+            (
+                ChangeKind::Modified,
+                PathBuf::from("home/.config/alacritty/alacritty.toml"),
+            ),
+            (
+                ChangeKind::Added,
+                PathBuf::from("home/.config/waybar/config"),
+            ),
+        ];
+
+        assert_eq!(
+            commit_message(DEFAULT_TEMPLATE, "nord-theme", "laptop", &changes),
+            "nord-theme: update alacritty.toml, add waybar/config (2 files)\n\nSynced from laptop."
+        );
+        assert_eq!(
+            commit_message(
+                "[{host}] {profile} +{added} ~{updated} -{removed}: {files}",
+                "nord-theme",
+                "laptop",
+                &changes[..1]
+            ),
+            "[laptop] nord-theme +0 ~1 -0: alacritty.toml"
+        );
+    }
+
+    #[test]
+    fn test_commit_message_braces_in_names() {
+        let changes = vec![(ChangeKind::Added, PathBuf::from("home/.{count}"))];
+
+        assert_eq!(
+            commit_message(
+                "{profile} on {host}: {files} {unknown} {",
+                "{files}",
+                "{host}",
+                &changes
+            ),
+            "{files} on {host}: .{count} {unknown} {"
+        );
+    }
+
+    #[test]
+    fn test_commit_message_truncates_long_lists() {
+        let changes: Vec<_> = [".bashrc", ".gitconfig", ".vimrc", ".zshrc", ".zprofile"]
+            .iter()
+            .map(|name| (ChangeKind::Removed, Path::new("home").join(name)))
+            .collect();
+
+        assert_eq!(
+            commit_message("{summary} ({file_count})", "work", "desktop", &changes),
+            "remove .bashrc, .gitconfig, .vimrc and 2 more (5 files)"
+        );
+    }
+}
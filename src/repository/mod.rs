@@ -0,0 +1,307 @@
+// Standard library imports
+use std::path::{Path, PathBuf};
+
+// External crate imports
+use anyhow::{bail, Result};
+use chrono::Utc;
+
+// Local module imports
+use crate::{
+    clients::{
+        file_system::FileSystem,
+        git::{Git, DEFAULT_REMOTE},
+    },
+    config::{ProfileConfig, ProfileId, TomlConfig},
+    manifest::{committed_modes, TrackedFile},
+    mappings,
+};
+
+// Submodules
+pub mod branches;
+pub mod message;
+pub mod sync;
+
+/// Starter `.gitignore` for the dotfiles repository.
+///
+/// Dotty keeps its own configuration and asset store next to the repository
+/// contents, as did the log and backups of older versions, but they must never
+/// be committed.
+pub const GITIGNORE: &str = "\
+# Machine-specific files written by Dotty
+dotty.log
+config.toml
+backups/
+assets/
+";
+
+/// Creates the dotfiles repository inside `base_path`.
+///
+/// A new repository is initialised on the active profile's branch, given a
+/// starter `.gitignore` and committed. An existing repository is detected and
+/// reused as it is.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the base path and active profile.
+/// * `fs` - An implementation of `FileSystem` for writing the `.gitignore`.
+/// * `git` - An implementation of `Git` for creating the repository.
+///
+/// # Returns
+/// Returns `true` if a new repository was created, or `false` if one already existed.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile.
+/// - The repository cannot be created or committed to.
+/// - The `.gitignore` cannot be written.
+///
+pub async fn init(config: &TomlConfig, fs: &impl FileSystem, git: &dyn Git) -> Result<bool> {
+    let path = &config.base_path;
+
+    if git.is_repository(path) {
+        log::info!("Reusing the existing repository in {}", path.display());
+        return Ok(false);
+    }
+
+    let branch = &config.active_profile()?.branch;
+    git.init(path, branch)?;
+
+    let gitignore = path.join(".gitignore");
+    if !fs.exists(&gitignore) {
+        fs.write(&gitignore, GITIGNORE).await?;
+    }
+
+    git.add(path, &[PathBuf::from(".gitignore")])?;
+    git.commit(path, "Initialise Dotty repository")?;
+
+    log::info!("Created a repository in {} on '{}'", path.display(), branch);
+    Ok(true)
+}
+
+/// Clones `url` into `base_path` and adds a profile for every branch it has.
+///
+/// Each new profile is named after its branch, and its manifest is rebuilt from
+/// the files under `home/` on that branch and the modes in `modes.toml`.
+/// Branches already used by a profile are skipped. No branch is checked out.
+///
+/// # Arguments
+/// * `config` - The configuration to add the profiles to.
+/// * `url` - The remote to clone. Local paths and `file://` URLs are supported.
+/// * `git` - An implementation of `Git` for cloning the repository.
+/// * `home` - The user's home directory, used to locate the tracked files.
+///
+/// # Returns
+/// Returns the IDs of the profiles that were added.
+///
+/// # Errors
+/// This function will return an error if:
+/// - `base_path` already contains a repository.
+/// - The remote cannot be cloned.
+///
+pub fn clone(
+    config: &mut TomlConfig,
+    url: &str,
+    git: &dyn Git,
+    home: &Path,
+) -> Result<Vec<ProfileId>> {
+    let path = &config.base_path;
+
+    if git.is_repository(path) {
+        bail!(
+            "'{}' already contains a repository. Remove it or choose a different base path.",
+            path.display()
+        );
+    }
+
+    git.clone_repository(url, path)?;
+
+    let mut added = Vec::new();
+    for branch in git.list_remote_branches(path, DEFAULT_REMOTE)? {
+        if config
+            .profiles
+            .values()
+            .any(|profile| profile.branch == branch)
+        {
+            log::info!("Branch '{}' already belongs to a profile", branch);
+            continue;
+        }
+
+        if config.profiles.contains_key(&branch) {
+            log::warn!(
+                "Skipping branch '{}': a profile with this ID exists",
+                branch
+            );
+            continue;
+        }
+
+        let remote_branch = format!("{}/{}", DEFAULT_REMOTE, branch);
+        let mappings = mappings::committed(path, &remote_branch, git)?;
+        let modes = committed_modes(path, &remote_branch, git)?;
+        let files = git
+            .list_files(path, &remote_branch)?
+            .into_iter()
+            .filter_map(|repo_path| {
+                mappings
+                    .source_for(&repo_path, home)
+                    .map(|source| TrackedFile {
+                        source,
+                        added: Utc::now(),
+                        strategy: None,
+                        mode: modes.get(&repo_path).copied(),
+                        repo_path,
+                    })
+            })
+            .collect();
+
+        config.profiles.insert(
+            branch.clone(),
+            ProfileConfig {
+                branch: branch.clone(),
+                files,
+                ..ProfileConfig::default()
+            },
+        );
+        added.push(branch);
+    }
+
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::ProfileConfig,
+        manifest::MODES_FILE,
+    };
+    use tempfile::tempdir;
+
+    fn config(base_path: PathBuf) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path,
+            active_profile: "nord-theme".to_string(),
+            ..TomlConfig::default()
+        };
+        config.profiles.insert(
+            "nord-theme".to_string(),
+            ProfileConfig {
+                branch: "nord".to_string(),
+                ..ProfileConfig::default()
+            },
+        );
+        config
+    }
+
+    #[tokio::test]
+    async fn test_init_creates_repository() {
+        let temp_dir = tempdir().unwrap();
+        let config = config(temp_dir.path().join("dotty"));
+
+        assert!(init(&config, &FileSystemClient, &GitClient).await.unwrap());
+
+        assert!(GitClient.is_repository(&config.base_path));
+        assert_eq!(
+            GitClient.current_branch(&config.base_path).unwrap(),
+            Some("nord".to_string())
+        );
+        assert_eq!(
+            std::fs::read_to_string(config.base_path.join(".gitignore")).unwrap(),
+            GITIGNORE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_init_reuses_repository() {
+        let temp_dir = tempdir().unwrap();
+        let config = config(temp_dir.path().to_path_buf());
+
+        GitClient.init(&config.base_path, "main").unwrap();
+        std::fs::write(config.base_path.join(".gitignore"), "*.swp\n").unwrap();
+
+        assert!(!init(&config, &FileSystemClient, &GitClient).await.unwrap());
+
+        assert_eq!(
+            GitClient.current_branch(&config.base_path).unwrap(),
+            Some("main".to_string())
+        );
+        assert_eq!(
+            std::fs::read_to_string(config.base_path.join(".gitignore")).unwrap(),
+            "*.swp\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clone_adds_profiles() {
+        let temp_dir = tempdir().unwrap();
+        let remote = temp_dir.path().join("remote");
+        let home = PathBuf::from("/home/dotty");
+
+        // Publish two profiles from another machine.
+        let other = config(temp_dir.path().join("other"));
+        init(&other, &FileSystemClient, &GitClient).await.unwrap();
+        std::fs::create_dir_all(other.base_path.join("home")).unwrap();
+        std::fs::write(other.base_path.join("home/.zshrc"), "export EDITOR=nvim").unwrap();
+        GitClient
+            .add(&other.base_path, &[PathBuf::from("home")])
+            .unwrap();
+        GitClient.commit(&other.base_path, "Track zshrc").unwrap();
+        GitClient
+            .create_branch(&other.base_path, "gruvbox", "HEAD")
+            .unwrap();
+        std::fs::write(
+            other.base_path.join(MODES_FILE),
+            "\"home/.zshrc\" = \"600\"",
+        )
+        .unwrap();
+        GitClient
+            .add(&other.base_path, &[PathBuf::from(MODES_FILE)])
+            .unwrap();
+        GitClient.commit(&other.base_path, "Record modes").unwrap();
+
+        GitClient.init(&remote, "main").unwrap();
+        std::process::Command::new("git")
+            .arg("-C")
+            .arg(&other.base_path)
+            .args(["remote", "add", "origin"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        GitClient.push(&other.base_path, "origin", "nord").unwrap();
+        GitClient
+            .push(&other.base_path, "origin", "gruvbox")
+            .unwrap();
+
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            ..TomlConfig::default()
+        };
+        let url = format!("file://{}", remote.display());
+        let added = clone(&mut config, &url, &GitClient, &home).unwrap();
+
+        assert_eq!(added, vec!["gruvbox", "nord"]);
+        assert_eq!(config.profiles["nord"].branch, "nord");
+        let sources: Vec<_> = config.profiles["gruvbox"]
+            .files
+            .iter()
+            .map(|file| file.source.clone())
+            .collect();
+        assert_eq!(sources, vec![home.join(".zshrc")]);
+        assert_eq!(config.profiles["nord"].files[0].mode, Some(0o600));
+        assert_eq!(config.profiles["gruvbox"].files[0].mode, None);
+
+        // Cloning into an existing repository is refused.
+        assert!(clone(&mut config, &url, &GitClient, &home).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_init_without_active_profile() {
+        let temp_dir = tempdir().unwrap();
+        let config = TomlConfig {
+            base_path: temp_dir.path().to_path_buf(),
+            ..TomlConfig::default()
+        };
+
+        assert!(init(&config, &FileSystemClient, &GitClient).await.is_err());
+        assert!(!GitClient.is_repository(&config.base_path));
+    }
+}
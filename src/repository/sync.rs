@@ -0,0 +1,542 @@
+// Standard library imports
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+// External crate imports
+use anyhow::{bail, Result};
+
+// Local module imports
+use crate::{
+    assets::stage_file,
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError, DEFAULT_REMOTE},
+    },
+    config::{ProfileConfig, PushPolicy, TomlConfig},
+    externals::EXTERNALS_FILE,
+    manifest::MODES_FILE,
+    mappings::{self, MAPPINGS_FILE},
+    repository::message::{commit_message, DEFAULT_TEMPLATE},
+};
+
+/// Dotty's own files, which are committed along with the tracked files.
+const DOTTY_FILES: [&str; 3] = [EXTERNALS_FILE, MAPPINGS_FILE, MODES_FILE];
+
+/// What a `sync` did.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    /// The ID of the commit created for local changes, if there were any.
+    pub commit: Option<String>,
+    /// The remotes the profile's branch was pulled from.
+    pub pulled: Vec<String>,
+    /// The remotes the profile's branch was pushed to.
+    pub pushed: Vec<String>,
+}
+
+/// Commits the active profile's files and exchanges them with the remote.
+///
+/// The tracked files are copied into the repository, with their modes in
+/// `modes.toml`, and committed on the profile's branch, which is checked out
+/// first if needed. Files that are no longer tracked are removed from the
+/// repository. The branch is then pulled from each of the profile's remotes,
+/// merging their changes, and pushed to those whose push policy is `always`.
+/// A profile without remotes uses `origin`, and without any remote the changes
+/// are only committed.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the base path and active profile.
+/// * `fs` - An implementation of `FileSystem` for copying the files.
+/// * `git` - An implementation of `Git` for interacting with the repository.
+///
+/// # Returns
+/// Returns a `SyncReport` describing what was done.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile.
+/// - A file cannot be copied into the repository.
+/// - The remote has conflicting changes. The merge is aborted, leaving the local
+///   commit in place, and a `GitError::MergeConflict` is returned.
+/// - Any other Git operation fails.
+///
+pub async fn sync(config: &TomlConfig, fs: &impl FileSystem, git: &dyn Git) -> Result<SyncReport> {
+    let repo = &config.base_path;
+    let profile = config.active_profile()?;
+
+    checkout_branch(git, repo, &profile.branch)?;
+    configure_remotes(profile, repo, git)?;
+
+    let paths = stage(config, profile, fs, git).await?;
+    git.add(repo, &paths)?;
+
+    let mut report = SyncReport::default();
+    let changes = git.staged_changes(repo)?;
+    if !changes.is_empty() {
+        let template = config
+            .commit_template
+            .as_deref()
+            .unwrap_or(DEFAULT_TEMPLATE);
+        let host = gethostname::gethostname().to_string_lossy().to_string();
+        // The message describes the user's files, unless only Dotty's own changed.
+        let files: Vec<_> = changes
+            .iter()
+            .filter(|(_, path)| !DOTTY_FILES.iter().any(|file| path == Path::new(file)))
+            .cloned()
+            .collect();
+        let files = if files.is_empty() { &changes } else { &files };
+        let message = commit_message(template, &config.active_profile, &host, files);
+
+        report.commit = Some(git.commit(repo, &message)?);
+    }
+
+    // Pull everything first, so every push includes all the remote changes.
+    for (remote, _) in remotes(profile) {
+        match git.pull(repo, &remote, &profile.branch) {
+            Ok(()) => {}
+            Err(error) => match error.downcast_ref() {
+                Some(GitError::RemoteNotFound(_)) => {
+                    log::info!("Remote '{}' is not configured, skipping it", remote);
+                    continue;
+                }
+                // The branch has not been pushed yet.
+                Some(GitError::BranchNotFound(_)) => {}
+                _ => return Err(error),
+            },
+        }
+        report.pulled.push(remote);
+    }
+
+    for (remote, policy) in remotes(profile) {
+        if policy == PushPolicy::Always && report.pulled.contains(&remote) {
+            git.push(repo, &remote, &profile.branch)?;
+            report.pushed.push(remote);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Pushes the active profile's branch without committing or pulling first.
+///
+/// Every remote whose push policy is `always` or `manual` is pushed to, or only
+/// `remote` when it is given.
+///
+/// # Arguments
+/// * `config` - The current configuration, providing the base path and active profile.
+/// * `git` - An implementation of `Git` for pushing the branch.
+/// * `remote` - The only remote to push to, if any.
+///
+/// # Returns
+/// Returns the names of the remotes that were pushed to.
+///
+/// # Errors
+/// This function will return an error if:
+/// - There is no active profile.
+/// - `remote` is not one of the profile's remotes, or its push policy is `never`.
+/// - Any push fails.
+///
+pub fn push(config: &TomlConfig, git: &dyn Git, remote: Option<&str>) -> Result<Vec<String>> {
+    let repo = &config.base_path;
+    let profile = config.active_profile()?;
+    let remotes = remotes(profile);
+
+    let targets: Vec<_> = match remote {
+        Some(name) => match remotes.iter().find(|(remote, _)| remote == name) {
+            None => bail!(
+                "Profile '{}' has no remote named '{}'.",
+                config.active_profile,
+                name
+            ),
+            Some((_, PushPolicy::Never)) => {
+                bail!("Remote '{}' is set to never be pushed to.", name)
+            }
+            Some((remote, _)) => vec![remote.clone()],
+        },
+        None => remotes
+            .into_iter()
+            .filter(|(_, policy)| *policy != PushPolicy::Never)
+            .map(|(remote, _)| remote)
+            .collect(),
+    };
+
+    configure_remotes(profile, repo, git)?;
+    for remote in &targets {
+        git.push(repo, remote, &profile.branch)?;
+    }
+
+    Ok(targets)
+}
+
+/// Returns the profile's remotes with their push policy, or `origin` when it has none.
+pub(crate) fn remotes(profile: &ProfileConfig) -> Vec<(String, PushPolicy)> {
+    match profile.remotes.is_empty() {
+        true => vec![(DEFAULT_REMOTE.to_string(), PushPolicy::Always)],
+        false => profile
+            .remotes
+            .iter()
+            .map(|remote| (remote.name.clone(), remote.push))
+            .collect(),
+    }
+}
+
+/// Points the repository's remotes at the URLs configured for the profile.
+fn configure_remotes(profile: &ProfileConfig, repo: &Path, git: &dyn Git) -> Result<()> {
+    for remote in &profile.remotes {
+        git.set_remote(repo, &remote.name, &remote.url)?;
+    }
+
+    Ok(())
+}
+
+/// Checks out `branch`, creating it from the current `HEAD` when it does not exist.
+pub(crate) fn checkout_branch(git: &dyn Git, repo: &Path, branch: &str) -> Result<()> {
+    if git.current_branch(repo)?.as_deref() == Some(branch) {
+        return Ok(());
+    }
+
+    match git.checkout(repo, branch) {
+        Err(error) if error.downcast_ref() == Some(&GitError::BranchNotFound(branch.into())) => {
+            git.create_branch(repo, branch, "HEAD")?;
+            git.checkout(repo, branch)
+        }
+        result => result,
+    }
+}
+
+/// Copies the profile's files into the repository and removes untracked ones.
+///
+/// Files that are missing on disk are left alone, as they may simply not have
+/// been deployed on this machine yet. Files over the size limit are moved to
+/// the asset store. Edits to `externals.toml`, `mappings.toml` and `modes.toml`
+/// are staged as well, and `modes.toml` is removed when no file has a mode.
+///
+/// Returns the repository paths that need to be staged.
+async fn stage(
+    config: &TomlConfig,
+    profile: &ProfileConfig,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+) -> Result<Vec<PathBuf>> {
+    let repo = &config.base_path;
+    let mut paths = Vec::new();
+
+    for file in &profile.files {
+        if !fs.exists(&file.source) {
+            log::warn!("Skipping missing file: {}", file.source.display());
+            continue;
+        }
+
+        stage_file(config, fs, &file.source, &repo.join(&file.repo_path)).await?;
+        paths.push(file.repo_path.clone());
+    }
+
+    let modes_path = repo.join(MODES_FILE);
+    match profile.modes_file()? {
+        Some(modes) => fs.write(&modes_path, &modes).await?,
+        // Staging the removal keeps old modes from coming back on a clone.
+        None if fs.exists(&modes_path) => {
+            fs.remove_file(&modes_path).await?;
+            paths.push(PathBuf::from(MODES_FILE));
+        }
+        None => {}
+    }
+
+    for file in DOTTY_FILES {
+        if fs.exists(&repo.join(file)) {
+            paths.push(PathBuf::from(file));
+        }
+    }
+
+    let tracked: HashSet<_> = profile.files.iter().map(|file| &file.repo_path).collect();
+    let committed = match git.list_files(repo, &profile.branch) {
+        Ok(files) => files,
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            Vec::new()
+        }
+        Err(error) => return Err(error),
+    };
+
+    // Only files a mapping covers were placed by Dotty; anything else was added by hand.
+    let mappings = mappings::load(fs, repo).await?;
+    for path in committed {
+        if mappings.covers(&path) && !tracked.contains(&path) {
+            let file = repo.join(&path);
+            if fs.exists(&file) {
+                fs.remove_file(&file).await?;
+            }
+            paths.push(path);
+        }
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mappings::Mappings;
+    use crate::{
+        assets::Pointer,
+        clients::{file_system::FileSystemClient, git::GitClient},
+        config::RemoteConfig,
+        repository::init,
+        status::{status, FileStatus},
+    };
+    use std::{fs, process::Command};
+    use tempfile::tempdir;
+
+    /// Creates a machine with its own home and a repository cloned from `remote`.
+    async fn machine(root: &Path, name: &str, remote: &Path) -> TomlConfig {
+        let mut config = TomlConfig {
+            base_path: root.join(name).join("dotty"),
+            active_profile: "nord-theme".to_string(),
+            ..TomlConfig::default()
+        };
+        config.profiles.insert(
+            "nord-theme".to_string(),
+            ProfileConfig {
+                branch: "nord".to_string(),
+                ..ProfileConfig::default()
+            },
+        );
+
+        GitClient
+            .clone_repository(remote.to_str().unwrap(), &config.base_path)
+            .unwrap();
+        if GitClient.checkout(&config.base_path, "nord").is_err() {
+            fs::remove_dir_all(&config.base_path).unwrap();
+            init(&config, &FileSystemClient, &GitClient).await.unwrap();
+            Command::new("git")
+                .arg("-C")
+                .arg(&config.base_path)
+                .args(["remote", "add", "origin"])
+                .arg(remote)
+                .status()
+                .unwrap();
+        }
+
+        config
+    }
+
+    async fn track(config: &mut TomlConfig, home: &Path, name: &str, contents: &str) {
+        let source = home.join(name);
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(&source, contents).unwrap();
+
+        let profile = config.active_profile_mut().unwrap();
+        if !profile.is_tracked(&source) {
+            profile
+                .track(&FileSystemClient, &source, home, &Mappings::default())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn bare_remote(root: &Path) -> PathBuf {
+        let remote = root.join("remote.git");
+        Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        remote
+    }
+
+    #[tokio::test]
+    async fn test_sync_between_machines() {
+        let temp_dir = tempdir().unwrap();
+        let remote = bare_remote(temp_dir.path());
+
+        let home = temp_dir.path().join("laptop/home");
+        let mut laptop = machine(temp_dir.path(), "laptop", &remote).await;
+        track(&mut laptop, &home, ".zshrc", "export EDITOR=nvim").await;
+
+        let report = sync(&laptop, &FileSystemClient, &GitClient).await.unwrap();
+        assert!(report.commit.is_some());
+        assert_eq!(report.pushed, vec![DEFAULT_REMOTE]);
+
+        let subject = Command::new("git")
+            .arg("-C")
+            .arg(&laptop.base_path)
+            .args(["log", "-1", "--format=%s"])
+            .output()
+            .unwrap()
+            .stdout;
+        assert_eq!(
+            String::from_utf8(subject).unwrap().trim(),
+            "nord-theme: add .zshrc (1 file)"
+        );
+
+        // Nothing changed, so nothing is committed.
+        let report = sync(&laptop, &FileSystemClient, &GitClient).await.unwrap();
+        assert_eq!(report.commit, None);
+
+        let desktop = machine(temp_dir.path(), "desktop", &remote).await;
+        assert_eq!(
+            fs::read_to_string(desktop.base_path.join("home/.zshrc")).unwrap(),
+            "export EDITOR=nvim"
+        );
+
+        // Untracking removes the file from the repository.
+        laptop
+            .active_profile_mut()
+            .unwrap()
+            .untrack(&home.join(".zshrc"))
+            .unwrap();
+        sync(&laptop, &FileSystemClient, &GitClient).await.unwrap();
+        assert!(!laptop.base_path.join("home/.zshrc").exists());
+        assert!(home.join(".zshrc").exists());
+
+        // No file has a mode left, so `modes.toml` is removed as well.
+        let files = GitClient.list_files(&laptop.base_path, "nord").unwrap();
+        assert!(!files.contains(&PathBuf::from(MODES_FILE)));
+    }
+
+    #[tokio::test]
+    async fn test_sync_stops_on_conflict() {
+        let temp_dir = tempdir().unwrap();
+        let remote = bare_remote(temp_dir.path());
+
+        let laptop_home = temp_dir.path().join("laptop/home");
+        let mut laptop = machine(temp_dir.path(), "laptop", &remote).await;
+        track(&mut laptop, &laptop_home, ".zshrc", "export EDITOR=nvim").await;
+        sync(&laptop, &FileSystemClient, &GitClient).await.unwrap();
+
+        let desktop_home = temp_dir.path().join("desktop/home");
+        let mut desktop = machine(temp_dir.path(), "desktop", &remote).await;
+        track(&mut desktop, &desktop_home, ".zshrc", "export EDITOR=helix").await;
+        sync(&desktop, &FileSystemClient, &GitClient).await.unwrap();
+
+        track(&mut laptop, &laptop_home, ".zshrc", "export EDITOR=emacs").await;
+        let error = sync(&laptop, &FileSystemClient, &GitClient)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref(),
+            Some(&GitError::MergeConflict(vec![PathBuf::from("home/.zshrc")]))
+        );
+        assert_eq!(
+            fs::read_to_string(laptop.base_path.join("home/.zshrc")).unwrap(),
+            "export EDITOR=emacs"
+        );
+        assert_eq!(
+            GitClient.current_branch(&laptop.base_path).unwrap(),
+            Some("nord".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_without_remote() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            active_profile: "nord-theme".to_string(),
+            ..TomlConfig::default()
+        };
+        config
+            .profiles
+            .insert("nord-theme".to_string(), ProfileConfig::default());
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+        track(&mut config, &home, ".zshrc", "export EDITOR=nvim").await;
+
+        let report = sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        assert!(report.commit.is_some());
+        assert!(report.pushed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_stores_large_files() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            active_profile: "nord-theme".to_string(),
+            ..TomlConfig::default()
+        };
+        config.assets.size_limit = 16;
+        config
+            .profiles
+            .insert("nord-theme".to_string(), ProfileConfig::default());
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+        track(
+            &mut config,
+            &home,
+            "wallpaper.png",
+            "a large wallpaper image",
+        )
+        .await;
+
+        sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+
+        let committed = GitClient
+            .show_file(&config.base_path, "main", Path::new("home/wallpaper.png"))
+            .unwrap()
+            .unwrap();
+        assert!(Pointer::parse(&committed).is_some());
+
+        let reports = status(
+            config.active_profile().unwrap(),
+            &config.base_path,
+            &FileSystemClient,
+            &GitClient,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reports[0].status, FileStatus::Unchanged);
+    }
+
+    #[tokio::test]
+    async fn test_sync_follows_push_policies() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let backup = bare_remote(&temp_dir.path().join("backup"));
+        let showcase = bare_remote(&temp_dir.path().join("showcase"));
+
+        let mut config = TomlConfig {
+            base_path: temp_dir.path().join("dotty"),
+            active_profile: "nord-theme".to_string(),
+            ..TomlConfig::default()
+        };
+        config.profiles.insert(
+            "nord-theme".to_string(),
+            ProfileConfig {
+                remotes: vec![
+                    RemoteConfig {
+                        name: "backup".to_string(),
+                        url: backup.to_str().unwrap().to_string(),
+                        push: PushPolicy::Always,
+                    },
+                    RemoteConfig {
+                        name: "showcase".to_string(),
+                        url: showcase.to_str().unwrap().to_string(),
+                        push: PushPolicy::Manual,
+                    },
+                ],
+                ..ProfileConfig::default()
+            },
+        );
+        init(&config, &FileSystemClient, &GitClient).await.unwrap();
+        track(&mut config, &home, ".zshrc", "export EDITOR=nvim").await;
+
+        let report = sync(&config, &FileSystemClient, &GitClient).await.unwrap();
+        assert_eq!(report.pulled, vec!["backup", "showcase"]);
+        assert_eq!(report.pushed, vec!["backup"]);
+        assert!(GitClient.list_branches(&showcase).unwrap().is_empty());
+
+        assert_eq!(
+            push(&config, &GitClient, Some("showcase")).unwrap(),
+            vec!["showcase"]
+        );
+        assert_eq!(GitClient.list_branches(&showcase).unwrap(), vec!["main"]);
+
+        config.active_profile_mut().unwrap().remotes[1].push = PushPolicy::Never;
+        assert!(push(&config, &GitClient, Some("showcase")).is_err());
+        assert!(push(&config, &GitClient, Some("missing")).is_err());
+        assert_eq!(push(&config, &GitClient, None).unwrap(), vec!["backup"]);
+    }
+}
@@ -0,0 +1,534 @@
+// Standard library imports
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+// External crate imports
+use anstyle::Style;
+use anyhow::Result;
+
+// Local module imports
+use crate::{
+    assets::{self, Pointer},
+    clients::{
+        file_system::FileSystem,
+        git::{Git, GitError},
+    },
+    config::{ProfileConfig, Strategy},
+    repository::sync::remotes,
+    ui::cli::{ERROR, HEADER, LITERAL, PLACEHOLDER},
+};
+
+/// How a file on disk compares with the active profile's branch.
+///
+/// The variants are ordered the way `dotty status` groups them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileStatus {
+    /// The file differs from the version committed on the branch, for example
+    /// because a copy was edited after it was deployed.
+    Modified,
+    /// The file matches the branch, but its mode differs from the one recorded
+    /// in the manifest, for example after a clone.
+    ModeChanged,
+    /// The file should be a symbolic link to the repository, but it points elsewhere.
+    Broken,
+    /// The file should be linked to the repository, but is a separate file, for
+    /// example because an application replaced the link when saving.
+    Unlinked,
+    /// The file is tracked but no longer exists on disk.
+    Missing,
+    /// The file is inside a tracked directory but is not tracked itself.
+    Untracked,
+    /// The branch on the remote has a different version of the file.
+    ChangedUpstream,
+    /// The file matches the version committed on the branch.
+    Unchanged,
+}
+
+impl FileStatus {
+    /// Returns the heading used to group files in the human readable output.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Modified => "Modified locally",
+            Self::ModeChanged => "Permissions changed",
+            Self::Broken => "Broken links",
+            Self::Unlinked => "Not linked to the repository",
+            Self::Missing => "Missing on disk",
+            Self::Untracked => "Untracked in a tracked directory",
+            Self::ChangedUpstream => "Changed upstream",
+            Self::Unchanged => "Unchanged",
+        }
+    }
+
+    /// Returns the stable keyword used by `--porcelain`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Modified => "modified",
+            Self::ModeChanged => "mode",
+            Self::Broken => "broken",
+            Self::Unlinked => "unlinked",
+            Self::Missing => "missing",
+            Self::Untracked => "untracked",
+            Self::ChangedUpstream => "upstream",
+            Self::Unchanged => "unchanged",
+        }
+    }
+
+    /// Returns the colour of the status, taken from the `ui::cli` palette.
+    pub fn style(self) -> Style {
+        match self {
+            Self::Modified | Self::ModeChanged => Style::new().fg_color(HEADER.get_fg_color()),
+            Self::Broken | Self::Unlinked | Self::Missing => ERROR,
+            Self::Untracked => PLACEHOLDER,
+            Self::ChangedUpstream => LITERAL,
+            Self::Unchanged => Style::new().dimmed(),
+        }
+    }
+}
+
+/// The status of a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub source: PathBuf,
+    pub status: FileStatus,
+}
+
+/// Compares every file of `profile` on disk with its branch in `repo`.
+///
+/// Local changes take precedence: a file that is modified on disk is reported
+/// as such even when the remote has changed it too, and so is a file whose
+/// mode differs from the one recorded in the manifest. Files kept in the asset
+/// store are compared by hash. A file deployed as a symbolic link is read
+/// through the link, and reported as broken when the link does not point at
+/// the file in the repository. A file that should be a symbolic or hard link
+/// but is a separate file is reported as unlinked; files kept in the asset
+/// store are always copied, so they never are. Each of the profile's remotes, or `origin`
+/// when it has none, is compared as of the last fetch, so this never touches
+/// the network.
+///
+/// # Arguments
+/// * `profile` - The profile whose files are compared.
+/// * `repo` - The dotfiles repository.
+/// * `fs` - An implementation of `FileSystem` for reading the files on disk.
+/// * `git` - An implementation of `Git` for reading the committed files.
+///
+/// # Returns
+/// Returns one report per file, sorted by status and then by path.
+///
+/// # Errors
+/// This function will return an error if the repository, a tracked directory or
+/// a file cannot be read.
+///
+pub async fn status(
+    profile: &ProfileConfig,
+    repo: &Path,
+    fs: &impl FileSystem,
+    git: &dyn Git,
+) -> Result<Vec<FileReport>> {
+    let branch = &profile.branch;
+    let committed = committed_files(git, repo, branch)?;
+
+    let mut upstream_branches = Vec::new();
+    for (remote, _) in remotes(profile) {
+        if git.list_remote_branches(repo, &remote)?.contains(branch) {
+            upstream_branches.push(format!("{}/{}", remote, branch));
+        }
+    }
+
+    let mut reports = Vec::new();
+
+    for file in &profile.files {
+        let local = match committed.contains(&file.repo_path) {
+            true => git.show_file(repo, branch, &file.repo_path)?,
+            false => None,
+        };
+
+        let mut strategy = profile.strategy_for(file);
+        if local
+            .as_deref()
+            .is_some_and(|local| Pointer::parse(local).is_some())
+        {
+            strategy = Strategy::Copy;
+        }
+
+        let linked = repo.join(&file.repo_path);
+        let is_symlink = fs.is_symlink(&file.source);
+        let status = if strategy == Strategy::Symlink
+            && is_symlink
+            && (!fs.exists(&file.source) || fs.read_link(&file.source).await? != linked)
+        {
+            FileStatus::Broken
+        } else if !fs.exists(&file.source) {
+            FileStatus::Missing
+        } else if match strategy {
+            Strategy::Symlink => !is_symlink,
+            Strategy::Hardlink => is_symlink || !fs.is_same_file(&file.source, &linked),
+            Strategy::Copy => false,
+        } {
+            FileStatus::Unlinked
+        } else {
+            let data = fs.read(&file.source).await?;
+            let metadata = fs.metadata(&file.source).await?;
+            if !local
+                .as_deref()
+                .is_some_and(|local| assets::matches(local, &data))
+            {
+                FileStatus::Modified
+            } else if file.mode.is_some_and(|mode| mode != metadata.mode) {
+                FileStatus::ModeChanged
+            } else if changed_upstream(git, repo, &upstream_branches, &file.repo_path, &local)? {
+                FileStatus::ChangedUpstream
+            } else {
+                FileStatus::Unchanged
+            }
+        };
+
+        reports.push(FileReport {
+            source: file.source.clone(),
+            status,
+        });
+    }
+
+    for dir in &profile.directories {
+        for source in untracked_files(profile, fs, dir).await? {
+            reports.push(FileReport {
+                source,
+                status: FileStatus::Untracked,
+            });
+        }
+    }
+
+    reports.sort_by(|a, b| (a.status, &a.source).cmp(&(b.status, &b.source)));
+    Ok(reports)
+}
+
+/// Returns whether any of `upstream_branches` has a different version of `path` than `local`.
+fn changed_upstream(
+    git: &dyn Git,
+    repo: &Path,
+    upstream_branches: &[String],
+    path: &Path,
+    local: &Option<Vec<u8>>,
+) -> Result<bool> {
+    for upstream in upstream_branches {
+        if git.show_file(repo, upstream, path)? != *local {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns the files committed on `branch`, which is empty while the branch is unborn.
+fn committed_files(git: &dyn Git, repo: &Path, branch: &str) -> Result<HashSet<PathBuf>> {
+    match git.list_files(repo, branch) {
+        Ok(files) => Ok(files.into_iter().collect()),
+        Err(error) if matches!(error.downcast_ref(), Some(GitError::BranchNotFound(_))) => {
+            Ok(HashSet::new())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+async fn untracked_files(
+    profile: &ProfileConfig,
+    fs: &impl FileSystem,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    if !fs.is_dir(dir) {
+        return Ok(Vec::new());
+    }
+
+    let mut pending = vec![dir.to_path_buf()];
+    let mut untracked = Vec::new();
+
+    while let Some(path) = pending.pop() {
+        if fs.is_dir(&path) {
+            pending.extend(fs.read_dir(&path).await?);
+        } else if !profile.is_tracked(&path) {
+            untracked.push(path);
+        }
+    }
+
+    Ok(untracked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::{
+        file_system::FileSystemClient,
+        git::{GitClient, DEFAULT_REMOTE},
+    };
+    use crate::config::{PushPolicy, RemoteConfig};
+    use crate::mappings::Mappings;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_status() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+        let nvim = home.join(".config/nvim");
+
+        fs::create_dir_all(&nvim).unwrap();
+        for (name, contents) in [
+            (".zshrc", "export EDITOR=nvim"),
+            (".bashrc", "set -o vi"),
+            (".tmux.conf", "set -g mouse on"),
+            (".config/nvim/init.lua", "vim.opt.number = true"),
+        ] {
+            fs::write(home.join(name), contents).unwrap();
+        }
+
+        let mut profile = ProfileConfig {
+            strategy: Strategy::Copy,
+            ..ProfileConfig::default()
+        };
+        for path in [
+            home.join(".zshrc"),
+            home.join(".bashrc"),
+            home.join(".tmux.conf"),
+            nvim.clone(),
+        ] {
+            profile
+                .track(&FileSystemClient, &path, &home, &Mappings::default())
+                .await
+                .unwrap();
+        }
+
+        // Commit everything as it is on disk.
+        GitClient.init(&repo, "main").unwrap();
+        for file in &profile.files {
+            let target = repo.join(&file.repo_path);
+            fs::create_dir_all(target.parent().unwrap()).unwrap();
+            fs::copy(&file.source, target).unwrap();
+        }
+        let paths: Vec<_> = profile
+            .files
+            .iter()
+            .map(|file| file.repo_path.clone())
+            .collect();
+        GitClient.add(&repo, &paths).unwrap();
+        GitClient.commit(&repo, "Track dotfiles").unwrap();
+
+        fs::write(home.join(".zshrc"), "export EDITOR=helix").unwrap();
+        fs::remove_file(home.join(".bashrc")).unwrap();
+        fs::write(nvim.join("lazy.lua"), "return {}").unwrap();
+
+        // `.tmux.conf` should only be readable by its owner.
+        let tmux = home.join(".tmux.conf");
+        FileSystemClient
+            .set_permissions(&tmux, 0o640)
+            .await
+            .unwrap();
+        for file in &mut profile.files {
+            if file.source == tmux {
+                file.mode = Some(0o600);
+            }
+        }
+
+        let reports = status(&profile, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+        let statuses: Vec<_> = reports
+            .iter()
+            .map(|report| (report.status, report.source.strip_prefix(&home).unwrap()))
+            .collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (FileStatus::Modified, Path::new(".zshrc")),
+                (FileStatus::ModeChanged, Path::new(".tmux.conf")),
+                (FileStatus::Missing, Path::new(".bashrc")),
+                (FileStatus::Untracked, Path::new(".config/nvim/lazy.lua")),
+                (FileStatus::Unchanged, Path::new(".config/nvim/init.lua")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_changed_upstream() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+        let other = temp_dir.path().join("other");
+        let remote = temp_dir.path().join("remote.git");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".zshrc"), "export EDITOR=nvim").unwrap();
+        let mut profile = ProfileConfig {
+            strategy: Strategy::Copy,
+            ..ProfileConfig::default()
+        };
+        profile
+            .track(
+                &FileSystemClient,
+                &home.join(".zshrc"),
+                &home,
+                &Mappings::default(),
+            )
+            .await
+            .unwrap();
+
+        std::process::Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        let url = remote.to_str().unwrap();
+
+        GitClient.clone_repository(url, &repo).unwrap();
+        fs::create_dir_all(repo.join("home")).unwrap();
+        fs::copy(home.join(".zshrc"), repo.join("home/.zshrc")).unwrap();
+        GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+        GitClient.commit(&repo, "Track zshrc").unwrap();
+        GitClient.push(&repo, DEFAULT_REMOTE, "main").unwrap();
+
+        GitClient.clone_repository(url, &other).unwrap();
+        GitClient.checkout(&other, "main").unwrap();
+        fs::write(other.join("home/.zshrc"), "export EDITOR=helix").unwrap();
+        GitClient.add(&other, &[PathBuf::from("home")]).unwrap();
+        GitClient.commit(&other, "Switch to helix").unwrap();
+        GitClient.push(&other, DEFAULT_REMOTE, "main").unwrap();
+
+        // Only the profile's remotes are compared, whatever they are called.
+        GitClient.set_remote(&repo, "backup", url).unwrap();
+        GitClient.fetch(&repo, "backup").unwrap();
+        let backup = RemoteConfig {
+            name: "backup".to_string(),
+            url: url.to_string(),
+            push: PushPolicy::Always,
+        };
+        let with_backup = ProfileConfig {
+            remotes: vec![backup],
+            ..profile.clone()
+        };
+        let reports = status(&with_backup, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+        assert_eq!(reports[0].status, FileStatus::ChangedUpstream);
+
+        // Without remotes, `origin` is used, which has not been fetched yet.
+        let reports = status(&profile, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+        assert_eq!(reports[0].status, FileStatus::Unchanged);
+
+        GitClient.fetch(&repo, DEFAULT_REMOTE).unwrap();
+        let reports = status(&profile, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+        assert_eq!(reports[0].status, FileStatus::ChangedUpstream);
+    }
+
+    #[tokio::test]
+    async fn test_status_before_first_commit() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".zshrc"), "export EDITOR=nvim").unwrap();
+        GitClient.init(&repo, "main").unwrap();
+
+        let mut profile = ProfileConfig {
+            strategy: Strategy::Copy,
+            ..ProfileConfig::default()
+        };
+        profile
+            .track(
+                &FileSystemClient,
+                &home.join(".zshrc"),
+                &home,
+                &Mappings::default(),
+            )
+            .await
+            .unwrap();
+
+        let reports = status(&profile, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+
+        assert_eq!(reports[0].status, FileStatus::Modified);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_status_strategies() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let repo = temp_dir.path().join("repo");
+
+        fs::create_dir_all(repo.join("home")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        let mut profile = ProfileConfig::default();
+        for (name, contents) in [
+            (".zshrc", "export EDITOR=nvim"),
+            (".bashrc", "set -o vi"),
+            (".vimrc", "set number"),
+            (".inputrc", "set editing-mode vi"),
+            (".gitconfig", "[user]"),
+            (".profile", "umask 022"),
+        ] {
+            fs::write(home.join(name), contents).unwrap();
+            fs::write(repo.join("home").join(name), contents).unwrap();
+            profile
+                .track(
+                    &FileSystemClient,
+                    &home.join(name),
+                    &home,
+                    &Mappings::default(),
+                )
+                .await
+                .unwrap();
+        }
+        profile.files[2].strategy = Some(Strategy::Copy);
+        profile.files[4].strategy = Some(Strategy::Hardlink);
+        profile.files[5].strategy = Some(Strategy::Hardlink);
+
+        GitClient.init(&repo, "main").unwrap();
+        GitClient.add(&repo, &[PathBuf::from("home")]).unwrap();
+        GitClient.commit(&repo, "Track dotfiles").unwrap();
+
+        // A link to the repository is read through, one pointing elsewhere is broken.
+        let elsewhere = temp_dir.path().join("zshrc");
+        fs::write(&elsewhere, "export EDITOR=nvim").unwrap();
+        for (name, target) in [
+            (".zshrc", elsewhere),
+            (".bashrc", repo.join("home/.bashrc")),
+        ] {
+            fs::remove_file(home.join(name)).unwrap();
+            std::os::unix::fs::symlink(target, home.join(name)).unwrap();
+        }
+        fs::write(home.join(".vimrc"), "set relativenumber").unwrap();
+
+        // A separate file where a link belongs is unlinked, even with the same contents.
+        fs::remove_file(home.join(".profile")).unwrap();
+        fs::hard_link(repo.join("home/.profile"), home.join(".profile")).unwrap();
+
+        let reports = status(&profile, &repo, &FileSystemClient, &GitClient)
+            .await
+            .unwrap();
+        let statuses: Vec<_> = reports.iter().map(|report| report.status).collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                FileStatus::Modified,
+                FileStatus::Broken,
+                FileStatus::Unlinked,
+                FileStatus::Unlinked,
+                FileStatus::Unchanged,
+                FileStatus::Unchanged
+            ]
+        );
+        assert_eq!(reports[0].source, home.join(".vimrc"));
+        assert_eq!(reports[1].source, home.join(".zshrc"));
+        assert_eq!(reports[2].source, home.join(".gitconfig"));
+        assert_eq!(reports[3].source, home.join(".inputrc"));
+    }
+}
@@ -1,12 +1,36 @@
+// Standard library imports
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
 // External crate imports
 use anstyle::{AnsiColor, Color::Ansi, Style};
 use clap::builder::Styles as ClapStyles;
 
+/// Headers and usage: bold, underlined, yellow text.
+pub const HEADER: Style = Style::new()
+    .bold()
+    .underline()
+    .fg_color(Some(Ansi(AnsiColor::Yellow)));
+/// Literals: green text.
+pub const LITERAL: Style = Style::new().fg_color(Some(Ansi(AnsiColor::Green)));
+/// Invalid input and errors: bold, red text.
+pub const ERROR: Style = Style::new().bold().fg_color(Some(Ansi(AnsiColor::Red)));
+/// Valid input: bold, underlined, green text.
+pub const VALID: Style = Style::new()
+    .bold()
+    .underline()
+    .fg_color(Some(Ansi(AnsiColor::Green)));
+/// Placeholders: white text.
+pub const PLACEHOLDER: Style = Style::new().fg_color(Some(Ansi(AnsiColor::White)));
+
 /// Defines custom styles for the Clap CLI interface.
 ///
 /// This function configures and returns a `ClapStyles` object with custom
 /// styling for various elements of the command-line interface. It uses ANSI
 /// color codes to enhance the visual appearance and readability of the CLI.
+/// The same palette is exported as constants so that command output can match it.
 ///
 /// # Styling Details
 ///
@@ -22,26 +46,25 @@ use clap::builder::Styles as ClapStyles;
 ///
 pub fn style() -> ClapStyles {
     ClapStyles::styled()
-        .usage(
-            Style::new()
-                .bold()
-                .underline()
-                .fg_color(Some(Ansi(AnsiColor::Yellow))),
-        )
-        .header(
-            Style::new()
-                .bold()
-                .underline()
-                .fg_color(Some(Ansi(AnsiColor::Yellow))),
-        )
-        .literal(Style::new().fg_color(Some(Ansi(AnsiColor::Green))))
-        .invalid(Style::new().bold().fg_color(Some(Ansi(AnsiColor::Red))))
-        .error(Style::new().bold().fg_color(Some(Ansi(AnsiColor::Red))))
-        .valid(
-            Style::new()
-                .bold()
-                .underline()
-                .fg_color(Some(Ansi(AnsiColor::Green))),
-        )
-        .placeholder(Style::new().fg_color(Some(Ansi(AnsiColor::White))))
+        .usage(HEADER)
+        .header(HEADER)
+        .literal(LITERAL)
+        .invalid(ERROR)
+        .error(ERROR)
+        .valid(VALID)
+        .placeholder(PLACEHOLDER)
+}
+
+/// Returns whether output should be coloured: stdout is a terminal and `NO_COLOR` is not set.
+pub fn use_colour() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// Wraps `text` in `style`, or returns it unchanged when `enabled` is false.
+pub fn paint(style: Style, text: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}{}", style.render(), text, style.render_reset())
+    } else {
+        text.to_string()
+    }
 }